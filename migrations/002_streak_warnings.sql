@@ -0,0 +1,2 @@
+ALTER TABLE users ADD COLUMN utc_offset INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE users ADD COLUMN streak_warned_on TEXT;
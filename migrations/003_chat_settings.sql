@@ -0,0 +1,4 @@
+CREATE TABLE IF NOT EXISTS chat_settings (
+	chat_id INTEGER PRIMARY KEY,
+	anonymous_leaderboard INTEGER NOT NULL DEFAULT 0
+);
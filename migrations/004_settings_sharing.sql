@@ -0,0 +1,2 @@
+ALTER TABLE users ADD COLUMN username TEXT;
+ALTER TABLE users ADD COLUMN share_settings INTEGER NOT NULL DEFAULT 0;
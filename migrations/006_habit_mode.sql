@@ -0,0 +1 @@
+ALTER TABLE chat_settings ADD COLUMN habit_mode INTEGER NOT NULL DEFAULT 0;
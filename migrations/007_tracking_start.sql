@@ -0,0 +1,2 @@
+-- Logs before this timestamp are kept but excluded from the user's stats
+ALTER TABLE users ADD COLUMN tracking_start INTEGER;
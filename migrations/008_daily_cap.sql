@@ -0,0 +1,2 @@
+-- Maximum number of logs per user per day counted in this chat, NULL for unlimited
+ALTER TABLE chat_settings ADD COLUMN daily_cap INTEGER;
@@ -0,0 +1,2 @@
+ALTER TABLE logs ADD COLUMN category TEXT;
+CREATE INDEX IF NOT EXISTS logs_category ON logs (category);
@@ -0,0 +1,2 @@
+-- Only logs sent at or after this timestamp count in this chat, NULL for all time
+ALTER TABLE chat_settings ADD COLUMN season_start INTEGER;
@@ -0,0 +1,9 @@
+-- Users notified about another user's milestones, once that user approves
+CREATE TABLE IF NOT EXISTS watchers (
+	watcher_id INTEGER NOT NULL,
+	watched_id INTEGER NOT NULL,
+	approved INTEGER NOT NULL DEFAULT 0,
+	PRIMARY KEY(watcher_id, watched_id),
+	FOREIGN KEY(watcher_id) REFERENCES users(id),
+	FOREIGN KEY(watched_id) REFERENCES users(id)
+);
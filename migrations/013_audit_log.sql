@@ -0,0 +1,8 @@
+-- Privileged actions, recorded if AUDIT_LOG is enabled
+CREATE TABLE IF NOT EXISTS audit_log (
+	id INTEGER PRIMARY KEY AUTOINCREMENT,
+	actor_telegram_id INTEGER NOT NULL,
+	action TEXT NOT NULL,
+	target TEXT,
+	timestamp INTEGER NOT NULL
+);
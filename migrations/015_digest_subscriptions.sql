@@ -0,0 +1,7 @@
+-- Users who get a weekly summary in a private message
+CREATE TABLE IF NOT EXISTS digest_subscriptions (
+	user_id INTEGER PRIMARY KEY,
+	-- Local date of the Monday of the last week the digest was sent for
+	sent_for_week TEXT NOT NULL,
+	FOREIGN KEY(user_id) REFERENCES users(id)
+);
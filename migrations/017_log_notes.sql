@@ -0,0 +1,2 @@
+-- Free-form text the user attached to the log
+ALTER TABLE logs ADD COLUMN note TEXT;
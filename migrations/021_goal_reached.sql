@@ -0,0 +1,3 @@
+-- Start of the last week/month the user was congratulated for reaching their goal
+ALTER TABLE users ADD COLUMN weekly_goal_reached INTEGER;
+ALTER TABLE users ADD COLUMN monthly_goal_reached INTEGER;
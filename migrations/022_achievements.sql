@@ -0,0 +1,8 @@
+-- Badges earned by users, see src/achievements.rs for the identifiers
+CREATE TABLE IF NOT EXISTS achievements (
+	user_id INTEGER NOT NULL,
+	achievement TEXT NOT NULL,
+	earned_at INTEGER NOT NULL,
+	PRIMARY KEY(user_id, achievement),
+	FOREIGN KEY(user_id) REFERENCES users(id)
+);
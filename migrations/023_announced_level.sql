@@ -0,0 +1,2 @@
+-- Highest level the user was congratulated for, so level-ups are announced once
+ALTER TABLE users ADD COLUMN announced_level INTEGER NOT NULL DEFAULT 1;
@@ -0,0 +1,2 @@
+-- Minutes after local midnight at which the user's day ends, logs before it count for the day before
+ALTER TABLE users ADD COLUMN day_end INTEGER NOT NULL DEFAULT 0;
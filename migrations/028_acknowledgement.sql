@@ -0,0 +1,2 @@
+-- How new logs are acknowledged in the chat: 'reaction' or 'silent', NULL for a reply
+ALTER TABLE chat_settings ADD COLUMN acknowledgement TEXT;
@@ -0,0 +1,2 @@
+-- The user's own replies to /done, phrases separated by '|', NULL for the default one
+ALTER TABLE users ADD COLUMN ack_messages TEXT;
@@ -0,0 +1,3 @@
+-- Names shown for users without a username
+ALTER TABLE users ADD COLUMN first_name TEXT;
+ALTER TABLE users ADD COLUMN last_name TEXT;
@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS users (
+	id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+	telegram_id BIGINT NOT NULL UNIQUE
+);
+
+CREATE TABLE IF NOT EXISTS logs (
+	id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+	user_id BIGINT NOT NULL,
+	timestamp BIGINT NOT NULL,
+	FOREIGN KEY(user_id) REFERENCES users(id)
+);
+
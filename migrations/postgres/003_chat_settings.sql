@@ -0,0 +1,4 @@
+CREATE TABLE IF NOT EXISTS chat_settings (
+	chat_id BIGINT PRIMARY KEY,
+	anonymous_leaderboard BOOLEAN NOT NULL DEFAULT FALSE
+);
@@ -0,0 +1 @@
+ALTER TABLE users ADD COLUMN weekly_goal BIGINT;
@@ -0,0 +1 @@
+ALTER TABLE chat_settings ADD COLUMN habit_mode BOOLEAN NOT NULL DEFAULT FALSE;
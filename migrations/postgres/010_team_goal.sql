@@ -0,0 +1,6 @@
+-- The chat the log was sent in
+ALTER TABLE logs ADD COLUMN chat_id BIGINT;
+CREATE INDEX IF NOT EXISTS logs_chat_id_timestamp ON logs (chat_id, timestamp);
+
+-- Monthly number of logs the chat members aim for together
+ALTER TABLE chat_settings ADD COLUMN team_goal BIGINT;
@@ -0,0 +1,8 @@
+-- Privileged actions, recorded if AUDIT_LOG is enabled
+CREATE TABLE IF NOT EXISTS audit_log (
+	id BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+	actor_telegram_id BIGINT NOT NULL,
+	action TEXT NOT NULL,
+	target TEXT,
+	timestamp BIGINT NOT NULL
+);
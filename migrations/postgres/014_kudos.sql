@@ -0,0 +1,3 @@
+-- The user who logged this entry for someone else as kudos, NULL for own logs
+ALTER TABLE logs ADD COLUMN granted_by BIGINT REFERENCES users(id);
+CREATE INDEX IF NOT EXISTS logs_granted_by_timestamp ON logs (granted_by, timestamp);
@@ -0,0 +1,9 @@
+-- Daily reminders sent if the user hasn't logged by a local time of day
+CREATE TABLE IF NOT EXISTS reminders (
+	user_id BIGINT PRIMARY KEY,
+	-- Minutes after local midnight
+	minute BIGINT NOT NULL,
+	-- Local date of the last day the reminder was handled
+	reminded_on TEXT,
+	FOREIGN KEY(user_id) REFERENCES users(id)
+);
@@ -0,0 +1,2 @@
+-- Whether others may put their charts next to this user's with /compare
+ALTER TABLE users ADD COLUMN allow_compare BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,2 @@
+-- Name of the chart theme the user picked, NULL for the deployment's default
+ALTER TABLE users ADD COLUMN chart_theme TEXT;
@@ -0,0 +1,2 @@
+-- Number of logs the user aims for per calendar month
+ALTER TABLE users ADD COLUMN monthly_goal BIGINT;
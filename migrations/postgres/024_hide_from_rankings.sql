@@ -0,0 +1,2 @@
+-- Whether the user is left out of leaderboards, streak rankings and comparisons
+ALTER TABLE users ADD COLUMN hide_from_rankings BOOLEAN NOT NULL DEFAULT FALSE;
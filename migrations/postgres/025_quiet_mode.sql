@@ -0,0 +1,2 @@
+-- Whether goals, achievements and level-ups are recorded without announcing them
+ALTER TABLE users ADD COLUMN quiet BOOLEAN NOT NULL DEFAULT FALSE;
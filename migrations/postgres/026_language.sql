@@ -0,0 +1,2 @@
+-- Language code of the replies the user picked, NULL to follow their Telegram app
+ALTER TABLE users ADD COLUMN language TEXT;
@@ -0,0 +1,2 @@
+-- Whether messages like "done" or "+1" in a private chat log like /done
+ALTER TABLE users ADD COLUMN plain_logging BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,2 @@
+-- Telegram's file ID of the user's latest /card, shared from inline queries
+ALTER TABLE users ADD COLUMN card_file_id TEXT;
@@ -0,0 +1,5 @@
+-- When kudos were given to the second, as `timestamp` may be rounded down, for the daily limit
+ALTER TABLE logs ADD COLUMN granted_at BIGINT;
+UPDATE logs SET granted_at = timestamp WHERE granted_by IS NOT NULL;
+DROP INDEX IF EXISTS logs_granted_by_timestamp;
+CREATE INDEX IF NOT EXISTS logs_granted_by_granted_at ON logs (granted_by, granted_at);
@@ -0,0 +1,48 @@
+/// A badge earned by reaching a number of logs.
+pub struct Achievement {
+    /// Stable identifier stored in the database, never change it.
+    pub id: &'static str,
+    pub icon: &'static str,
+    pub name: &'static str,
+    /// Number of logs needed to earn it.
+    pub logs: i64,
+}
+
+/// Every achievement, by increasing number of logs.
+pub const ACHIEVEMENTS: &[Achievement] = &[
+    Achievement {
+        id: "logs_10",
+        icon: "🌱",
+        name: "First steps",
+        logs: 10,
+    },
+    Achievement {
+        id: "logs_100",
+        icon: "💯",
+        name: "Centurion",
+        logs: 100,
+    },
+    Achievement {
+        id: "logs_500",
+        icon: "⭐",
+        name: "Dedicated",
+        logs: 500,
+    },
+    Achievement {
+        id: "logs_1000",
+        icon: "💎",
+        name: "Thousand club",
+        logs: 1000,
+    },
+    Achievement {
+        id: "logs_5000",
+        icon: "👑",
+        name: "Legend",
+        logs: 5000,
+    },
+];
+
+/// The achievements a user with `total` logs has earned.
+pub fn earned(total: i64) -> impl Iterator<Item = &'static Achievement> {
+    ACHIEVEMENTS.iter().filter(move |a| total >= a.logs)
+}
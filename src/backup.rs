@@ -0,0 +1,59 @@
+use std::{fs, path::Path, time::Duration};
+
+use chrono::Utc;
+use tracing::{error, info};
+
+use crate::database::Database;
+
+const BACKUP_PREFIX: &str = "backup-";
+const BACKUP_EXTENSION: &str = ".db";
+
+/// Copies the database into `dir` every `interval`, keeping the newest `retention` backups.
+pub async fn run_backups(
+    db: Database,
+    dir: impl AsRef<Path>,
+    interval: Duration,
+    retention: usize,
+) {
+    let dir = dir.as_ref();
+    let mut interval = tokio::time::interval(interval);
+    loop {
+        interval.tick().await;
+        if let Err(err) = backup(&db, dir, retention).await {
+            error!("Failed to back up the database to {}: {err}", dir.display());
+        }
+    }
+}
+
+async fn backup(db: &Database, dir: &Path, retention: usize) -> anyhow::Result<()> {
+    fs::create_dir_all(dir)?;
+    let name = format!(
+        "{BACKUP_PREFIX}{}{BACKUP_EXTENSION}",
+        Utc::now().format("%Y%m%d-%H%M%S")
+    );
+    let path = dir.join(name);
+    db.backup_to(&path).await?;
+    let size = fs::metadata(&path)?.len();
+    info!(
+        "Backed up the database to {} ({size} bytes)",
+        path.display()
+    );
+
+    // The names sort chronologically
+    let mut backups: Vec<_> = fs::read_dir(dir)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with(BACKUP_PREFIX) && n.ends_with(BACKUP_EXTENSION))
+        })
+        .collect();
+    backups.sort();
+    let excess = backups.len().saturating_sub(retention);
+    for old in &backups[..excess] {
+        fs::remove_file(old)?;
+        info!("Removed the old backup {}", old.display());
+    }
+    Ok(())
+}
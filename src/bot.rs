@@ -1,14 +1,64 @@
-use futures::future::join_all;
+use std::{
+    io::Cursor,
+    net::SocketAddr,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use chrono::{
+    DateTime, Datelike, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Timelike, Utc,
+};
+use futures::future::{try_join, try_join3};
+use image::ImageReader;
 use teloxide::{
+    dispatching::dialogue::{Dialogue, InMemStorage},
+    payloads,
     prelude::*,
-    types::{InputFile, KeyboardButton, KeyboardMarkup, ReplyMarkup},
-    utils::command::BotCommands,
+    requests::{JsonRequest, MultipartRequest},
+    types::ThreadId,
+    types::{
+        BotCommand, BotCommandScope, Chat, FileId, InlineKeyboardButton, InlineKeyboardMarkup,
+        InlineQueryResult, InlineQueryResultArticle, InlineQueryResultCachedPhoto, InputFile,
+        InputMedia, InputMediaDocument, InputMediaPhoto, InputMessageContent,
+        InputMessageContentText, KeyboardButton, KeyboardMarkup, MessageEntityKind, ParseMode,
+        ReactionType, Recipient, ReplyMarkup, User,
+    },
+    update_listeners::webhooks,
+    utils::{command::BotCommands, html},
 };
-use tracing::{debug, error};
+use tracing::{debug, error, info, warn};
+use url::Url;
 
 use crate::{
-    chart::{generate_personal_annual_chart, generate_personal_hourly_chart},
-    database::Database,
+    achievements::{self, ACHIEVEMENTS},
+    cache::{ChartCache, ChartKey},
+    chart::{
+        ChartTheme, ProfileData, generate_daily_distribution_chart, generate_heatmap_chart,
+        generate_month_chart, generate_personal_annual_chart, generate_personal_hourly_chart,
+        generate_profile_card, generate_streak_comparison_chart, generate_timeline_chart,
+        generate_user_comparison_chart, generate_weekday_chart, generate_year_comparison_chart,
+        generate_year_over_year_pace_chart, render, send_as_document,
+    },
+    config::Config,
+    cooldown::Cooldowns,
+    database::{Database, Kudos, LogEntry, RankedTotal, UserName, UserSettings},
+    export::export_csv,
+    import::{fetch_csv, fetch_document, is_json, parse_json_timestamps, parse_timestamps},
+    levels,
+    locale::{Language, fill},
+    names::DisplayName,
+    pending::{PendingLog, PendingLogs},
+    plain,
+    snapshot::build_snapshot,
+    stats::{
+        ActivityTrend, best_day, classify_trend, current_streak, current_streaks,
+        dates_from_day_numbers, day_bounds, day_offset, is_milestone, local_dates, local_midnight,
+        local_monday, longest_streak, month_start, months_bounds, percentile, top_current_streak,
+        user_offset, utc_month_bounds, week_start,
+    },
 };
 
 #[derive(BotCommands, Clone)]
@@ -16,219 +66,5684 @@ use crate::{
 enum Command {
     #[command(description = "Start the bot")]
     Start,
-    #[command(description = "Log when you're done")]
-    Done,
+    #[command(description = "Refresh the keyboard")]
+    Keyboard,
+    #[command(
+        description = "Open a menu with buttons for your stats, charts, leaderboard and settings"
+    )]
+    Menu,
+    #[command(
+        description = "Log when you're done, optionally with a category and a note: /done gym leg day"
+    )]
+    Done(String),
+    #[command(
+        description = "Log for an earlier time in your timezone: /log yesterday 14:30 or /log 2024-03-01"
+    )]
+    Log(String),
+    #[command(description = "Remove your last log")]
+    Undo,
+    #[command(description = "Log for someone else by replying to their message")]
+    Kudos,
     #[command(description = "Show your stats")]
     Stats,
-    #[command(description = "Show your annual stats")]
-    AnnualStats,
-    #[command(description = "Show your hourly stats")]
-    HourlyStats,
-    #[command(description = "Show the leaderboard")]
-    Leaderboard,
+    #[command(description = "Get some motivation based on your recent activity")]
+    Motivation,
+    #[command(description = "Browse your logs with their notes")]
+    History,
+    #[command(description = "Count your logs in a period: /statssince 2024-01-01 [2024-03-31]")]
+    StatsSince(String),
+    #[command(
+        description = "Show your annual stats, optionally for a year and a category: /annualstats 2023 gym"
+    )]
+    AnnualStats(String),
+    #[command(description = "Show your hourly stats, optionally for a category: /hourlystats gym")]
+    HourlyStats(String),
+    #[command(description = "Show every log this year as a dot by date and time")]
+    Timeline,
+    #[command(description = "Show this year's logs per day as a calendar")]
+    Heatmap,
+    #[command(description = "Show your logs per day of a month: /monthstats [2024-06]")]
+    MonthStats(String),
+    #[command(description = "Show your annual, hourly and day of the week charts at once")]
+    Dashboard,
+    #[command(description = "Show your logs per day of the week")]
+    WeekdayStats,
+    #[command(description = "Compare this year's logs per month with last year")]
+    CompareYears,
+    #[command(description = "Compare this year's logs per month with someone: /compare @user")]
+    Compare(String),
+    #[command(description = "Compare this year's pace with last year")]
+    PaceYoy,
+    #[command(description = "Show how many logs your days usually have")]
+    Distribution,
+    #[command(description = "Compare your current streak with your best one")]
+    StreakBars,
+    #[command(description = "Show all your numbers on one card")]
+    Card,
+    #[command(description = "List the badges you earned and the ones still locked")]
+    Achievements,
+    #[command(
+        description = "Show the leaderboard, optionally for a category or period: /leaderboard gym|day|week|month|alltime"
+    )]
+    Leaderboard(String),
+    #[command(description = "Show your position on the leaderboard")]
+    Rank,
+    #[command(description = "Rank everyone by their current streak")]
+    Streaks,
+    #[command(description = "Show a one-line summary to pin")]
+    CompactStats,
+    #[command(description = "Show who logged the most on a day: /leaderboardday 2024-06-01")]
+    LeaderboardDay(String),
+    #[command(description = "List the categories used in logs")]
+    Categories,
+    #[command(description = "Hide names on this chat's leaderboard: /anonymousleaderboard on|off")]
+    AnonymousLeaderboard(String),
+    #[command(description = "Count only the first log of each day: /habitmode on|off")]
+    HabitMode(String),
+    #[command(
+        description = "Acknowledge logs in this chat with a reply, a reaction or silently: /ack reply|reaction|silent"
+    )]
+    Ack(String),
+    #[command(description = "Count at most N logs per day in this chat: /dailycap 5|off")]
+    DailyCap(String),
+    #[command(description = "Start a new season, counting only logs from now on in this chat")]
+    NewSeason,
+    #[command(description = "Set a monthly goal for the whole chat: /teamgoal 1000|off")]
+    TeamGoal(String),
+    #[command(description = "Show the chat's progress toward the team goal")]
+    TeamProgress,
+    #[command(description = "Set a goal: /goal 5 per week or /goal 20 per month (0 to remove)")]
+    Goal(String),
+    #[command(description = "Set a weekly goal: /setweeklygoal 5 (0 to remove)")]
+    SetWeeklyGoal(String),
+    #[command(
+        description = "Ignore logs before a date in your stats (they are kept): /startdate 2024-01-01 or /startdate off"
+    )]
+    StartDate(String),
+    #[command(
+        description = "Pick the colors of your charts: /theme dark|light|colorblind|highcontrast|default"
+    )]
+    Theme(String),
+    #[command(description = "Set your UTC offset, e.g. /timezone +3 or /timezone -5:30")]
+    Timezone(String),
+    #[command(description = "Redraw your charts and reminders after changing your timezone")]
+    FixTimezone,
+    #[command(description = "Let your day end after midnight, e.g. /dayend 04:00, or /dayend off")]
+    DayEnd(String),
+    #[command(
+        description = "Log by sending done, +1 or ✅ to the bot in a private chat: /plainlog on|off"
+    )]
+    PlainLog(String),
+    #[command(
+        description = "Set your reply to /done, phrases separated by | taking turns, {today} for today's count: /ackmessage 🔥 {today}|off"
+    )]
+    AckMessage(String),
+    #[command(description = "Let others compare their charts with yours: /allowcompare on|off")]
+    AllowCompare(String),
+    #[command(
+        description = "Hide yourself from leaderboards, streak rankings and comparisons: /privacy on|off"
+    )]
+    Privacy(String),
+    #[command(
+        description = "Don't announce reached goals, achievements and level-ups: /quiet on|off"
+    )]
+    Quiet(String),
+    #[command(description = "Pick the language of the replies: /language en|de|auto")]
+    Language(String),
+    #[command(description = "View and change your settings")]
+    Settings,
+    #[command(description = "Let others copy your settings: /sharesettings on|off")]
+    ShareSettings(String),
+    #[command(description = "Copy someone's settings: /copysettings @user")]
+    CopySettings(String),
+    #[command(description = "Get reminded if you haven't logged by a time: /remind 20:00|off")]
+    Remind(String),
+    #[command(description = "Get a weekly summary in a private message: /digest on|off")]
+    Digest(String),
+    #[command(description = "Get notified about someone's milestones: /watch @user")]
+    Watch(String),
+    #[command(description = "Stop watching someone's milestones: /unwatch @user")]
+    Unwatch(String),
+    #[command(description = "Admin: vacuum and analyze the database")]
+    Maintenance,
+    #[command(description = "Admin: pause the bot for everyone else: /maintenancemode on|off")]
+    MaintenanceMode(String),
+    #[command(description = "Admin: show percentiles of the number of logs per user")]
+    Percentiles,
+    #[command(description = "Admin: show the latest privileged actions")]
+    Audit,
+    #[command(description = "Admin: export aggregated stats as JSON")]
+    Snapshot,
+    #[command(description = "Admin: check the database, charts and Telegram API")]
+    Diagnose,
+    #[command(description = "Admin: show the database schema and applied migrations")]
+    DumpSchema,
+    #[command(description = "Admin: remove duplicate logs left by redelivered updates")]
+    MergeDuplicates,
+    #[command(description = "Import logs from a CSV file: /importurl https://example.com/logs.csv")]
+    ImportUrl(String),
+    #[command(description = "Import logs by replying to a CSV or JSON file with /import")]
+    Import,
+    #[command(description = "Download all your logs as a CSV file")]
+    Export,
+    #[command(description = "Delete all of today's logs")]
+    ClearToday,
     #[command(description = "Delete all your data")]
     Delete,
 }
 
-fn main_keyboard() -> ReplyMarkup {
-    let keyboard = KeyboardMarkup::new(vec![
-        vec![KeyboardButton::new("/done")],
-        vec![
-            KeyboardButton::new("/stats"),
-            KeyboardButton::new("/leaderboard"),
-        ],
-        vec![
-            KeyboardButton::new("/annualstats"),
-            KeyboardButton::new("/hourlystats"),
+impl Command {
+    /// Name under which expensive commands are rate limited, `None` for cheap ones.
+    fn cooldown_key(&self) -> Option<&'static str> {
+        match self {
+            Self::AnnualStats(_) => Some("annualstats"),
+            Self::HourlyStats(_) => Some("hourlystats"),
+            Self::Timeline => Some("timeline"),
+            Self::Heatmap => Some("heatmap"),
+            Self::MonthStats(_) => Some("monthstats"),
+            Self::WeekdayStats => Some("weekdaystats"),
+            Self::Dashboard => Some("dashboard"),
+            Self::CompareYears => Some("compareyears"),
+            Self::Compare(_) => Some("compare"),
+            Self::PaceYoy => Some("paceyoy"),
+            Self::Distribution => Some("distribution"),
+            Self::StreakBars => Some("streakbars"),
+            Self::Card => Some("card"),
+            Self::Maintenance => Some("maintenance"),
+            Self::Snapshot => Some("snapshot"),
+            Self::ImportUrl(_) => Some("importurl"),
+            Self::Import => Some("import"),
+            Self::Export => Some("export"),
+            _ => None,
+        }
+    }
+}
+
+/// Runtime switch that makes the bot ignore everyone except admins, e.g. during a backup.
+/// Shared by all bots and the scheduler, since they share the database.
+#[derive(Clone, Default)]
+pub struct MaintenanceMode(Arc<AtomicBool>);
+
+impl MaintenanceMode {
+    pub fn is_enabled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn set(&self, enabled: bool) {
+        self.0.store(enabled, Ordering::Relaxed);
+    }
+}
+
+const MAINTENANCE_TEXT: &str = "Under maintenance, back soon";
+
+const DELETE_PRIVATE_TEXT: &str = "Send /delete in a private chat with me";
+
+const COOLDOWN_PRUNE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Telegram's limits for photos, bigger charts are sent as documents.
+const MAX_PHOTO_SIZE: usize = 10 * 1024 * 1024;
+const MAX_PHOTO_DIMENSIONS_SUM: u32 = 10_000;
+
+const AUDIT_ENTRIES_SHOWN: i64 = 20;
+
+/// Longer replies are sent as a file instead.
+const MAX_MESSAGE_LEN: usize = 4096;
+
+/// Phrase the user has to send to confirm `/delete`.
+const DELETE_CONFIRMATION_PHRASE: &str = "DELETE";
+const DELETE_CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Commands about a group and its members, left out of the menu in private chats.
+const GROUP_COMMANDS: [&str; 7] = [
+    "kudos",
+    "anonymousleaderboard",
+    "ack",
+    "dailycap",
+    "newseason",
+    "teamgoal",
+    "teamprogress",
+];
+
+/// Commands about the user's own data, left out of the menu in groups.
+const PRIVATE_COMMANDS: [&str; 5] = ["plainlog", "importurl", "import", "export", "delete"];
+
+/// Per-chat state for the `/delete` confirmation. It only runs in private chats, where the chat
+/// belongs to its owner, so it never swallows other members' messages.
+#[derive(Clone, Default, PartialEq)]
+pub enum DeleteState {
+    #[default]
+    Idle,
+    AwaitingConfirmation {
+        owner: UserId,
+        requested_at: DateTime<Utc>,
+    },
+}
+
+type DeleteDialogue = Dialogue<DeleteState, InMemStorage<DeleteState>>;
+
+/// Actions behind inline keyboard buttons.
+/// The callback data is `action:telegram_id`, so only the user who asked can press them.
+enum CallbackAction {
+    ClearToday,
+    /// Ask for the typed confirmation before deleting all of the owner's data.
+    Delete,
+    /// Insert the owner's `/done` that was held back as a likely double tap.
+    LogAnyway,
+    Cancel,
+    /// Let the user with this ID watch the owner's milestones.
+    ApproveWatch(i64),
+    DeclineWatch(i64),
+    /// Show the owner's history starting at this offset.
+    HistoryPage(i64),
+    /// Show the owner's annual chart of this year, optionally for a category.
+    AnnualYear(i32, Option<String>),
+    /// Change one of the owner's settings from the `/settings` menu.
+    Settings(SettingChange),
+    /// Show this page of the `/menu` in place.
+    Menu(MenuPage),
+    /// Send this chart picked on the `/menu`'s charts page.
+    MenuChart(MenuChart),
+}
+
+/// The pages of the `/menu`, shown by editing its message.
+#[derive(Clone, Copy)]
+enum MenuPage {
+    Main,
+    Stats,
+    Charts,
+    Leaderboard,
+    Settings,
+}
+
+impl MenuPage {
+    const ALL: [Self; 5] = [
+        Self::Main,
+        Self::Stats,
+        Self::Charts,
+        Self::Leaderboard,
+        Self::Settings,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Main => "main",
+            Self::Stats => "stats",
+            Self::Charts => "charts",
+            Self::Leaderboard => "leaderboard",
+            Self::Settings => "settings",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|page| page.name() == name)
+    }
+}
+
+/// The charts on the `/menu`'s charts page. They are sent as new messages,
+/// a text message can't be edited into a photo.
+#[derive(Clone, Copy)]
+enum MenuChart {
+    Annual,
+    Hourly,
+    Weekday,
+}
+
+impl MenuChart {
+    const ALL: [Self; 3] = [Self::Annual, Self::Hourly, Self::Weekday];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Annual => "annual",
+            Self::Hourly => "hourly",
+            Self::Weekday => "weekday",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|chart| chart.name() == name)
+    }
+}
+
+/// The changes offered by the `/settings` menu.
+#[derive(Clone, Copy)]
+enum SettingChange {
+    /// Switch to the next language, after the last one back to the Telegram app's.
+    NextLanguage,
+    /// Move the timezone by this many minutes.
+    ShiftTimezone(i64),
+    /// Switch to the next chart theme, after the last one back to the default.
+    NextTheme,
+    TogglePrivacy,
+    ToggleQuiet,
+    TogglePlainLogging,
+    ReminderOff,
+    /// Switch to the next reply preset, after the last one (or a custom reply) back to the default.
+    NextAckMessage,
+}
+
+impl CallbackAction {
+    fn encode(&self, owner: UserId) -> String {
+        let action = match self {
+            Self::ClearToday => "cleartoday".to_string(),
+            Self::Delete => "delete".to_string(),
+            Self::LogAnyway => "loganyway".to_string(),
+            Self::Cancel => "cancel".to_string(),
+            Self::ApproveWatch(watcher_id) => format!("approvewatch:{watcher_id}"),
+            Self::DeclineWatch(watcher_id) => format!("declinewatch:{watcher_id}"),
+            Self::HistoryPage(offset) => format!("history:{offset}"),
+            Self::AnnualYear(year, category) => {
+                format!("annual:{year}:{}", category.as_deref().unwrap_or_default())
+            }
+            Self::Settings(change) => match change {
+                SettingChange::ShiftTimezone(minutes) => format!("settings:tz{minutes}"),
+                SettingChange::NextLanguage => "settings:language".to_string(),
+                SettingChange::NextTheme => "settings:theme".to_string(),
+                SettingChange::TogglePrivacy => "settings:privacy".to_string(),
+                SettingChange::ToggleQuiet => "settings:quiet".to_string(),
+                SettingChange::TogglePlainLogging => "settings:plainlog".to_string(),
+                SettingChange::ReminderOff => "settings:reminderoff".to_string(),
+                SettingChange::NextAckMessage => "settings:ackmessage".to_string(),
+            },
+            Self::Menu(page) => format!("menu:{}", page.name()),
+            Self::MenuChart(chart) => format!("menuchart:{}", chart.name()),
+        };
+        format!("{action}:{owner}")
+    }
+
+    fn parse(data: &str) -> Option<(Self, UserId)> {
+        let (action, owner) = data.rsplit_once(':')?;
+        let action = match action.split_once(':') {
+            None if action == "cleartoday" => Self::ClearToday,
+            None if action == "delete" => Self::Delete,
+            None if action == "loganyway" => Self::LogAnyway,
+            None if action == "cancel" => Self::Cancel,
+            Some(("approvewatch", id)) => Self::ApproveWatch(id.parse().ok()?),
+            Some(("declinewatch", id)) => Self::DeclineWatch(id.parse().ok()?),
+            Some(("history", offset)) => Self::HistoryPage(offset.parse().ok()?),
+            Some(("annual", rest)) => {
+                let (year, category) = rest.split_once(':')?;
+                Self::AnnualYear(year.parse().ok()?, parse_category(category))
+            }
+            Some(("settings", change)) => Self::Settings(match change {
+                "language" => SettingChange::NextLanguage,
+                "theme" => SettingChange::NextTheme,
+                "privacy" => SettingChange::TogglePrivacy,
+                "quiet" => SettingChange::ToggleQuiet,
+                "plainlog" => SettingChange::TogglePlainLogging,
+                "reminderoff" => SettingChange::ReminderOff,
+                "ackmessage" => SettingChange::NextAckMessage,
+                _ => SettingChange::ShiftTimezone(change.strip_prefix("tz")?.parse().ok()?),
+            }),
+            Some(("menu", page)) => Self::Menu(MenuPage::from_name(page)?),
+            Some(("menuchart", chart)) => Self::MenuChart(MenuChart::from_name(chart)?),
+            _ => return None,
+        };
+        Some((action, UserId(owner.parse().ok()?)))
+    }
+}
+
+fn main_keyboard() -> ReplyMarkup {
+    let keyboard = KeyboardMarkup::new(vec![
+        vec![KeyboardButton::new("/done"), KeyboardButton::new("/undo")],
+        vec![
+            KeyboardButton::new("/stats"),
+            KeyboardButton::new("/leaderboard"),
+        ],
+        vec![
+            KeyboardButton::new("/annualstats"),
+            KeyboardButton::new("/hourlystats"),
+            KeyboardButton::new("/distribution"),
+        ],
+    ])
+    .resize_keyboard();
+    ReplyMarkup::Keyboard(keyboard)
+}
+
+/// Parses a UTC offset like `+3`, `-5:30` or `UTC+02:00` into minutes.
+fn parse_utc_offset(input: &str) -> Option<i64> {
+    let input = input.trim();
+    let input = input
+        .strip_prefix("UTC")
+        .or_else(|| input.strip_prefix("utc"))
+        .unwrap_or(input);
+    let (sign, rest) = match input.strip_prefix('-') {
+        Some(rest) => (-1, rest),
+        None => (1, input.strip_prefix('+').unwrap_or(input)),
+    };
+    let (hours, minutes) = match rest.split_once(':') {
+        Some((h, m)) => (h.parse::<i64>().ok()?, m.parse::<i64>().ok()?),
+        None => (rest.parse::<i64>().ok()?, 0),
+    };
+    if !(0..=14).contains(&hours) || !(0..60).contains(&minutes) {
+        return None;
+    }
+    Some(sign * (hours * 60 + minutes))
+}
+
+/// Parses `[today|yesterday|YYYY-MM-DD] [HH:MM]` as a local date and time. Without a date
+/// it is today, without a time noon, so that a date alone can't fall on the adjacent day.
+fn parse_backdate(input: &str, today: NaiveDate) -> Option<NaiveDateTime> {
+    let mut parts = input.split_whitespace();
+    let first = parts.next()?;
+    let (date, time) = match first.to_lowercase().as_str() {
+        "today" => (today, parts.next()),
+        "yesterday" => (today.pred_opt()?, parts.next()),
+        _ => match NaiveDate::parse_from_str(first, "%Y-%m-%d") {
+            Ok(date) => (date, parts.next()),
+            Err(_) => (today, Some(first)),
+        },
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+    let time = match time {
+        Some(time) => NaiveTime::parse_from_str(time, "%H:%M").ok()?,
+        None => NaiveTime::from_hms_opt(12, 0, 0)?,
+    };
+    Some(date.and_time(time))
+}
+
+/// Parses `YYYY-MM` into the first day of that month, the month of `today` if empty.
+fn parse_month(input: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let input = input.trim();
+    if input.is_empty() {
+        return today.with_day(1);
+    }
+    NaiveDate::parse_from_str(&format!("{input}-01"), "%Y-%m-%d").ok()
+}
+
+/// How a chat acknowledges new logs, see `/ack`.
+#[derive(Clone, Copy, PartialEq)]
+enum Acknowledgement {
+    /// A message with the user's counts of today and this month.
+    Reply,
+    /// A 👍 reaction on the log's message, falling back to a reply if reactions aren't allowed.
+    Reaction,
+    Silent,
+}
+
+impl Acknowledgement {
+    const ALL: [Self; 3] = [Self::Reply, Self::Reaction, Self::Silent];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Reply => "reply",
+            Self::Reaction => "reaction",
+            Self::Silent => "silent",
+        }
+    }
+
+    /// Returns the acknowledgement stored in the chat settings, a reply by default.
+    fn for_chat(name: Option<&str>) -> Self {
+        Self::ALL
+            .into_iter()
+            .find(|ack| Some(ack.name()) == name)
+            .unwrap_or(Self::Reply)
+    }
+}
+
+/// Parses an `on`/`off` toggle argument.
+fn parse_toggle(input: &str) -> Option<bool> {
+    match input.trim().to_lowercase().as_str() {
+        "on" => Some(true),
+        "off" => Some(false),
+        _ => None,
+    }
+}
+
+/// Private chats belong to the user, in groups only administrators may change the settings.
+async fn can_change_chat_settings(bot: &Bot, chat: &Chat, user_id: UserId) -> ResponseResult<bool> {
+    if chat.is_private() {
+        return Ok(true);
+    }
+    Ok(bot.get_chat_member(chat.id, user_id).await?.is_privileged())
+}
+
+/// Labels a leaderboard row by position, except for the caller's own row.
+fn anonymous_label(position: usize, is_caller: bool, lang: Language) -> String {
+    if is_caller {
+        lang.translate("You").into()
+    } else {
+        fill(lang.translate("User {}"), &[&position])
+    }
+}
+
+/// Renders a fixed-width text progress bar like `[■■■□□□□□□□]`.
+fn progress_bar(current: i64, goal: i64) -> String {
+    const WIDTH: i64 = 10;
+    let filled = (current * WIDTH / goal.max(1)).clamp(0, WIDTH) as usize;
+    format!(
+        "[{}{}]",
+        "■".repeat(filled),
+        "□".repeat(WIDTH as usize - filled)
+    )
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum GoalPeriod {
+    Week,
+    Month,
+}
+
+impl GoalPeriod {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Week => "week",
+            Self::Month => "month",
+        }
+    }
+}
+
+/// The user's goals with the UTC timestamp at which their current period started.
+fn current_goals(
+    settings: &UserSettings,
+    now: DateTime<Utc>,
+) -> [(GoalPeriod, Option<i64>, i64); 2] {
+    let offset = day_offset(settings.utc_offset, settings.day_end);
+    [
+        (
+            GoalPeriod::Week,
+            settings.weekly_goal,
+            week_start(now, offset),
+        ),
+        (
+            GoalPeriod::Month,
+            settings.monthly_goal,
+            month_start(now, offset),
+        ),
+    ]
+}
+
+/// Parses `/goal` arguments like `5 per week`, `20/month` or `0 week`.
+/// A goal of 0 means removing it.
+fn parse_goal(input: &str) -> Option<(i64, GoalPeriod)> {
+    let input = input.trim().to_lowercase();
+    let (count, period) = input.split_once(|c: char| c == '/' || c.is_whitespace())?;
+    let period = period.trim();
+    let period = period.strip_prefix("per ").unwrap_or(period).trim();
+    let period = match period {
+        "week" | "weekly" | "w" => GoalPeriod::Week,
+        "month" | "monthly" | "m" => GoalPeriod::Month,
+        _ => return None,
+    };
+    let count = count.trim().parse::<i64>().ok().filter(|&n| n >= 0)?;
+    Some((count, period))
+}
+
+const IMPROVING_MESSAGES: &[&str] = &[
+    "You're on fire this week! 🔥",
+    "Look at that progress, keep climbing! 📈",
+    "Better than last week, that's how it's done 💪",
+];
+const STEADY_MESSAGES: &[&str] = &[
+    "Nice and steady, consistency wins 🐢",
+    "Same great pace as last week, keep it up 👌",
+    "Slow and steady still gets there 🚶",
+];
+const SLIPPING_MESSAGES: &[&str] = &[
+    "A slower week happens, today is a good day to bounce back 🌱",
+    "Every streak starts with a single /done 🙂",
+    "You've done it before, you can do it again 💫",
+];
+
+/// Picks a message for the trend. The choice only changes once a day
+/// so it is deterministic for a given `seed` and day.
+fn motivation_message(trend: &ActivityTrend, seed: i64, day: i64) -> &'static str {
+    let messages = match trend {
+        ActivityTrend::Improving => IMPROVING_MESSAGES,
+        ActivityTrend::Steady => STEADY_MESSAGES,
+        ActivityTrend::Slipping => SLIPPING_MESSAGES,
+    };
+    messages[(seed + day).rem_euclid(messages.len() as i64) as usize]
+}
+
+const MAX_CATEGORY_LEN: usize = 32;
+/// Telegram's limit on the callback data of a button, in bytes.
+const MAX_CALLBACK_DATA_LEN: usize = 64;
+const MAX_NOTE_LEN: usize = 200;
+/// Replies to `/done` offered by the `/settings` menu, in the format of `UserSettings::ack_messages`.
+const ACK_PRESETS: [&str; 2] = [
+    "🔥 {today}",
+    "💪 Keep it up, that's {today} today!|🚀 {today} today, nice work|⭐ One more done, {today} today",
+];
+const MAX_ACK_LEN: usize = 300;
+/// Latest end of the user's day in minutes after midnight, so that the shifted offset stays valid.
+const MAX_DAY_END: i64 = 6 * 60;
+
+const HISTORY_PAGE_SIZE: i64 = 10;
+
+/// Earliest year the annual chart can be shown for.
+const MIN_CHART_YEAR: i32 = 1970;
+
+/// Splits the `/done` argument into a category (the first word) and a note (the rest).
+fn parse_done_arg(input: &str) -> (Option<String>, Option<&str>) {
+    let input = input.trim();
+    let (category, note) = input.split_once(char::is_whitespace).unwrap_or((input, ""));
+    let note = note.trim();
+    (parse_category(category), (!note.is_empty()).then_some(note))
+}
+
+/// Appends the category a chart is limited to, if any, to its title.
+fn chart_title(name: String, category: &Option<String>) -> String {
+    match category {
+        Some(category) => format!("{name} ({category})"),
+        None => name,
+    }
+}
+
+/// Normalizes a category argument, `None` if it is empty.
+fn parse_category(input: &str) -> Option<String> {
+    let category = input.trim().to_lowercase();
+    (!category.is_empty()).then_some(category)
+}
+
+/// Formats a number with commas between thousands, e.g. `3,410`.
+fn format_thousands(n: i64) -> String {
+    let digits = n.unsigned_abs().to_string();
+    let mut text = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            text.push(',');
+        }
+        text.push(c);
+    }
+    if n < 0 {
+        text.insert(0, '-');
+    }
+    text
+}
+
+fn format_utc_offset(offset: i64) -> String {
+    let sign = if offset < 0 { '-' } else { '+' };
+    format!("UTC{sign}{:02}:{:02}", offset.abs() / 60, offset.abs() % 60)
+}
+
+/// Resolves the user mentioned in a command, either as a text mention or as an `@username`
+/// of someone who has used the bot before.
+async fn resolve_mention(db: &Database, msg: &Message, arg: &str) -> anyhow::Result<Option<i64>> {
+    let text_mention = msg
+        .entities()
+        .into_iter()
+        .flatten()
+        .find_map(|e| match &e.kind {
+            MessageEntityKind::TextMention { user } => Some(user.id),
+            _ => None,
+        });
+    if let Some(tg_id) = text_mention {
+        return db.find_user_by_telegram_id(tg_id.0 as i64).await;
+    }
+    match arg.trim().strip_prefix('@') {
+        Some(username) if !username.is_empty() => db.find_user_by_username(username).await,
+        _ => Ok(None),
+    }
+}
+
+/// Returns the user's ID, keeping their cached username and name up to date.
+async fn register_user(db: &Database, user: &User) -> anyhow::Result<i64> {
+    db.get_user_id(
+        user.id.0 as i64,
+        user.username.as_deref(),
+        &user.first_name,
+        user.last_name.as_deref(),
+    )
+    .await
+}
+
+/// The forum topic the message was sent in, `None` outside forums and in the General topic.
+fn topic(msg: &Message) -> Option<ThreadId> {
+    msg.thread_id.filter(|_| msg.is_topic_message)
+}
+
+/// Sends a reply into the forum topic the user wrote in, Telegram puts it
+/// in the General topic otherwise.
+trait InTopic {
+    fn in_topic(self, thread: Option<ThreadId>) -> Self;
+}
+
+impl InTopic for JsonRequest<payloads::SendMessage> {
+    fn in_topic(mut self, thread: Option<ThreadId>) -> Self {
+        self.message_thread_id = thread;
+        self
+    }
+}
+
+impl InTopic for MultipartRequest<payloads::SendPhoto> {
+    fn in_topic(mut self, thread: Option<ThreadId>) -> Self {
+        self.message_thread_id = thread;
+        self
+    }
+}
+
+impl InTopic for MultipartRequest<payloads::SendDocument> {
+    fn in_topic(mut self, thread: Option<ThreadId>) -> Self {
+        self.message_thread_id = thread;
+        self
+    }
+}
+
+impl InTopic for MultipartRequest<payloads::SendMediaGroup> {
+    fn in_topic(mut self, thread: Option<ThreadId>) -> Self {
+        self.message_thread_id = thread;
+        self
+    }
+}
+
+/// The author of the replied-to message, who gets credit for `/done` or `/kudos`,
+/// unless it's a bot or the sender themselves.
+fn kudos_recipient(msg: &Message, sender: &User) -> Option<User> {
+    // In forum topics, every message replies to the topic's first message
+    msg.reply_to_message()
+        .filter(|m| m.forum_topic_created().is_none())
+        .and_then(|m| m.from.clone())
+        .filter(|u| !u.is_bot && u.id != sender.id)
+}
+
+/// Logs an entry for `recipient` granted by the sender, at most
+/// `kudos_daily_limit` per sender per day.
+async fn give_kudos(
+    bot: &Bot,
+    msg: &Message,
+    db: &Database,
+    config: &Config,
+    cache: &ChartCache,
+    granted_by: i64,
+    recipient: User,
+) -> ResponseResult<()> {
+    let chat_id = msg.chat.id;
+    let thread = topic(msg);
+    let lang = match &msg.from {
+        Some(user) => user_language(db, granted_by, user).await,
+        None => Language::English,
+    };
+    // The exact time, the daily limit counts the last 24 hours to the second
+    let ts = msg.date.timestamp();
+    let settings = db.get_chat_settings(chat_id.0);
+    let recipient_id = register_user(db, &recipient);
+    let (settings, recipient_id) = match try_join(settings, recipient_id).await {
+        Ok(d) => d,
+        Err(err) => {
+            error!("Failed to prepare kudos from {granted_by}: {err}");
+            bot.send_message(chat_id, lang.translate("Database error :("))
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+            return respond(());
+        }
+    };
+    let limit = config.kudos_daily_limit;
+    let inserted = if settings.habit_mode {
+        match db.get_user_settings(recipient_id).await {
+            Ok(recipient_settings) => {
+                let offset = day_offset(recipient_settings.utc_offset, recipient_settings.day_end);
+                let day = day_bounds(msg.date.with_timezone(&offset).date_naive(), offset);
+                db.insert_kudos(recipient_id, chat_id.0, ts, granted_by, limit, Some(day))
+                    .await
+            }
+            Err(err) => Err(err),
+        }
+    } else {
+        db.insert_kudos(recipient_id, chat_id.0, ts, granted_by, limit, None)
+            .await
+    };
+    let text = match inserted {
+        Ok(Kudos::Given) => {
+            cache.invalidate(recipient_id);
+            fill(
+                lang.translate("👏 Kudos to {}!"),
+                &[&DisplayName::from(&recipient).plain()],
+            )
+        }
+        Ok(Kudos::AlreadyLogged) => fill(
+            lang.translate("{} already logged today ✅"),
+            &[&DisplayName::from(&recipient).plain()],
+        ),
+        Ok(Kudos::LimitReached) => lang
+            .translate("You've given all your kudos for today")
+            .to_string(),
+        Err(err) => {
+            error!("Failed to insert kudos from {granted_by} for {recipient_id}: {err}");
+            bot.send_message(chat_id, lang.translate("Database error :("))
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+            return respond(());
+        }
+    };
+    bot.send_message(chat_id, text)
+        .in_topic(thread)
+        .reply_markup(main_keyboard())
+        .await?;
+    if matches!(inserted, Ok(Kudos::Given)) {
+        let recipient_lang = user_language(db, recipient_id, &recipient).await;
+        celebrate_log(bot, db, chat_id, thread, recipient_id, recipient_lang).await;
+        if let Err(err) = notify_watchers(bot, db, recipient_id, &recipient).await {
+            error!("Failed to notify the watchers of the user {recipient_id}: {err}");
+        }
+    }
+    respond(())
+}
+
+/// Tells the user's approved watchers if their new log total is a milestone.
+async fn notify_watchers(
+    bot: &Bot,
+    db: &Database,
+    user_id: i64,
+    user: &User,
+) -> anyhow::Result<()> {
+    let total = db.get_count_between(user_id, i64::MIN, i64::MAX).await?;
+    if !is_milestone(total) {
+        return Ok(());
+    }
+    for (tg_id, language) in db.get_watchers_to_notify(user_id).await? {
+        let lang = Language::for_user(language.as_deref(), None);
+        let text = fill(
+            lang.translate("🎉 {} just reached {} logs!"),
+            &[&DisplayName::from(user).plain(), &total],
+        );
+        if let Err(err) = bot.send_message(ChatId(tg_id), text).await {
+            debug!("Failed to notify the watcher {tg_id}: {err}");
+        }
+    }
+    Ok(())
+}
+
+/// Returns the user's number of logs today and this month, and their current streak.
+async fn recent_counts(
+    db: &Database,
+    user_id: i64,
+    settings: &UserSettings,
+) -> anyhow::Result<(i64, i64, u32)> {
+    let offset = day_offset(settings.utc_offset, settings.day_end);
+    let now = Utc::now();
+    let today = now.with_timezone(&offset).date_naive();
+    let (day_start, _) = day_bounds(today, offset);
+    let ((day, month), days) = try_join(
+        db.get_day_and_month_counts(user_id, day_start, month_start(now, offset)),
+        db.get_active_days(user_id),
+    )
+    .await?;
+    let streak = current_streak(&dates_from_day_numbers(&days), today);
+    Ok((day, month, streak))
+}
+
+/// The reply to a new log: the user's own one if they set it, otherwise their counts of today
+/// and this month and their streak. A plain thumbs up if they can't be loaded.
+async fn log_reply(db: &Database, user_id: i64, lang: Language) -> String {
+    let settings = match db.get_user_settings(user_id).await {
+        Ok(settings) => settings,
+        Err(err) => {
+            error!("Failed to get settings for the user {user_id}: {err}");
+            return "👍".to_string();
+        }
+    };
+    match recent_counts(db, user_id, &settings).await {
+        Ok((today, month, streak)) => match &settings.ack_messages {
+            Some(messages) => custom_acknowledgement(messages, today),
+            None => {
+                let streak = match streak {
+                    1 => lang.translate("1 day").to_string(),
+                    n => fill(lang.translate("{} days"), &[&n]),
+                };
+                fill(
+                    lang.translate("👍 That's {} today, {} this month, streak: {}"),
+                    &[&today, &month, &streak],
+                )
+            }
+        },
+        Err(err) => {
+            error!("Failed to count the logs of the user {user_id}: {err}");
+            "👍".to_string()
+        }
+    }
+}
+
+/// Picks the user's phrase for their `today`-th log of the day, taking turns through them.
+fn custom_acknowledgement(messages: &str, today: i64) -> String {
+    let phrases: Vec<&str> = messages.split('|').collect();
+    let phrase = phrases[(today.max(1) - 1) as usize % phrases.len()];
+    phrase.replace("{today}", &today.to_string())
+}
+
+/// Announces what the user's new log earned: reached goals, achievements and level-ups.
+/// In quiet mode they are only recorded, so they aren't announced later either.
+async fn celebrate_log(
+    bot: &Bot,
+    db: &Database,
+    chat_id: ChatId,
+    thread: Option<ThreadId>,
+    user_id: i64,
+    lang: Language,
+) {
+    let settings = match db.get_user_settings(user_id).await {
+        Ok(settings) => settings,
+        Err(err) => {
+            error!("Failed to get settings for the user {user_id}: {err}");
+            return;
+        }
+    };
+    if let Err(err) = congratulate_goals(bot, db, chat_id, thread, user_id, &settings, lang).await {
+        error!("Failed to check the goals of the user {user_id}: {err}");
+    }
+    if let Err(err) =
+        award_achievements(bot, db, chat_id, thread, user_id, settings.quiet, lang).await
+    {
+        error!("Failed to award achievements to the user {user_id}: {err}");
+    }
+    if let Err(err) =
+        announce_level_up(bot, db, chat_id, thread, user_id, settings.quiet, lang).await
+    {
+        error!("Failed to check the level of the user {user_id}: {err}");
+    }
+}
+
+/// Congratulates the user in the chat if their new log reached one of their goals,
+/// at most once per week or month.
+async fn congratulate_goals(
+    bot: &Bot,
+    db: &Database,
+    chat_id: ChatId,
+    thread: Option<ThreadId>,
+    user_id: i64,
+    settings: &UserSettings,
+    lang: Language,
+) -> anyhow::Result<()> {
+    let now = Utc::now();
+    for (period, goal, from) in current_goals(settings, now) {
+        let Some(goal) = goal else { continue };
+        let count = db
+            .get_count_between(user_id, from, now.timestamp() + 1)
+            .await?;
+        if count < goal {
+            continue;
+        }
+        let first = match period {
+            GoalPeriod::Week => db.mark_weekly_goal_reached(user_id, from).await?,
+            GoalPeriod::Month => db.mark_monthly_goal_reached(user_id, from).await?,
+        };
+        if first && !settings.quiet {
+            let template = match period {
+                GoalPeriod::Week => "🎯 Weekly goal reached: {}/{}! Well done 🎉",
+                GoalPeriod::Month => "🎯 Monthly goal reached: {}/{}! Well done 🎉",
+            };
+            let text = fill(lang.translate(template), &[&count, &goal]);
+            bot.send_message(chat_id, text)
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Awards the achievements the user's new log total earned and announces them in the chat.
+async fn award_achievements(
+    bot: &Bot,
+    db: &Database,
+    chat_id: ChatId,
+    thread: Option<ThreadId>,
+    user_id: i64,
+    quiet: bool,
+    lang: Language,
+) -> anyhow::Result<()> {
+    let total = db.get_count_between(user_id, i64::MIN, i64::MAX).await?;
+    let now = Utc::now().timestamp();
+    let mut unlocked = Vec::new();
+    for achievement in achievements::earned(total) {
+        if db.award_achievement(user_id, achievement.id, now).await? {
+            unlocked.push(fill(
+                lang.translate("{} {} ({} logs)"),
+                &[
+                    &achievement.icon,
+                    &lang.translate(achievement.name),
+                    &achievement.logs,
+                ],
+            ));
+        }
+    }
+    if !unlocked.is_empty() && !quiet {
+        let text = format!(
+            "{}\n{}",
+            lang.translate("🏅 Achievement unlocked!"),
+            unlocked.join("\n")
+        );
+        bot.send_message(chat_id, text)
+            .in_topic(thread)
+            .reply_markup(main_keyboard())
+            .await?;
+    }
+    Ok(())
+}
+
+/// Announces in the chat if the user's new log took them to a new level.
+async fn announce_level_up(
+    bot: &Bot,
+    db: &Database,
+    chat_id: ChatId,
+    thread: Option<ThreadId>,
+    user_id: i64,
+    quiet: bool,
+    lang: Language,
+) -> anyhow::Result<()> {
+    let xp = db.get_count_between(user_id, i64::MIN, i64::MAX).await?;
+    let level = levels::level(xp);
+    if level > 1 && db.mark_level_announced(user_id, level).await? && !quiet {
+        let text = fill(
+            lang.translate("⬆️ Level up! You are now level {}"),
+            &[&level],
+        );
+        bot.send_message(chat_id, text)
+            .in_topic(thread)
+            .reply_markup(main_keyboard())
+            .await?;
+    }
+    Ok(())
+}
+
+/// Sends a chart as a photo, or as a document if Telegram wouldn't accept it as a photo.
+async fn send_chart(
+    bot: &Bot,
+    chat_id: ChatId,
+    thread: Option<ThreadId>,
+    png_bytes: Vec<u8>,
+) -> ResponseResult<Message> {
+    send_chart_with_markup(bot, chat_id, thread, png_bytes, main_keyboard()).await
+}
+
+async fn send_chart_with_markup(
+    bot: &Bot,
+    chat_id: ChatId,
+    thread: Option<ThreadId>,
+    png_bytes: Vec<u8>,
+    markup: ReplyMarkup,
+) -> ResponseResult<Message> {
+    let dimensions = ImageReader::new(Cursor::new(&png_bytes))
+        .with_guessed_format()
+        .ok()
+        .and_then(|reader| reader.into_dimensions().ok());
+    let too_big = png_bytes.len() > MAX_PHOTO_SIZE
+        || dimensions.is_some_and(|(w, h)| w + h > MAX_PHOTO_DIMENSIONS_SUM);
+    if too_big {
+        warn!(
+            "A chart of {} bytes ({dimensions:?}) is too big for a photo, sending it as a document",
+            png_bytes.len()
+        );
+    }
+    if too_big || send_as_document() {
+        return bot
+            .send_document(chat_id, InputFile::memory(png_bytes).file_name("chart.png"))
+            .in_topic(thread)
+            .reply_markup(markup)
+            .await;
+    }
+    bot.send_photo(chat_id, InputFile::memory(png_bytes))
+        .in_topic(thread)
+        .reply_markup(markup)
+        .await
+}
+
+/// Returns the user's annual chart of `year` from the cache, rendering it if needed.
+async fn annual_chart(
+    db: &Database,
+    cache: &ChartCache,
+    user_id: i64,
+    name: String,
+    year: i32,
+    category: Option<String>,
+    language: Option<String>,
+) -> anyhow::Result<Vec<u8>> {
+    let chart = format!(
+        "annual:{year}:{name}:{}",
+        language.as_deref().unwrap_or_default()
+    );
+    let cache_key = chart_cache_key(db, user_id, chart).await;
+    if let Some(png_bytes) = cache_key.as_ref().and_then(|key| cache.get(key)) {
+        return Ok(png_bytes);
+    }
+    let monthly = db
+        .get_monthly_counts(user_id, year, category.as_deref())
+        .await?;
+    let settings = db.get_user_settings(user_id).await?;
+    let theme = ChartTheme::for_user(settings.chart_theme.as_deref());
+    // The monthly goal counts every log, so it means nothing on a category chart.
+    let goal = settings.monthly_goal.filter(|_| category.is_none());
+    let png_bytes = render(move || {
+        generate_personal_annual_chart(&name, monthly, year, language.as_deref(), goal, theme)
+    })
+    .await?;
+    if let Some(key) = cache_key {
+        cache.insert(key, png_bytes.clone());
+    }
+    Ok(png_bytes)
+}
+
+/// Returns the user's hourly chart from the cache, rendering it if needed.
+async fn hourly_chart(
+    db: &Database,
+    cache: &ChartCache,
+    user_id: i64,
+    name: String,
+    category: Option<String>,
+    language: Option<String>,
+) -> anyhow::Result<Vec<u8>> {
+    let chart = format!("hourly:{name}:{}", language.as_deref().unwrap_or_default());
+    let cache_key = chart_cache_key(db, user_id, chart).await;
+    if let Some(png_bytes) = cache_key.as_ref().and_then(|key| cache.get(key)) {
+        return Ok(png_bytes);
+    }
+    let hourly = db.get_hourly_counts(user_id, category.as_deref()).await?;
+    let theme = user_theme(db, user_id).await;
+    let png_bytes =
+        render(move || generate_personal_hourly_chart(&name, hourly, language.as_deref(), theme))
+            .await?;
+    if let Some(key) = cache_key {
+        cache.insert(key, png_bytes.clone());
+    }
+    Ok(png_bytes)
+}
+
+/// Returns the user's day-of-week chart from the cache, rendering it if needed.
+async fn weekday_chart(
+    db: &Database,
+    cache: &ChartCache,
+    user_id: i64,
+    name: String,
+    language: Option<String>,
+) -> anyhow::Result<Vec<u8>> {
+    let settings = db.get_user_settings(user_id).await?;
+    let offset = day_offset(settings.utc_offset, settings.day_end);
+    let chart = format!(
+        "weekday:{offset}:{name}:{}",
+        language.as_deref().unwrap_or_default()
+    );
+    let cache_key = chart_cache_key(db, user_id, chart).await;
+    if let Some(png_bytes) = cache_key.as_ref().and_then(|key| cache.get(key)) {
+        return Ok(png_bytes);
+    }
+    let weekday = db.get_weekday_counts(user_id).await?;
+    let theme = ChartTheme::for_user(settings.chart_theme.as_deref());
+    let png_bytes =
+        render(move || generate_weekday_chart(&name, weekday, language.as_deref(), theme)).await?;
+    if let Some(key) = cache_key {
+        cache.insert(key, png_bytes.clone());
+    }
+    Ok(png_bytes)
+}
+
+/// Buttons flipping the annual chart to the previous and next year. A button whose
+/// category doesn't fit in the callback data is left out.
+fn annual_buttons(year: i32, category: Option<&str>, owner: UserId) -> InlineKeyboardMarkup {
+    let mut buttons = Vec::new();
+    if year > MIN_CHART_YEAR {
+        let data = CallbackAction::AnnualYear(year - 1, category.map(Into::into)).encode(owner);
+        if data.len() <= MAX_CALLBACK_DATA_LEN {
+            buttons.push(InlineKeyboardButton::callback(
+                format!("◀ {}", year - 1),
+                data,
+            ));
+        }
+    }
+    if year < Utc::now().year() {
+        let data = CallbackAction::AnnualYear(year + 1, category.map(Into::into)).encode(owner);
+        if data.len() <= MAX_CALLBACK_DATA_LEN {
+            buttons.push(InlineKeyboardButton::callback(
+                format!("{} ▶", year + 1),
+                data,
+            ));
+        }
+    }
+    InlineKeyboardMarkup::new(vec![buttons])
+}
+
+/// Formats one step of `/diagnose` with its duration, logging failures.
+fn diagnostic_line(step: &str, start: Instant, result: anyhow::Result<()>) -> String {
+    let elapsed = start.elapsed();
+    match result {
+        Ok(()) => format!("\n✅ {step}: {elapsed:.0?}"),
+        Err(err) => {
+            error!("Diagnostics step {step:?} failed: {err}");
+            format!("\n❌ {step}: {err} ({elapsed:.0?})")
+        }
+    }
+}
+
+/// Records a privileged action if the audit log is enabled.
+/// A failure is only logged, so it never keeps the action itself from running.
+async fn audit(db: &Database, config: &Config, actor: UserId, action: &str, target: Option<&str>) {
+    if !config.audit_log {
+        return;
+    }
+    let result = db
+        .insert_audit_entry(actor.0 as i64, action, target, Utc::now().timestamp())
+        .await;
+    if let Err(err) = result {
+        error!("Failed to record {action} by {actor} in the audit log: {err}");
+    }
+}
+
+/// Whether the message is exactly one of the configured `/done` aliases.
+/// Aliases named like a real command are ignored, so they can't shadow it.
+fn is_done_alias(config: &Config, msg: &Message) -> bool {
+    let Some(text) = msg.text() else {
+        return false;
+    };
+    let word = text.trim().trim_start_matches('/');
+    let word = word.split_once('@').map_or(word, |(w, _)| w).to_lowercase();
+    let is_command = Command::bot_commands()
+        .iter()
+        .any(|c| c.command.trim_start_matches('/') == word);
+    !is_command && config.done_aliases.contains(&word)
+}
+
+/// Whether the message is a plain log like "done" sent in a private chat by a user who turned
+/// `/plainlog` on. During maintenance it can't be checked, so every plain log is let through
+/// to be told about it.
+async fn is_plain_log(
+    db: &Database,
+    config: &Config,
+    maintenance: &MaintenanceMode,
+    msg: &Message,
+) -> bool {
+    let (Some(text), Some(user)) = (msg.text(), &msg.from) else {
+        return false;
+    };
+    if !msg.chat.is_private() || !plain::is_log_message(text) {
+        return false;
+    }
+    if maintenance.is_enabled() && !config.is_admin(user.id.0 as i64) {
+        return true;
+    }
+    match db.has_plain_logging(user.id.0 as i64).await {
+        Ok(enabled) => enabled,
+        Err(err) => {
+            error!("Failed to check plain logging for {}: {err}", user.id);
+            false
+        }
+    }
+}
+
+/// Returns the start of the leaderboard period named by `arg` (`day`, `week` or `month`, in UTC),
+/// `None` for anything else.
+fn leaderboard_window(arg: &str, now: DateTime<Utc>) -> Option<i64> {
+    let utc = user_offset(0);
+    match arg.trim().to_lowercase().as_str() {
+        "day" | "today" => Some(local_midnight(now.date_naive(), utc)),
+        "week" => Some(week_start(now, utc)),
+        "month" => Some(utc_month_bounds(now).0),
+        _ => None,
+    }
+}
+
+/// Group leaderboards only show members who logged in the group, private chats show everyone.
+fn leaderboard_scope(chat: &Chat) -> Option<i64> {
+    (!chat.is_private()).then_some(chat.id.0)
+}
+
+/// Returns a page of the user's history, newest first, with buttons to the adjacent pages.
+async fn history_page(
+    db: &Database,
+    user_id: i64,
+    owner: UserId,
+    offset: i64,
+    lang: Language,
+) -> anyhow::Result<(String, InlineKeyboardMarkup)> {
+    let offset = offset.max(0);
+    // One more than shown to know whether there is a next page
+    let (mut logs, settings) = try_join(
+        db.get_recent_logs(user_id, offset, HISTORY_PAGE_SIZE + 1),
+        db.get_user_settings(user_id),
+    )
+    .await?;
+    let has_next = logs.len() as i64 > HISTORY_PAGE_SIZE;
+    logs.truncate(HISTORY_PAGE_SIZE as usize);
+    if logs.is_empty() {
+        return Ok((
+            lang.translate("No logs yet").into(),
+            InlineKeyboardMarkup::default(),
+        ));
+    }
+    let mut buttons = Vec::new();
+    if offset > 0 {
+        buttons.push(InlineKeyboardButton::callback(
+            lang.translate("◀ Prev"),
+            CallbackAction::HistoryPage(offset - HISTORY_PAGE_SIZE).encode(owner),
+        ));
+    }
+    if has_next {
+        buttons.push(InlineKeyboardButton::callback(
+            lang.translate("Next ▶"),
+            CallbackAction::HistoryPage(offset + HISTORY_PAGE_SIZE).encode(owner),
+        ));
+    }
+    let text = format_history(&logs, user_offset(settings.utc_offset));
+    Ok((text, InlineKeyboardMarkup::new(vec![buttons])))
+}
+
+/// Builds the `/settings` menu: the owner's current settings with buttons to change them.
+async fn settings_menu(
+    db: &Database,
+    user_id: i64,
+    user: &User,
+) -> anyhow::Result<(String, InlineKeyboardMarkup)> {
+    let settings = db.get_user_settings(user_id).await?;
+    let lang = Language::for_user(settings.language.as_deref(), user.language_code.as_deref());
+    let on_off = |enabled: bool| lang.translate(if enabled { "on" } else { "off" });
+    let language = match settings.language.as_deref().and_then(Language::from_code) {
+        Some(language) => language.name(),
+        None => lang.translate("automatic"),
+    };
+    let theme = settings
+        .chart_theme
+        .as_deref()
+        .unwrap_or(lang.translate("default"));
+    let reminder = settings.reminder_minute.map_or(on_off(false).into(), |m| {
+        format!("{:02}:{:02}", m / 60, m % 60)
+    });
+    let ack_message = match settings.ack_messages.as_deref() {
+        Some(messages) => messages.split('|').next().unwrap_or_default(),
+        None => lang.translate("default"),
+    };
+    let text = [
+        lang.translate("Your settings:").to_string(),
+        fill(lang.translate("Language: {}"), &[&language]),
+        fill(
+            lang.translate("Timezone: {}"),
+            &[&format_utc_offset(settings.utc_offset)],
+        ),
+        fill(lang.translate("Chart theme: {}"), &[&theme]),
+        fill(
+            lang.translate("Reminder: {} (set it with /remind HH:MM)"),
+            &[&reminder],
+        ),
+        fill(
+            lang.translate("Hidden from rankings: {}"),
+            &[&on_off(settings.hide_from_rankings)],
+        ),
+        fill(lang.translate("Quiet mode: {}"), &[&on_off(settings.quiet)]),
+        fill(lang.translate("Reply to /done: {}"), &[&ack_message]),
+        fill(
+            lang.translate("Log with \"done\" or \"+1\": {}"),
+            &[&on_off(settings.plain_logging)],
+        ),
+        fill(
+            lang.translate("Day ends at: {}"),
+            &[&format!(
+                "{:02}:{:02}",
+                settings.day_end / 60,
+                settings.day_end % 60
+            )],
+        ),
+    ]
+    .join("\n");
+    let button = |label: String, change| {
+        InlineKeyboardButton::callback(label, CallbackAction::Settings(change).encode(user.id))
+    };
+    let mut rows = vec![
+        vec![button(
+            fill(lang.translate("Language: {} ▶"), &[&language]),
+            SettingChange::NextLanguage,
+        )],
+        vec![
+            button(
+                lang.translate("Timezone −1h").into(),
+                SettingChange::ShiftTimezone(-60),
+            ),
+            button(
+                lang.translate("Timezone +1h").into(),
+                SettingChange::ShiftTimezone(60),
+            ),
+        ],
+        vec![button(
+            fill(lang.translate("Theme: {} ▶"), &[&theme]),
+            SettingChange::NextTheme,
+        )],
+        vec![button(
+            fill(
+                lang.translate("Hidden from rankings: {}"),
+                &[&on_off(settings.hide_from_rankings)],
+            ),
+            SettingChange::TogglePrivacy,
+        )],
+        vec![button(
+            fill(lang.translate("Quiet mode: {}"), &[&on_off(settings.quiet)]),
+            SettingChange::ToggleQuiet,
+        )],
+        vec![button(
+            fill(lang.translate("Reply to /done: {} ▶"), &[&ack_message]),
+            SettingChange::NextAckMessage,
+        )],
+        vec![button(
+            fill(
+                lang.translate("Log with \"done\" or \"+1\": {}"),
+                &[&on_off(settings.plain_logging)],
+            ),
+            SettingChange::TogglePlainLogging,
+        )],
+    ];
+    if settings.reminder_minute.is_some() {
+        rows.push(vec![button(
+            lang.translate("Turn the reminder off").into(),
+            SettingChange::ReminderOff,
+        )]);
+    }
+    rows.push(vec![InlineKeyboardButton::callback(
+        lang.translate("◀ Menu"),
+        CallbackAction::Menu(MenuPage::Main).encode(user.id),
+    )]);
+    Ok((text, InlineKeyboardMarkup::new(rows)))
+}
+
+/// The `/stats` reply: the user's score in the chat, level, categories and goal progress.
+async fn stats_text(
+    db: &Database,
+    config: &Config,
+    chat_id: ChatId,
+    user_id: i64,
+    lang: Language,
+) -> anyhow::Result<String> {
+    let chat_settings = db.get_chat_settings(chat_id.0).await?;
+    let count = db
+        .get_user_stats(
+            user_id,
+            config.daily_cap(chat_settings.daily_cap),
+            chat_settings.season_start,
+        )
+        .await?;
+    let settings = db.get_user_settings(user_id).await?;
+    let mut text = fill(lang.translate("Your score: {}"), &[&count]);
+    if let Some(start) = settings
+        .tracking_start
+        .and_then(|ts| DateTime::from_timestamp(ts, 0))
+    {
+        let offset = day_offset(settings.utc_offset, settings.day_end);
+        text.push_str(&fill(
+            lang.translate(" (since {})"),
+            &[&start.with_timezone(&offset).date_naive()],
+        ));
+    }
+    let xp = db.get_count_between(user_id, i64::MIN, i64::MAX).await?;
+    let (level, gained, needed) = levels::progress(xp);
+    text.push('\n');
+    text.push_str(&fill(
+        lang.translate("Level {} {} {}/{} XP"),
+        &[&level, &progress_bar(gained, needed), &gained, &needed],
+    ));
+    let categories = db.get_user_categories(user_id).await?;
+    if !categories.is_empty() {
+        text.push('\n');
+        text.push_str(lang.translate("By category:"));
+        for (category, logs) in categories {
+            text.push_str(&format!("\n{category} - {logs}"));
+        }
+    }
+    let now = Utc::now();
+    for (period, goal, from) in current_goals(&settings, now) {
+        let Some(goal) = goal else { continue };
+        let count = db
+            .get_count_between(user_id, from, now.timestamp() + 1)
+            .await?;
+        let template = match period {
+            GoalPeriod::Week => "This week: {}/{} {}",
+            GoalPeriod::Month => "This month: {}/{} {}",
+        };
+        text.push('\n');
+        text.push_str(&fill(
+            lang.translate(template),
+            &[&count, &goal, &progress_bar(count, goal)],
+        ));
+    }
+    Ok(text)
+}
+
+/// Builds a page of the `/menu`: its text as HTML and its buttons.
+async fn menu_page(
+    db: &Database,
+    config: &Config,
+    chat: &Chat,
+    user: &User,
+    page: MenuPage,
+) -> anyhow::Result<(String, InlineKeyboardMarkup)> {
+    let user_id = register_user(db, user).await?;
+    let lang = user_language(db, user_id, user).await;
+    let button = |label: &str, action: CallbackAction| {
+        InlineKeyboardButton::callback(lang.translate(label), action.encode(user.id))
+    };
+    let back = vec![button("◀ Menu", CallbackAction::Menu(MenuPage::Main))];
+    let (text, rows) = match page {
+        MenuPage::Main => {
+            let rows = vec![
+                vec![
+                    button("Stats", CallbackAction::Menu(MenuPage::Stats)),
+                    button("Charts", CallbackAction::Menu(MenuPage::Charts)),
+                ],
+                vec![
+                    button("Leaderboard", CallbackAction::Menu(MenuPage::Leaderboard)),
+                    button("Settings", CallbackAction::Menu(MenuPage::Settings)),
+                ],
+            ];
+            let text = lang.translate("What would you like to see?");
+            (html::escape(text), rows)
+        }
+        MenuPage::Stats => {
+            let text = stats_text(db, config, chat.id, user_id, lang).await?;
+            (html::escape(&text), vec![back])
+        }
+        MenuPage::Charts => {
+            let rows = vec![
+                vec![
+                    button("Year", CallbackAction::MenuChart(MenuChart::Annual)),
+                    button("Hours", CallbackAction::MenuChart(MenuChart::Hourly)),
+                    button("Weekdays", CallbackAction::MenuChart(MenuChart::Weekday)),
+                ],
+                back,
+            ];
+            (html::escape(lang.translate("Pick a chart:")), rows)
+        }
+        MenuPage::Leaderboard => {
+            let settings = db.get_chat_settings(chat.id.0).await?;
+            let leaderboard = db
+                .get_leaderboard(
+                    config.daily_cap(settings.daily_cap),
+                    None,
+                    settings.season_start,
+                    None,
+                    leaderboard_scope(chat),
+                    Some(user_id),
+                )
+                .await?;
+            let anonymous = settings.anonymous_leaderboard;
+            let text =
+                format_leaderboard(db, &leaderboard, anonymous, user.id.0 as i64, lang).await;
+            (text, vec![back])
+        }
+        MenuPage::Settings => {
+            let (text, buttons) = settings_menu(db, user_id, user).await?;
+            (html::escape(&text), buttons.inline_keyboard)
+        }
+    };
+    Ok((text, InlineKeyboardMarkup::new(rows)))
+}
+
+/// Renders a chart picked on the `/menu`'s charts page.
+async fn menu_chart(
+    db: &Database,
+    cache: &ChartCache,
+    user: &User,
+    chart: MenuChart,
+) -> anyhow::Result<Vec<u8>> {
+    let user_id = register_user(db, user).await?;
+    let language = user_language_code(db, user_id, user).await;
+    let name = DisplayName::from(user).title();
+    match chart {
+        MenuChart::Annual => {
+            let year = Utc::now().year();
+            annual_chart(db, cache, user_id, name, year, None, language).await
+        }
+        MenuChart::Hourly => hourly_chart(db, cache, user_id, name, None, language).await,
+        MenuChart::Weekday => weekday_chart(db, cache, user_id, name, language).await,
+    }
+}
+
+/// Applies a change picked in the `/settings` menu.
+async fn apply_setting_change(
+    db: &Database,
+    cache: &ChartCache,
+    user_id: i64,
+    change: SettingChange,
+) -> anyhow::Result<()> {
+    let settings = db.get_user_settings(user_id).await?;
+    match change {
+        SettingChange::NextLanguage => {
+            let current = settings.language.as_deref().and_then(Language::from_code);
+            let next = match current {
+                Some(language) => Language::ALL.iter().skip_while(|&&l| l != language).nth(1),
+                None => Language::ALL.first(),
+            };
+            db.set_language(user_id, next.map(|l| l.code())).await?;
+        }
+        SettingChange::ShiftTimezone(minutes) => {
+            let offset = (settings.utc_offset + minutes).clamp(-14 * 60, 14 * 60);
+            let updated = db.set_utc_offset(user_id, offset).await;
+            cache.invalidate(user_id);
+            updated?;
+            db.reset_local_day_markers(user_id).await?;
+        }
+        SettingChange::NextTheme => {
+            let current = settings
+                .chart_theme
+                .as_deref()
+                .and_then(|theme| ChartTheme::NAMES.iter().position(|&name| name == theme));
+            let next = match current {
+                Some(i) => ChartTheme::NAMES.get(i + 1).copied(),
+                None => ChartTheme::NAMES.first().copied(),
+            };
+            db.set_chart_theme(user_id, next).await?;
+            cache.invalidate(user_id);
+        }
+        SettingChange::TogglePrivacy => {
+            db.set_hide_from_rankings(user_id, !settings.hide_from_rankings)
+                .await?
+        }
+        SettingChange::ToggleQuiet => db.set_quiet(user_id, !settings.quiet).await?,
+        SettingChange::TogglePlainLogging => {
+            db.set_plain_logging(user_id, !settings.plain_logging)
+                .await?
+        }
+        SettingChange::ReminderOff => db.set_reminder(user_id, None).await?,
+        SettingChange::NextAckMessage => {
+            let current = settings
+                .ack_messages
+                .as_deref()
+                .map(|messages| ACK_PRESETS.iter().position(|&preset| preset == messages));
+            let next = match current {
+                None => ACK_PRESETS.first().copied(),
+                Some(Some(i)) => ACK_PRESETS.get(i + 1).copied(),
+                Some(None) => None,
+            };
+            db.set_ack_messages(user_id, next).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Formats logs one per line with their local time, category and note.
+fn format_history(logs: &[LogEntry], offset: FixedOffset) -> String {
+    logs.iter()
+        .map(|log| {
+            let mut line = DateTime::from_timestamp(log.timestamp, 0)
+                .map(|dt| {
+                    dt.with_timezone(&offset)
+                        .format("%Y-%m-%d %H:%M")
+                        .to_string()
+                })
+                .unwrap_or_else(|| log.timestamp.to_string());
+            for part in [&log.category, &log.note].into_iter().flatten() {
+                line.push_str(" · ");
+                line.push_str(part);
+            }
+            line + "\n"
+        })
+        .collect()
+}
+
+/// Formats leaderboard rows as HTML with the cached names, medals for the top three
+/// and the users' achievement icons, hiding names if `anonymous`.
+/// Rows past the top 10 (the caller's own) are set apart.
+async fn format_leaderboard(
+    db: &Database,
+    leaderboard: &[RankedTotal],
+    anonymous: bool,
+    caller_tg_id: i64,
+    lang: Language,
+) -> String {
+    let ids: Vec<i64> = leaderboard.iter().map(|r| r.user_id).collect();
+    // The badges are decoration, the leaderboard is still worth showing without them.
+    let badges = match db.get_users_achievements(&ids).await {
+        Ok(badges) => badges,
+        Err(err) => {
+            error!("Failed to get the achievements of the leaderboard: {err}");
+            Vec::new()
+        }
+    };
+    let mut text = String::new();
+    for r in leaderboard {
+        if r.position > 10 {
+            text.push_str("…\n");
+        }
+        let position = match r.position {
+            1 => "🥇".to_string(),
+            2 => "🥈".to_string(),
+            3 => "🥉".to_string(),
+            p => format!("{p}."),
+        };
+        let is_caller = r.telegram_id == caller_tg_id;
+        let label = if anonymous {
+            anonymous_label(r.position as usize, is_caller, lang)
+        } else {
+            DisplayName::from(r).html()
+        };
+        let icons: String = ACHIEVEMENTS
+            .iter()
+            .filter(|a| badges.iter().any(|(id, b)| *id == r.user_id && b == a.id))
+            .map(|a| a.icon)
+            .collect();
+        let pointer = if is_caller { "👉 " } else { "" };
+        text.push_str(&format!("{pointer}{position} {label}"));
+        if !icons.is_empty() {
+            text.push_str(&format!(" {icons}"));
+        }
+        text.push_str(&format!(" - {}\n", r.logs));
+    }
+    if text.is_empty() {
+        lang.translate("The leaderboard is empty").into()
+    } else {
+        text
+    }
+}
+
+/// Formats `(user_id, streak)` rows as HTML with medals for the top three,
+/// hiding names if `anonymous`.
+fn format_streaks(
+    streaks: &[(i64, u32)],
+    names: &[UserName],
+    anonymous: bool,
+    caller_tg_id: i64,
+    lang: Language,
+) -> String {
+    let text: String = streaks
+        .iter()
+        .enumerate()
+        .map(|(i, &(user_id, streak))| {
+            let position = match i {
+                0 => "🥇".to_string(),
+                1 => "🥈".to_string(),
+                2 => "🥉".to_string(),
+                _ => format!("{}.", i + 1),
+            };
+            let name = names.iter().find(|n| n.id == user_id);
+            let is_caller = name.is_some_and(|n| n.telegram_id == caller_tg_id);
+            let label = if anonymous {
+                anonymous_label(i + 1, is_caller, lang)
+            } else {
+                name.map_or_else(|| user_id.to_string(), |n| DisplayName::from(n).html())
+            };
+            let days = match streak {
+                1 => lang.translate("1 day").to_string(),
+                n => fill(lang.translate("{} days"), &[&n]),
+            };
+            format!("{position} {label} - {days}\n")
+        })
+        .collect();
+    if text.is_empty() {
+        lang.translate("No active streaks").into()
+    } else {
+        text
+    }
+}
+
+/// The language of the user's replies, see `Language::for_user`.
+async fn user_language(db: &Database, user_id: i64, user: &User) -> Language {
+    Language::for_user(user_language_code(db, user_id, user).await.as_deref(), None)
+}
+
+/// The code of the language the user picked, otherwise the one of their Telegram app.
+/// Charts use it as is, so that languages without translated replies still get their month names.
+async fn user_language_code(db: &Database, user_id: i64, user: &User) -> Option<String> {
+    match db.get_user_settings(user_id).await {
+        Ok(settings) => settings.language.or_else(|| user.language_code.clone()),
+        Err(err) => {
+            error!("Failed to get the language of the user {user_id}: {err}");
+            user.language_code.clone()
+        }
+    }
+}
+
+/// Lists the commands with their descriptions in `lang`, for `/start`.
+fn command_descriptions(lang: Language) -> String {
+    if lang == Language::English {
+        return Command::descriptions().to_string();
+    }
+    Command::bot_commands()
+        .iter()
+        .map(|c| format!("{} — {}", c.command, lang.translate(&c.description)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The commands for Telegram's command menu with descriptions in `lang`, except the ones
+/// in `left_out` and the admin ones unless `admin`.
+fn menu_commands(lang: Language, left_out: &[&str], admin: bool) -> Vec<BotCommand> {
+    Command::bot_commands()
+        .into_iter()
+        .filter(|c| admin || !c.description.starts_with("Admin: "))
+        .map(|c| {
+            let command = c.command.trim_start_matches('/').to_string();
+            BotCommand::new(command, lang.translate(&c.description))
+        })
+        .filter(|c| !left_out.contains(&c.command.as_str()))
+        .collect()
+}
+
+/// Sets the command menus shown by Telegram, one for private chats and one for groups
+/// in every language, and one with the admin commands in each admin's private chat.
+/// They are built from `Command`, so every start brings them up to date.
+async fn register_commands(bot: &Bot, config: &Config) -> anyhow::Result<()> {
+    for lang in Language::ALL {
+        let language_code = (lang != Language::English).then(|| lang.code().to_string());
+        let scopes = [
+            (BotCommandScope::AllPrivateChats, &GROUP_COMMANDS[..]),
+            (BotCommandScope::AllGroupChats, &PRIVATE_COMMANDS[..]),
+        ];
+        for (scope, left_out) in scopes {
+            let mut request = bot
+                .set_my_commands(menu_commands(lang, left_out, false))
+                .scope(scope);
+            request.language_code = language_code.clone();
+            request.await?;
+        }
+        for &admin in &config.admin_ids {
+            let scope = BotCommandScope::Chat {
+                chat_id: Recipient::Id(ChatId(admin)),
+            };
+            let mut request = bot
+                .set_my_commands(menu_commands(lang, &GROUP_COMMANDS, true))
+                .scope(scope);
+            request.language_code = language_code.clone();
+            // Fails for admins who haven't started a chat with the bot yet
+            if let Err(err) = request.await {
+                warn!("Failed to set the admin commands for {admin}: {err}");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The user's chart theme, the default one if it can't be loaded.
+async fn user_theme(db: &Database, user_id: i64) -> ChartTheme {
+    match db.get_user_settings(user_id).await {
+        Ok(settings) => ChartTheme::for_user(settings.chart_theme.as_deref()),
+        Err(err) => {
+            error!("Failed to get the chart theme of the user {user_id}: {err}");
+            ChartTheme::for_user(None)
+        }
+    }
+}
+
+/// Builds the cache key for a chart of the user's current data.
+/// Returns `None` (no caching) if the data version can't be determined.
+async fn chart_cache_key(db: &Database, user_id: i64, chart: String) -> Option<ChartKey> {
+    match db.get_latest_log_id(user_id).await {
+        Ok(latest_log_id) => Some(ChartKey {
+            user_id,
+            chart,
+            latest_log_id,
+        }),
+        Err(err) => {
+            debug!("Failed to get the latest log ID for {user_id}: {err}");
+            None
+        }
+    }
+}
+
+pub async fn run_bot(
+    bot: Bot,
+    database: Database,
+    config: Config,
+    maintenance: MaintenanceMode,
+    cache: ChartCache,
+    pending: PendingLogs,
+    deletes: Arc<InMemStorage<DeleteState>>,
+) -> anyhow::Result<()> {
+    let handler = dptree::entry()
+        .branch(
+            Update::filter_message()
+                .enter_dialogue::<Message, InMemStorage<DeleteState>, DeleteState>()
+                .branch(
+                    dptree::entry()
+                        .filter_command::<Command>()
+                        .endpoint(handle_command),
+                )
+                .branch(
+                    dptree::case![DeleteState::AwaitingConfirmation {
+                        owner,
+                        requested_at
+                    }]
+                    .endpoint(handle_delete_confirmation),
+                )
+                .branch(
+                    dptree::filter(|msg: Message, config: Config| is_done_alias(&config, &msg))
+                        .map(|| Command::Done(String::new()))
+                        .endpoint(handle_command),
+                )
+                .branch(
+                    dptree::filter_async(
+                        |msg: Message,
+                         db: Database,
+                         config: Config,
+                         maintenance: MaintenanceMode| async move {
+                            is_plain_log(&db, &config, &maintenance, &msg).await
+                        },
+                    )
+                    .map(|| Command::Done(String::new()))
+                    .endpoint(handle_command),
+                ),
+        )
+        .branch(Update::filter_callback_query().endpoint(handle_callback))
+        .branch(Update::filter_inline_query().endpoint(handle_inline_query));
+    if let Err(err) = register_commands(&bot, &config).await {
+        warn!("Failed to register the commands with Telegram: {err}");
+    }
+    let cooldowns = Cooldowns::new(config.command_cooldown);
+    tokio::spawn({
+        let (cooldowns, pending) = (cooldowns.clone(), pending.clone());
+        async move {
+            let mut interval = tokio::time::interval(COOLDOWN_PRUNE_INTERVAL);
+            loop {
+                interval.tick().await;
+                cooldowns.prune();
+                pending.prune();
+            }
+        }
+    });
+    let webhook = config.webhook_url.clone().map(|url| {
+        let address = SocketAddr::from(([0, 0, 0, 0], config.port));
+        let options = webhooks::Options::new(address, url);
+        // Without a secret, teloxide generates one
+        match config.webhook_secret.clone() {
+            Some(secret) => options.secret_token(secret),
+            None => options,
+        }
+    });
+    let mut dispatcher = Dispatcher::builder(bot.clone(), handler)
+        .dependencies(dptree::deps![
+            database,
+            config,
+            cache,
+            maintenance,
+            cooldowns,
+            pending,
+            deletes
+        ])
+        .enable_ctrlc_handler()
+        .build();
+    match webhook {
+        Some(options) => {
+            info!(
+                "Listening for webhook updates on {} at {}",
+                options.address, options.url
+            );
+            let listener = webhooks::axum(bot.clone(), options).await?;
+            dispatcher
+                .dispatch_with_listener(
+                    listener,
+                    LoggingErrorHandler::with_custom_text("An error from the webhook listener"),
+                )
+                .await;
+        }
+        None => dispatcher.dispatch().await,
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn handle_command(
+    bot: Bot,
+    msg: Message,
+    command: Command,
+    db: Database,
+    config: Config,
+    cache: ChartCache,
+    maintenance: MaintenanceMode,
+    cooldowns: Cooldowns,
+    pending: PendingLogs,
+) -> ResponseResult<()> {
+    let user = match msg.from.clone() {
+        Some(u) => u,
+        None => return respond(()),
+    };
+    let chat_id = msg.chat.id;
+    let thread = topic(&msg);
+    // Until the user's own choice is loaded
+    let lang = Language::for_user(None, user.language_code.as_deref());
+    if maintenance.is_enabled() && !config.is_admin(user.id.0 as i64) {
+        bot.send_message(chat_id, lang.translate(MAINTENANCE_TEXT))
+            .in_topic(thread)
+            .reply_markup(main_keyboard())
+            .await?;
+        return respond(());
+    }
+    if let Some(key) = command.cooldown_key()
+        && !cooldowns.try_acquire(user.id.0 as i64, key)
+    {
+        bot.send_message(chat_id, lang.translate("Please slow down"))
+            .in_topic(thread)
+            .reply_markup(main_keyboard())
+            .await?;
+        return respond(());
+    }
+    let user_id = match register_user(&db, &user).await {
+        Ok(id) => id,
+        Err(err) => {
+            error!("Failed to get user ID from the DB: {err}");
+            bot.send_message(chat_id, lang.translate("Database error :("))
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+            return respond(());
+        }
+    };
+    let language = user_language_code(&db, user_id, &user).await;
+    let lang = Language::for_user(language.as_deref(), None);
+
+    match command {
+        Command::Start => {
+            bot.send_message(chat_id, command_descriptions(lang))
+                .in_topic(thread)
+                .await?;
+            // The menu edits itself in place, /keyboard brings back the reply keyboard
+            match menu_page(&db, &config, &msg.chat, &user, MenuPage::Main).await {
+                Ok((text, buttons)) => {
+                    bot.send_message(chat_id, text)
+                        .in_topic(thread)
+                        .parse_mode(ParseMode::Html)
+                        .reply_markup(buttons)
+                        .await?;
+                }
+                Err(err) => error!("Failed to show the menu to the user {user_id}: {err}"),
+            }
+        }
+        Command::Keyboard => {
+            bot.send_message(chat_id, lang.translate("Keyboard updated"))
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::Menu => match menu_page(&db, &config, &msg.chat, &user, MenuPage::Main).await {
+            Ok((text, buttons)) => {
+                bot.send_message(chat_id, text)
+                    .in_topic(thread)
+                    .parse_mode(ParseMode::Html)
+                    .reply_markup(buttons)
+                    .await?;
+            }
+            Err(err) => {
+                error!("Failed to show the menu to the user {user_id}: {err}");
+                bot.send_message(chat_id, lang.translate("Database error :("))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+            }
+        },
+        Command::Done(arg) => {
+            if let Some(recipient) = kudos_recipient(&msg, &user) {
+                return give_kudos(&bot, &msg, &db, &config, &cache, user_id, recipient).await;
+            }
+            let (category, note) = parse_done_arg(&arg);
+            if category
+                .as_ref()
+                .is_some_and(|c| c.chars().count() > MAX_CATEGORY_LEN)
+            {
+                bot.send_message(
+                    chat_id,
+                    fill(
+                        lang.translate("Categories can be at most {} characters long"),
+                        &[&MAX_CATEGORY_LEN],
+                    ),
+                )
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+                return respond(());
+            }
+            if note.is_some_and(|n| n.chars().count() > MAX_NOTE_LEN) {
+                bot.send_message(
+                    chat_id,
+                    fill(
+                        lang.translate("Notes can be at most {} characters long"),
+                        &[&MAX_NOTE_LEN],
+                    ),
+                )
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+                return respond(());
+            }
+            let ts = msg.date.timestamp();
+            let (habit_mode, acknowledgement) = match db.get_chat_settings(chat_id.0).await {
+                Ok(settings) => (
+                    settings.habit_mode,
+                    Acknowledgement::for_chat(settings.acknowledgement.as_deref()),
+                ),
+                Err(err) => {
+                    error!("Failed to get the settings for the chat {chat_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            // Habit mode allows one log a day anyway
+            if !habit_mode && !config.duplicate_cooldown.is_zero() {
+                let cooldown = config.duplicate_cooldown.as_secs() as i64;
+                match db.get_latest_log_timestamp(user_id).await {
+                    Ok(Some(last)) if (0..cooldown).contains(&(ts - last)) => {
+                        pending.insert(
+                            user_id,
+                            PendingLog {
+                                chat_id: chat_id.0,
+                                timestamp: ts,
+                                category,
+                                note: note.map(str::to_string),
+                            },
+                        );
+                        let buttons = InlineKeyboardMarkup::new(vec![vec![
+                            InlineKeyboardButton::callback(
+                                lang.translate("Log anyway"),
+                                CallbackAction::LogAnyway.encode(user.id),
+                            ),
+                            InlineKeyboardButton::callback(
+                                lang.translate("Cancel"),
+                                CallbackAction::Cancel.encode(user.id),
+                            ),
+                        ]]);
+                        bot.send_message(
+                            chat_id,
+                            fill(
+                                lang.translate("You logged {} seconds ago, log again?"),
+                                &[&(ts - last)],
+                            ),
+                        )
+                        .in_topic(thread)
+                        .reply_markup(buttons)
+                        .await?;
+                        return respond(());
+                    }
+                    Ok(_) => {}
+                    Err(err) => {
+                        error!("Failed to get the latest log of the user {user_id}: {err}");
+                        bot.send_message(chat_id, lang.translate("Database error :("))
+                            .in_topic(thread)
+                            .reply_markup(main_keyboard())
+                            .await?;
+                        return respond(());
+                    }
+                }
+            }
+            let inserted = if habit_mode {
+                match db.get_user_settings(user_id).await {
+                    Ok(settings) => {
+                        let offset = day_offset(settings.utc_offset, settings.day_end);
+                        let (from, to) =
+                            day_bounds(msg.date.with_timezone(&offset).date_naive(), offset);
+                        db.insert_log_once_between(
+                            user_id,
+                            chat_id.0,
+                            ts,
+                            category.as_deref(),
+                            note,
+                            from,
+                            to,
+                        )
+                        .await
+                    }
+                    Err(err) => Err(err),
+                }
+            } else {
+                db.insert_log(user_id, chat_id.0, ts, category.as_deref(), note)
+                    .await
+                    .map(|_| true)
+            };
+            let text = match inserted {
+                Ok(true) => {
+                    cache.invalidate(user_id);
+                    match acknowledgement {
+                        Acknowledgement::Reply => Some(log_reply(&db, user_id, lang).await),
+                        Acknowledgement::Reaction => {
+                            let thumbs_up = ReactionType::Emoji {
+                                emoji: "👍".into()
+                            };
+                            match bot
+                                .set_message_reaction(chat_id, msg.id)
+                                .reaction([thumbs_up])
+                                .await
+                            {
+                                Ok(_) => None,
+                                Err(err) => {
+                                    debug!("Failed to react to a log in the chat {chat_id}: {err}");
+                                    Some(log_reply(&db, user_id, lang).await)
+                                }
+                            }
+                        }
+                        Acknowledgement::Silent => None,
+                    }
+                }
+                Ok(false) => Some(lang.translate("Already logged today ✅").to_string()),
+                Err(err) => {
+                    error!("Failed to insert a log for the user {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            if let Some(text) = text {
+                bot.send_message(chat_id, text)
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+            }
+            if matches!(inserted, Ok(true)) {
+                celebrate_log(&bot, &db, chat_id, thread, user_id, lang).await;
+                if let Err(err) = notify_watchers(&bot, &db, user_id, &user).await {
+                    error!("Failed to notify the watchers of the user {user_id}: {err}");
+                }
+            }
+        }
+        Command::Log(arg) => {
+            let settings = match db.get_user_settings(user_id).await {
+                Ok(s) => s,
+                Err(err) => {
+                    error!("Failed to get settings for the user {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            let offset = user_offset(settings.utc_offset);
+            let now = msg.date.with_timezone(&offset);
+            let Some(local) = parse_backdate(&arg, now.date_naive()) else {
+                bot.send_message(
+                    chat_id,
+                    lang.translate("Usage: /log [today|yesterday|2024-03-01] [14:30], in your timezone (see /timezone)"),
+                ).in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+                return respond(());
+            };
+            let ts = local.and_utc().timestamp() - i64::from(offset.local_minus_utc());
+            if ts > msg.date.timestamp() {
+                bot.send_message(chat_id, lang.translate("You can't log in the future"))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            // A log before the end of the user's day counts for the day before
+            let day = (local - TimeDelta::minutes(settings.day_end)).date();
+            let inserted = match db.get_chat_settings(chat_id.0).await {
+                Ok(chat_settings) if chat_settings.habit_mode => {
+                    let (from, to) =
+                        day_bounds(day, day_offset(settings.utc_offset, settings.day_end));
+                    db.insert_log_once_between(user_id, chat_id.0, ts, None, None, from, to)
+                        .await
+                }
+                Ok(_) => db
+                    .insert_log(user_id, chat_id.0, ts, None, None)
+                    .await
+                    .map(|_| true),
+                Err(err) => Err(err),
+            };
+            let text = match inserted {
+                Ok(true) => {
+                    cache.invalidate(user_id);
+                    fill(
+                        lang.translate("Logged for {} 👍"),
+                        &[&local.format("%Y-%m-%d %H:%M")],
+                    )
+                }
+                Ok(false) => fill(lang.translate("Already logged on {} ✅"), &[&day]),
+                Err(err) => {
+                    error!("Failed to insert a backdated log for the user {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            bot.send_message(chat_id, text)
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+            if matches!(inserted, Ok(true)) {
+                celebrate_log(&bot, &db, chat_id, thread, user_id, lang).await;
+            }
+        }
+        Command::Undo => {
+            let deleted = match db.delete_last_log(user_id).await {
+                Ok(deleted) => deleted,
+                Err(err) => {
+                    error!("Failed to delete the last log of the user {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            if !deleted {
+                bot.send_message(chat_id, lang.translate("You have no logs to undo"))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            cache.invalidate(user_id);
+            let stats = match db.get_chat_settings(chat_id.0).await {
+                Ok(chat_settings) => {
+                    db.get_user_stats(
+                        user_id,
+                        config.daily_cap(chat_settings.daily_cap),
+                        chat_settings.season_start,
+                    )
+                    .await
+                }
+                Err(err) => Err(err),
+            };
+            let text = match stats {
+                Ok(count) => fill(
+                    lang.translate("Removed your last log, your score: {}"),
+                    &[&count],
+                ),
+                Err(err) => {
+                    error!("Failed to get stats for the user {user_id}: {err}");
+                    lang.translate("Removed your last log").into()
+                }
+            };
+            bot.send_message(chat_id, text)
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::Kudos => {
+            let Some(recipient) = kudos_recipient(&msg, &user) else {
+                bot.send_message(
+                    chat_id,
+                    lang.translate("Reply to someone's message with /kudos to log for them"),
+                )
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+                return respond(());
+            };
+            return give_kudos(&bot, &msg, &db, &config, &cache, user_id, recipient).await;
+        }
+        Command::Stats => match stats_text(&db, &config, chat_id, user_id, lang).await {
+            Ok(text) => {
+                bot.send_message(chat_id, text)
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+            }
+            Err(err) => {
+                error!("Failed to get stats for the user {user_id}: {err}");
+                bot.send_message(chat_id, lang.translate("Database error :("))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+            }
+        },
+        Command::History => {
+            let (text, buttons) = match history_page(&db, user_id, user.id, 0, lang).await {
+                Ok(page) => page,
+                Err(err) => {
+                    error!("Failed to get the history of the user {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            bot.send_message(chat_id, text)
+                .in_topic(thread)
+                .reply_markup(buttons)
+                .await?;
+        }
+        Command::StatsSince(arg) => {
+            let dates: Result<Vec<NaiveDate>, _> = arg
+                .split_whitespace()
+                .map(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d"))
+                .collect();
+            let (start, end) = match dates.as_deref() {
+                Ok([start]) => (*start, None),
+                Ok([a, b]) => (*a.min(b), Some(*a.max(b))),
+                _ => {
+                    bot.send_message(
+                        chat_id,
+                        lang.translate("Usage: /statssince 2024-01-01 [2024-03-31]"),
+                    )
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                    return respond(());
+                }
+            };
+            let activity = match db.get_user_settings(user_id).await {
+                Ok(settings) => {
+                    let offset = day_offset(settings.utc_offset, settings.day_end);
+                    let from = local_midnight(start, offset);
+                    let to = match end.and_then(|d| d.succ_opt()) {
+                        Some(next) => local_midnight(next, offset),
+                        None => Utc::now().timestamp() + 1,
+                    };
+                    db.get_activity_between(user_id, from, to).await
+                }
+                Err(err) => Err(err),
+            };
+            let (logs, days) = match activity {
+                Ok(a) => a,
+                Err(err) => {
+                    error!("Failed to get the activity for the user {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            let text = match end {
+                Some(end) => fill(
+                    lang.translate("From {} to {}: {} logs on {} days"),
+                    &[&start, &end, &logs, &days],
+                ),
+                None => fill(
+                    lang.translate("Since {}: {} logs on {} days"),
+                    &[&start, &logs, &days],
+                ),
+            };
+            bot.send_message(chat_id, text)
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::Motivation => {
+            const WEEK: i64 = 7 * 24 * 60 * 60;
+            let now = Utc::now().timestamp();
+            let counts = try_join(
+                db.get_count_between(user_id, now - WEEK, now + 1),
+                db.get_count_between(user_id, now - 2 * WEEK, now - WEEK),
+            );
+            let (recent, previous) = match counts.await {
+                Ok(c) => c,
+                Err(err) => {
+                    error!("Failed to get recent counts for the user {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            let trend = classify_trend(recent, previous);
+            let text = motivation_message(&trend, user_id, now / (24 * 60 * 60));
+            bot.send_message(chat_id, lang.translate(text))
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::AnnualStats(arg) => {
+            let current_year = Utc::now().year();
+            let mut words = arg.split_whitespace().peekable();
+            let year = match words.peek().and_then(|w| w.parse::<i32>().ok()) {
+                Some(year) => {
+                    words.next();
+                    year
+                }
+                None => current_year,
+            };
+            if !(MIN_CHART_YEAR..=current_year).contains(&year) {
+                bot.send_message(
+                    chat_id,
+                    fill(
+                        lang.translate(
+                            "Usage: /annualstats [year] [category], the year from {} to {}",
+                        ),
+                        &[&MIN_CHART_YEAR, &current_year],
+                    ),
+                )
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+                return respond(());
+            }
+            let category = parse_category(&words.collect::<Vec<_>>().join(" "));
+            if category
+                .as_ref()
+                .is_some_and(|c| c.chars().count() > MAX_CATEGORY_LEN)
+            {
+                bot.send_message(
+                    chat_id,
+                    fill(
+                        lang.translate("Categories can be at most {} characters long"),
+                        &[&MAX_CATEGORY_LEN],
+                    ),
+                )
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+                return respond(());
+            }
+            let name = chart_title(DisplayName::from(&user).title(), &category);
+            let chart = annual_chart(&db, &cache, user_id, name, year, category.clone(), language);
+            match chart.await {
+                Ok(png_bytes) => {
+                    let buttons = annual_buttons(year, category.as_deref(), user.id);
+                    send_chart_with_markup(&bot, chat_id, thread, png_bytes, buttons.into())
+                        .await?;
+                }
+                Err(err) => {
+                    error!("Failed to generate the chart for {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Error generating the chart :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            }
+        }
+        Command::HourlyStats(arg) => {
+            let category = parse_category(&arg);
+            let name = chart_title(DisplayName::from(&user).title(), &category);
+            match hourly_chart(&db, &cache, user_id, name, category, language).await {
+                Ok(png_bytes) => {
+                    send_chart(&bot, chat_id, thread, png_bytes).await?;
+                }
+                Err(err) => {
+                    error!("Failed to generate the chart for {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Error generating the chart :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            }
+        }
+        Command::Timeline => {
+            let settings = match db.get_user_settings(user_id).await {
+                Ok(settings) => settings,
+                Err(err) => {
+                    error!("Failed to get the settings of the user {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            let name = DisplayName::from(&user).title();
+            let offset = user_offset(settings.utc_offset);
+            let year = Utc::now().with_timezone(&offset).year();
+            let chart = format!(
+                "timeline:{year}:{offset}:{name}:{}",
+                language.as_deref().unwrap_or_default()
+            );
+            let cache_key = chart_cache_key(&db, user_id, chart).await;
+            if let Some(png_bytes) = cache_key.as_ref().and_then(|key| cache.get(key)) {
+                send_chart(&bot, chat_id, thread, png_bytes).await?;
+                return respond(());
+            }
+            let jan1 = NaiveDate::from_ymd_opt(year, 1, 1).unwrap_or_default();
+            let (from, to) = months_bounds(jan1, 12, offset);
+            let timestamps = match db.get_user_timestamps_between(user_id, from, to).await {
+                Ok(ts) => ts,
+                Err(err) => {
+                    error!("Failed to get timestamps for the user {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            let theme = ChartTheme::for_user(settings.chart_theme.as_deref());
+            match render(move || {
+                generate_timeline_chart(&name, timestamps, year, offset, language.as_deref(), theme)
+            })
+            .await
+            {
+                Ok(png_bytes) => {
+                    if let Some(key) = cache_key {
+                        cache.insert(key, png_bytes.clone());
+                    }
+                    send_chart(&bot, chat_id, thread, png_bytes).await?;
+                }
+                Err(err) => {
+                    error!("Failed to generate the chart for {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Error generating the chart :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            }
+        }
+        Command::Heatmap => {
+            let settings = match db.get_user_settings(user_id).await {
+                Ok(settings) => settings,
+                Err(err) => {
+                    error!("Failed to get the settings of the user {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            let name = DisplayName::from(&user).title();
+            let offset = day_offset(settings.utc_offset, settings.day_end);
+            let year = Utc::now().with_timezone(&offset).year();
+            let chart = format!(
+                "heatmap:{year}:{offset}:{name}:{}",
+                language.as_deref().unwrap_or_default()
+            );
+            let cache_key = chart_cache_key(&db, user_id, chart).await;
+            if let Some(png_bytes) = cache_key.as_ref().and_then(|key| cache.get(key)) {
+                send_chart(&bot, chat_id, thread, png_bytes).await?;
+                return respond(());
+            }
+            let jan1 = NaiveDate::from_ymd_opt(year, 1, 1).unwrap_or_default();
+            let (from, to) = months_bounds(jan1, 12, offset);
+            let timestamps = match db.get_user_timestamps_between(user_id, from, to).await {
+                Ok(ts) => ts,
+                Err(err) => {
+                    error!("Failed to get timestamps for the user {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            let theme = ChartTheme::for_user(settings.chart_theme.as_deref());
+            match render(move || {
+                generate_heatmap_chart(&name, timestamps, year, offset, language.as_deref(), theme)
+            })
+            .await
+            {
+                Ok(png_bytes) => {
+                    if let Some(key) = cache_key {
+                        cache.insert(key, png_bytes.clone());
+                    }
+                    send_chart(&bot, chat_id, thread, png_bytes).await?;
+                }
+                Err(err) => {
+                    error!("Failed to generate the chart for {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Error generating the chart :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            }
+        }
+        Command::MonthStats(arg) => {
+            let settings = match db.get_user_settings(user_id).await {
+                Ok(settings) => settings,
+                Err(err) => {
+                    error!("Failed to get the settings of the user {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            let offset = day_offset(settings.utc_offset, settings.day_end);
+            let today = Utc::now().with_timezone(&offset).date_naive();
+            let month = match parse_month(&arg, today) {
+                Some(month) if month.year() >= MIN_CHART_YEAR && month <= today => month,
+                _ => {
+                    bot.send_message(chat_id, lang.translate("Usage: /monthstats [YYYY-MM]"))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            let name = DisplayName::from(&user).title();
+            let chart = format!(
+                "month:{month}:{offset}:{name}:{}",
+                language.as_deref().unwrap_or_default()
+            );
+            let cache_key = chart_cache_key(&db, user_id, chart).await;
+            if let Some(png_bytes) = cache_key.as_ref().and_then(|key| cache.get(key)) {
+                send_chart(&bot, chat_id, thread, png_bytes).await?;
+                return respond(());
+            }
+            let (from, to) = months_bounds(month, 1, offset);
+            let timestamps = match db.get_user_timestamps_between(user_id, from, to).await {
+                Ok(ts) => ts,
+                Err(err) => {
+                    error!("Failed to get timestamps for the user {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            let theme = ChartTheme::for_user(settings.chart_theme.as_deref());
+            match render(move || {
+                generate_month_chart(&name, timestamps, month, offset, language.as_deref(), theme)
+            })
+            .await
+            {
+                Ok(png_bytes) => {
+                    if let Some(key) = cache_key {
+                        cache.insert(key, png_bytes.clone());
+                    }
+                    send_chart(&bot, chat_id, thread, png_bytes).await?;
+                }
+                Err(err) => {
+                    error!("Failed to generate the chart for {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Error generating the chart :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            }
+        }
+        Command::WeekdayStats => {
+            let name = DisplayName::from(&user).title();
+            match weekday_chart(&db, &cache, user_id, name, language).await {
+                Ok(png_bytes) => {
+                    send_chart(&bot, chat_id, thread, png_bytes).await?;
+                }
+                Err(err) => {
+                    error!("Failed to generate the chart for {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Error generating the chart :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            }
+        }
+        Command::Dashboard => {
+            let name = DisplayName::from(&user).title();
+            let charts = try_join3(
+                annual_chart(
+                    &db,
+                    &cache,
+                    user_id,
+                    name.clone(),
+                    Utc::now().year(),
+                    None,
+                    language.clone(),
+                ),
+                hourly_chart(&db, &cache, user_id, name.clone(), None, language.clone()),
+                weekday_chart(&db, &cache, user_id, name, language),
+            );
+            match charts.await {
+                Ok((annual, hourly, weekday)) => {
+                    let media = [annual, hourly, weekday].map(|png_bytes| {
+                        let file = InputFile::memory(png_bytes).file_name("chart.png");
+                        if send_as_document() {
+                            InputMedia::Document(InputMediaDocument::new(file))
+                        } else {
+                            InputMedia::Photo(InputMediaPhoto::new(file))
+                        }
+                    });
+                    bot.send_media_group(chat_id, media)
+                        .in_topic(thread)
+                        .await?;
+                }
+                Err(err) => {
+                    error!("Failed to generate the dashboard for {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Error generating the charts :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            }
+        }
+        Command::CompareYears => {
+            let year = Utc::now().year();
+            let counts = try_join(
+                db.get_monthly_counts(user_id, year, None),
+                db.get_monthly_counts(user_id, year - 1, None),
+            );
+            let (this_year, last_year) = match counts.await {
+                Ok(c) => c,
+                Err(err) => {
+                    error!("Failed to get monthly counts for the user {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            let name = DisplayName::from(&user).title();
+            let chart = format!(
+                "compareyears:{year}:{name}:{}",
+                language.as_deref().unwrap_or_default()
+            );
+            let cache_key = chart_cache_key(&db, user_id, chart).await;
+            if let Some(png_bytes) = cache_key.as_ref().and_then(|key| cache.get(key)) {
+                send_chart(&bot, chat_id, thread, png_bytes).await?;
+                return respond(());
+            }
+            let theme = user_theme(&db, user_id).await;
+            match render(move || {
+                generate_year_comparison_chart(
+                    &name,
+                    this_year,
+                    last_year,
+                    year,
+                    language.as_deref(),
+                    theme,
+                )
+            })
+            .await
+            {
+                Ok(png_bytes) => {
+                    if let Some(key) = cache_key {
+                        cache.insert(key, png_bytes.clone());
+                    }
+                    send_chart(&bot, chat_id, thread, png_bytes).await?;
+                }
+                Err(err) => {
+                    error!("Failed to generate the chart for {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Error generating the chart :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            }
+        }
+        Command::Compare(arg) => {
+            let other_id = match resolve_mention(&db, &msg, &arg).await {
+                Ok(Some(id)) if id == user_id => {
+                    bot.send_message(
+                        chat_id,
+                        lang.translate("Use /compareyears to compare with yourself"),
+                    )
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                    return respond(());
+                }
+                Ok(Some(id)) => id,
+                Ok(None) => {
+                    bot.send_message(
+                        chat_id,
+                        lang.translate(
+                            "Usage: /compare @user (they must have used the bot before)",
+                        ),
+                    )
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                    return respond(());
+                }
+                Err(err) => {
+                    error!("Failed to resolve the mention {arg:?}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            match db.allows_compare(other_id).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    bot.send_message(
+                        chat_id,
+                        lang.translate("This user doesn't allow comparisons, they can turn them on with /allowcompare on"),
+                    ).in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                    return respond(());
+                }
+                Err(err) => {
+                    error!("Failed to check comparison consent of {other_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            }
+            let year = Utc::now().year();
+            let other_ids = [other_id];
+            let data = try_join3(
+                db.get_monthly_counts(user_id, year, None),
+                db.get_monthly_counts(other_id, year, None),
+                db.get_user_names(&other_ids),
+            );
+            let (mine, theirs, names) = match data.await {
+                Ok(d) => d,
+                Err(err) => {
+                    error!("Failed to get monthly counts of {user_id} and {other_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            let other = names
+                .first()
+                .map_or_else(|| other_id.to_string(), |n| DisplayName::from(n).title());
+            let name = DisplayName::from(&user).title();
+            let theme = user_theme(&db, user_id).await;
+            // Not cached, the key only tracks the caller's own logs
+            match render(move || {
+                generate_user_comparison_chart(
+                    &name,
+                    &other,
+                    mine,
+                    theirs,
+                    year,
+                    language.as_deref(),
+                    theme,
+                )
+            })
+            .await
+            {
+                Ok(png_bytes) => {
+                    send_chart(&bot, chat_id, thread, png_bytes).await?;
+                }
+                Err(err) => {
+                    error!("Failed to generate the chart for {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Error generating the chart :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            }
+        }
+        Command::PaceYoy => {
+            let now = Utc::now();
+            let year = now.year();
+            let day_of_year = now.ordinal();
+            let year_start = |y: i32| {
+                NaiveDate::from_ymd_opt(y, 1, 1)
+                    .map_or(0, |d| d.and_time(NaiveTime::MIN).and_utc().timestamp())
+            };
+            let window = i64::from(day_of_year) * 24 * 60 * 60;
+            let (this_start, last_start) = (year_start(year), year_start(year - 1));
+            let windows = try_join(
+                db.get_user_timestamps_between(user_id, this_start, this_start + window),
+                db.get_user_timestamps_between(user_id, last_start, last_start + window),
+            );
+            let (this_year, last_year) = match windows.await {
+                Ok(ts) => ts,
+                Err(err) => {
+                    error!("Failed to get timestamps for the user {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            let name = DisplayName::from(&user).title();
+            let cache_key = chart_cache_key(
+                &db,
+                user_id,
+                format!(
+                    "paceyoy:{year}:{day_of_year}:{name}:{}",
+                    language.as_deref().unwrap_or_default()
+                ),
+            )
+            .await;
+            if let Some(png_bytes) = cache_key.as_ref().and_then(|key| cache.get(key)) {
+                send_chart(&bot, chat_id, thread, png_bytes).await?;
+                return respond(());
+            }
+            let theme = user_theme(&db, user_id).await;
+            match render(move || {
+                generate_year_over_year_pace_chart(
+                    &name,
+                    this_year,
+                    last_year,
+                    year,
+                    day_of_year,
+                    language.as_deref(),
+                    theme,
+                )
+            })
+            .await
+            {
+                Ok(png_bytes) => {
+                    if let Some(key) = cache_key {
+                        cache.insert(key, png_bytes.clone());
+                    }
+                    send_chart(&bot, chat_id, thread, png_bytes).await?;
+                }
+                Err(err) => {
+                    error!("Failed to generate the chart for {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Error generating the chart :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            }
+        }
+        Command::Distribution => {
+            let name = DisplayName::from(&user).title();
+            let chart = format!(
+                "distribution:{name}:{}",
+                language.as_deref().unwrap_or_default()
+            );
+            let cache_key = chart_cache_key(&db, user_id, chart).await;
+            if let Some(png_bytes) = cache_key.as_ref().and_then(|key| cache.get(key)) {
+                send_chart(&bot, chat_id, thread, png_bytes).await?;
+                return respond(());
+            }
+            let timestamps = match db.get_all_user_timestamps(user_id).await {
+                Ok(ts) => ts,
+                Err(err) => {
+                    error!("Failed to get timestamps for the user {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            if timestamps.is_empty() {
+                bot.send_message(chat_id, lang.translate("You have no logs yet"))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            let theme = user_theme(&db, user_id).await;
+            match render(move || {
+                generate_daily_distribution_chart(&name, timestamps, language.as_deref(), theme)
+            })
+            .await
+            {
+                Ok(png_bytes) => {
+                    if let Some(key) = cache_key {
+                        cache.insert(key, png_bytes.clone());
+                    }
+                    send_chart(&bot, chat_id, thread, png_bytes).await?;
+                }
+                Err(err) => {
+                    error!("Failed to generate the chart for {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Error generating the chart :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            }
+        }
+        Command::StreakBars => {
+            let settings = match db.get_user_settings(user_id).await {
+                Ok(settings) => settings,
+                Err(err) => {
+                    error!("Failed to get the settings of the user {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            let offset = day_offset(settings.utc_offset, settings.day_end);
+            let today = Utc::now().with_timezone(&offset).date_naive();
+            let name = DisplayName::from(&user).title();
+            // The current streak depends on the date, not only on the logs
+            let cache_key = chart_cache_key(
+                &db,
+                user_id,
+                format!(
+                    "streakbars:{name}:{today}:{}",
+                    language.as_deref().unwrap_or_default()
+                ),
+            )
+            .await;
+            if let Some(png_bytes) = cache_key.as_ref().and_then(|key| cache.get(key)) {
+                send_chart(&bot, chat_id, thread, png_bytes).await?;
+                return respond(());
+            }
+            let dates = match db.get_active_days(user_id).await {
+                Ok(days) => dates_from_day_numbers(&days),
+                Err(err) => {
+                    error!("Failed to get the active days of the user {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            let (current, best) = (current_streak(&dates, today), longest_streak(&dates));
+            let theme = ChartTheme::for_user(settings.chart_theme.as_deref());
+            match render(move || {
+                generate_streak_comparison_chart(&name, current, best, language.as_deref(), theme)
+            })
+            .await
+            {
+                Ok(png_bytes) => {
+                    if let Some(key) = cache_key {
+                        cache.insert(key, png_bytes.clone());
+                    }
+                    send_chart(&bot, chat_id, thread, png_bytes).await?;
+                }
+                Err(err) => {
+                    error!("Failed to generate the chart for {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Error generating the chart :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            }
+        }
+        Command::Achievements => {
+            let data = try_join(db.get_user_settings(user_id), db.get_achievements(user_id));
+            let (settings, earned) = match data.await {
+                Ok(d) => d,
+                Err(err) => {
+                    error!("Failed to get the achievements of the user {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            let offset = user_offset(settings.utc_offset);
+            let mut text = lang.translate("Your achievements:").to_string();
+            for achievement in ACHIEVEMENTS {
+                let earned_at = earned
+                    .iter()
+                    .find(|(id, _)| id == achievement.id)
+                    .and_then(|&(_, ts)| DateTime::from_timestamp(ts, 0));
+                let line = match earned_at {
+                    Some(at) => fill(
+                        lang.translate("{} {} - {} logs, earned {}"),
+                        &[
+                            &achievement.icon,
+                            &lang.translate(achievement.name),
+                            &achievement.logs,
+                            &at.with_timezone(&offset).date_naive(),
+                        ],
+                    ),
+                    None => fill(
+                        lang.translate("🔒 {} - {} logs"),
+                        &[&lang.translate(achievement.name), &achievement.logs],
+                    ),
+                };
+                text.push('\n');
+                text.push_str(&line);
+            }
+            bot.send_message(chat_id, text)
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::Card => {
+            let data = try_join(db.get_user_settings(user_id), db.get_user_rank(user_id));
+            let (settings, rank) = match data.await {
+                Ok(d) => d,
+                Err(err) => {
+                    error!("Failed to get the profile of the user {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            let offset = day_offset(settings.utc_offset, settings.day_end);
+            let now = Utc::now().with_timezone(&offset);
+            let name = DisplayName::from(&user).title();
+            // The streak and rank change without the user's logs changing
+            let cache_key = chart_cache_key(
+                &db,
+                user_id,
+                format!(
+                    "card:{name}:{}:{rank}:{}",
+                    now.date_naive(),
+                    language.as_deref().unwrap_or_default()
+                ),
+            )
+            .await;
+            if let Some(png_bytes) = cache_key.as_ref().and_then(|key| cache.get(key)) {
+                let sent = send_chart(&bot, chat_id, thread, png_bytes).await?;
+                remember_card(&db, user_id, &sent).await;
+                return respond(());
+            }
+            // The profile's year chart and best day need every log
+            let timestamps = match db.get_all_user_timestamps(user_id).await {
+                Ok(ts) => ts,
+                Err(err) => {
+                    error!("Failed to get the profile of the user {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            let dates = local_dates(&timestamps, offset);
+            let profile = ProfileData {
+                username: name,
+                total: timestamps.len() as i64,
+                current_streak: current_streak(&dates, now.date_naive()),
+                best_streak: longest_streak(&dates),
+                rank: (!timestamps.is_empty()).then_some(rank),
+                best_day: best_day(&timestamps, offset),
+                year: now.year(),
+                timestamps,
+            };
+            let theme = user_theme(&db, user_id).await;
+            match render(move || generate_profile_card(profile, language.as_deref(), theme)).await {
+                Ok(png_bytes) => {
+                    if let Some(key) = cache_key {
+                        cache.insert(key, png_bytes.clone());
+                    }
+                    let sent = send_chart(&bot, chat_id, thread, png_bytes).await?;
+                    remember_card(&db, user_id, &sent).await;
+                }
+                Err(err) => {
+                    error!("Failed to generate the chart for {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Error generating the chart :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            }
+        }
+        Command::Leaderboard(arg) => {
+            let all_time = arg.trim().eq_ignore_ascii_case("alltime");
+            let window_start = leaderboard_window(&arg, Utc::now());
+            let category = if all_time || window_start.is_some() {
+                None
+            } else {
+                parse_category(&arg)
+            };
+            if let Some(category) = &category {
+                match db.get_categories().await {
+                    Ok(categories) if categories.iter().any(|(c, _)| c == category) => {}
+                    Ok(_) => {
+                        bot.send_message(chat_id, lang.translate("No logs in that category"))
+                            .in_topic(thread)
+                            .reply_markup(main_keyboard())
+                            .await?;
+                        return respond(());
+                    }
+                    Err(err) => {
+                        error!("Failed to get the categories: {err}");
+                        bot.send_message(chat_id, lang.translate("Database error :("))
+                            .in_topic(thread)
+                            .reply_markup(main_keyboard())
+                            .await?;
+                        return respond(());
+                    }
+                }
+            }
+            let settings = match db.get_chat_settings(chat_id.0).await {
+                Ok(settings) => settings,
+                Err(err) => {
+                    error!("Failed to get the settings for the chat {chat_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            let leaderboard = match db
+                .get_leaderboard(
+                    config.daily_cap(settings.daily_cap),
+                    category.as_deref(),
+                    settings
+                        .season_start
+                        .filter(|_| !all_time)
+                        .max(window_start),
+                    None,
+                    leaderboard_scope(&msg.chat),
+                    Some(user_id),
+                )
+                .await
+            {
+                Ok(lb) => lb,
+                Err(err) => {
+                    error!("Failed to get the leaderboard: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            let text = format_leaderboard(
+                &db,
+                &leaderboard,
+                settings.anonymous_leaderboard,
+                user.id.0 as i64,
+                lang,
+            )
+            .await;
+            bot.send_message(chat_id, text)
+                .in_topic(thread)
+                .parse_mode(ParseMode::Html)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::Rank => {
+            let data = try_join(
+                db.get_chat_settings(chat_id.0),
+                db.get_user_settings(user_id),
+            );
+            let (settings, user_settings) = match data.await {
+                Ok(d) => d,
+                Err(err) => {
+                    error!("Failed to get the settings for the chat {chat_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            if user_settings.hide_from_rankings {
+                bot.send_message(
+                    chat_id,
+                    lang.translate(
+                        "You are hidden from rankings, show yourself again with /privacy off",
+                    ),
+                )
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+                return respond(());
+            }
+            let rank = db
+                .get_leaderboard_rank(
+                    user_id,
+                    config.daily_cap(settings.daily_cap),
+                    settings.season_start,
+                    leaderboard_scope(&msg.chat),
+                )
+                .await;
+            let text = match rank {
+                Ok(Some((rank, total))) => fill(
+                    lang.translate("You are #{} of {} (top {}%)"),
+                    &[&rank, &total, &((rank * 100 + total - 1) / total)],
+                ),
+                Ok(None) => lang
+                    .translate("You are not on the leaderboard yet, log something first")
+                    .into(),
+                Err(err) => {
+                    error!("Failed to get the rank of the user {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            bot.send_message(chat_id, text)
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::Streaks => {
+            let data = try_join(
+                db.get_chat_settings(chat_id.0),
+                db.get_all_active_days(leaderboard_scope(&msg.chat)),
+            );
+            let (settings, days) = match data.await {
+                Ok(d) => d,
+                Err(err) => {
+                    error!("Failed to get the active days: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            let mut streaks = current_streaks(&days, Utc::now());
+            streaks.truncate(10);
+            let ids: Vec<i64> = streaks.iter().map(|&(id, _)| id).collect();
+            let names = match db.get_user_names(&ids).await {
+                Ok(names) => names,
+                Err(err) => {
+                    error!("Failed to get the usernames: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            let text = format_streaks(
+                &streaks,
+                &names,
+                settings.anonymous_leaderboard,
+                user.id.0 as i64,
+                lang,
+            );
+            bot.send_message(chat_id, text)
+                .in_topic(thread)
+                .parse_mode(ParseMode::Html)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::CompactStats => {
+            // Like /leaderboard, the top user of a group is among its members
+            let top = db.get_user_totals(1, leaderboard_scope(&msg.chat));
+            let data = try_join(top, db.get_global_counts());
+            let days = db.get_all_active_days(None);
+            let ((top, (users, logs)), days) = match try_join(data, days).await {
+                Ok(d) => d,
+                Err(err) => {
+                    error!("Failed to get the compact stats: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            let top = top.first().map_or("-".into(), |t| {
+                let name = DisplayName::from(t).plain();
+                format!("{name} ({})", format_thousands(t.logs))
+            });
+            let streak = top_current_streak(&days, Utc::now());
+            let text = fill(
+                lang.translate("🏆 {} | 👥 {} | 📊 {} total | 🔥 top streak {}d"),
+                &[
+                    &top,
+                    &format_thousands(users),
+                    &format_thousands(logs),
+                    &streak,
+                ],
+            );
+            bot.send_message(chat_id, text)
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::LeaderboardDay(arg) => {
+            let Ok(day) = NaiveDate::parse_from_str(arg.trim(), "%Y-%m-%d") else {
+                bot.send_message(chat_id, lang.translate("Usage: /leaderboardday 2024-06-01"))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            };
+            let data = try_join(
+                db.get_chat_settings(chat_id.0),
+                db.get_user_settings(user_id),
+            );
+            let (chat_settings, user_settings) = match data.await {
+                Ok(d) => d,
+                Err(err) => {
+                    error!("Failed to get the settings for the chat {chat_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            // The day is taken in the caller's timezone
+            let (from, to) = day_bounds(
+                day,
+                day_offset(user_settings.utc_offset, user_settings.day_end),
+            );
+            let leaderboard = match db
+                .get_leaderboard(
+                    config.daily_cap(chat_settings.daily_cap),
+                    None,
+                    Some(from),
+                    Some(to),
+                    leaderboard_scope(&msg.chat),
+                    Some(user_id),
+                )
+                .await
+            {
+                Ok(lb) => lb,
+                Err(err) => {
+                    error!("Failed to get the leaderboard for {day}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            let text = format_leaderboard(
+                &db,
+                &leaderboard,
+                chat_settings.anonymous_leaderboard,
+                user.id.0 as i64,
+                lang,
+            )
+            .await;
+            bot.send_message(chat_id, text)
+                .in_topic(thread)
+                .parse_mode(ParseMode::Html)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::Categories => {
+            let categories = match db.get_categories().await {
+                Ok(c) => c,
+                Err(err) => {
+                    error!("Failed to get the categories: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            let mut text: String = categories
+                .iter()
+                .map(|(category, logs)| format!("{category} - {logs}\n"))
+                .collect();
+            if text.is_empty() {
+                text = lang
+                    .translate("No categories yet, log one with /done <category>")
+                    .into();
+            }
+            bot.send_message(chat_id, text)
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::AnonymousLeaderboard(arg) => {
+            let Some(enabled) = parse_toggle(&arg) else {
+                bot.send_message(
+                    chat_id,
+                    lang.translate("Usage: /anonymousleaderboard on|off"),
+                )
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+                return respond(());
+            };
+            if !can_change_chat_settings(&bot, &msg.chat, user.id).await? {
+                bot.send_message(
+                    chat_id,
+                    lang.translate("Only chat admins can change this setting"),
+                )
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+                return respond(());
+            }
+            if let Err(err) = db.set_anonymous_leaderboard(chat_id.0, enabled).await {
+                error!("Failed to update the settings for the chat {chat_id}: {err}");
+                bot.send_message(chat_id, lang.translate("Database error :("))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            let text = if enabled {
+                "The leaderboard is now anonymous"
+            } else {
+                "The leaderboard now shows usernames"
+            };
+            bot.send_message(chat_id, lang.translate(text))
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::HabitMode(arg) => {
+            let Some(enabled) = parse_toggle(&arg) else {
+                bot.send_message(chat_id, lang.translate("Usage: /habitmode on|off"))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            };
+            if !can_change_chat_settings(&bot, &msg.chat, user.id).await? {
+                bot.send_message(
+                    chat_id,
+                    lang.translate("Only chat admins can change this setting"),
+                )
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+                return respond(());
+            }
+            if let Err(err) = db.set_habit_mode(chat_id.0, enabled).await {
+                error!("Failed to update the settings for the chat {chat_id}: {err}");
+                bot.send_message(chat_id, lang.translate("Database error :("))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            let text = if enabled {
+                "Habit mode is on: only the first /done of each day counts"
+            } else {
+                "Habit mode is off: every /done counts"
+            };
+            bot.send_message(chat_id, lang.translate(text))
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::Ack(arg) => {
+            let arg = arg.trim().to_lowercase();
+            let Some(acknowledgement) = Acknowledgement::ALL
+                .into_iter()
+                .find(|ack| ack.name() == arg)
+            else {
+                bot.send_message(chat_id, lang.translate("Usage: /ack reply|reaction|silent"))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            };
+            if !can_change_chat_settings(&bot, &msg.chat, user.id).await? {
+                bot.send_message(
+                    chat_id,
+                    lang.translate("Only chat admins can change this setting"),
+                )
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+                return respond(());
+            }
+            let name = (acknowledgement != Acknowledgement::Reply).then(|| acknowledgement.name());
+            if let Err(err) = db.set_acknowledgement(chat_id.0, name).await {
+                error!("Failed to update the settings for the chat {chat_id}: {err}");
+                bot.send_message(chat_id, lang.translate("Database error :("))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            let text = match acknowledgement {
+                Acknowledgement::Reply => "New logs are now acknowledged with a reply",
+                Acknowledgement::Reaction => "New logs are now acknowledged with a 👍 reaction",
+                Acknowledgement::Silent => "New logs are now recorded without an acknowledgement",
+            };
+            bot.send_message(chat_id, lang.translate(text))
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::DailyCap(arg) => {
+            let cap = match arg.trim() {
+                "off" => None,
+                n => match n.parse::<i64>() {
+                    Ok(n) if n > 0 => Some(n),
+                    _ => {
+                        bot.send_message(
+                            chat_id,
+                            lang.translate("Usage: /dailycap 5 or /dailycap off"),
+                        )
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                        return respond(());
+                    }
+                },
+            };
+            if !can_change_chat_settings(&bot, &msg.chat, user.id).await? {
+                bot.send_message(
+                    chat_id,
+                    lang.translate("Only chat admins can change this setting"),
+                )
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+                return respond(());
+            }
+            if let Err(err) = db.set_daily_cap(chat_id.0, cap).await {
+                error!("Failed to update the settings for the chat {chat_id}: {err}");
+                bot.send_message(chat_id, lang.translate("Database error :("))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            let text = match config.daily_cap(cap) {
+                Some(n) => fill(
+                    lang.translate("At most {} logs per day now count toward scores"),
+                    &[&n],
+                ),
+                None => lang.translate("All logs now count toward scores").into(),
+            };
+            bot.send_message(chat_id, text)
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::NewSeason => {
+            if !can_change_chat_settings(&bot, &msg.chat, user.id).await? {
+                bot.send_message(
+                    chat_id,
+                    lang.translate("Only chat admins can change this setting"),
+                )
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+                return respond(());
+            }
+            if let Err(err) = db.set_season_start(chat_id.0, Utc::now().timestamp()).await {
+                error!("Failed to update the settings for the chat {chat_id}: {err}");
+                bot.send_message(chat_id, lang.translate("Database error :("))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            bot.send_message(
+                chat_id,
+                "🏁 A new season has started! Scores count from now on, \
+                 use /leaderboard alltime for lifetime totals",
+            )
+            .in_topic(thread)
+            .reply_markup(main_keyboard())
+            .await?;
+        }
+        Command::TeamGoal(arg) => {
+            let goal = match arg.trim() {
+                "off" => None,
+                n => match n.parse::<i64>() {
+                    Ok(n) if n > 0 => Some(n),
+                    _ => {
+                        bot.send_message(
+                            chat_id,
+                            lang.translate("Usage: /teamgoal 1000 or /teamgoal off"),
+                        )
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                        return respond(());
+                    }
+                },
+            };
+            if !can_change_chat_settings(&bot, &msg.chat, user.id).await? {
+                bot.send_message(
+                    chat_id,
+                    lang.translate("Only chat admins can change this setting"),
+                )
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+                return respond(());
+            }
+            if let Err(err) = db.set_team_goal(chat_id.0, goal).await {
+                error!("Failed to update the settings for the chat {chat_id}: {err}");
+                bot.send_message(chat_id, lang.translate("Database error :("))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            let text = match goal {
+                Some(n) => fill(
+                    lang.translate("The team goal is now {} logs per month"),
+                    &[&n],
+                ),
+                None => lang.translate("The team goal has been removed").into(),
+            };
+            bot.send_message(chat_id, text)
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::TeamProgress => {
+            let (from, to) = utc_month_bounds(Utc::now());
+            let data = try_join(
+                db.get_chat_settings(chat_id.0),
+                db.get_chat_contributions(chat_id.0, from, to),
+            );
+            let (settings, contributions) = match data.await {
+                Ok(d) => d,
+                Err(err) => {
+                    error!("Failed to get the team progress for the chat {chat_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            let Some(goal) = settings.team_goal else {
+                bot.send_message(
+                    chat_id,
+                    lang.translate("This chat has no team goal, set one with /teamgoal"),
+                )
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+                return respond(());
+            };
+            let total: i64 = contributions.iter().map(|c| c.logs).sum();
+            let mut text = fill(
+                lang.translate("Team progress this month: {}/{} {}"),
+                &[&total, &goal, &progress_bar(total, goal)],
+            );
+            if !contributions.is_empty() {
+                text.push_str("\n\n");
+                text.push_str(lang.translate("Top contributors:"));
+            }
+            for (i, c) in contributions.iter().take(3).enumerate() {
+                let name = DisplayName::from(c).html();
+                text.push_str(&format!("\n{}. {name} - {}", i + 1, c.logs));
+            }
+            bot.send_message(chat_id, text)
+                .in_topic(thread)
+                .parse_mode(ParseMode::Html)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::Goal(arg) => {
+            let Some((count, period)) = parse_goal(&arg) else {
+                bot.send_message(
+                    chat_id,
+                    lang.translate("Usage: /goal 5 per week or /goal 20 per month (0 to remove)"),
+                )
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+                return respond(());
+            };
+            let goal = (count > 0).then_some(count);
+            let result = match period {
+                GoalPeriod::Week => db.set_weekly_goal(user_id, goal).await,
+                GoalPeriod::Month => db.set_monthly_goal(user_id, goal).await,
+            };
+            if let Err(err) = result {
+                error!(
+                    "Failed to set the {} goal for the user {user_id}: {err}",
+                    period.name()
+                );
+                bot.send_message(chat_id, lang.translate("Database error :("))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            cache.invalidate(user_id);
+            let text = match (goal, period) {
+                (Some(n), GoalPeriod::Week) => {
+                    fill(lang.translate("Your goal is now {} per week"), &[&n])
+                }
+                (Some(n), GoalPeriod::Month) => {
+                    fill(lang.translate("Your goal is now {} per month"), &[&n])
+                }
+                (None, GoalPeriod::Week) => {
+                    lang.translate("Your weekly goal has been removed").into()
+                }
+                (None, GoalPeriod::Month) => {
+                    lang.translate("Your monthly goal has been removed").into()
+                }
+            };
+            bot.send_message(chat_id, text)
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::SetWeeklyGoal(arg) => {
+            let goal = match arg.trim().parse::<i64>() {
+                Ok(0) => None,
+                Ok(n) if n > 0 => Some(n),
+                _ => {
+                    bot.send_message(
+                        chat_id,
+                        lang.translate("Usage: /setweeklygoal 5 (0 to remove)"),
+                    )
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                    return respond(());
+                }
+            };
+            if let Err(err) = db.set_weekly_goal(user_id, goal).await {
+                error!("Failed to set the weekly goal for the user {user_id}: {err}");
+                bot.send_message(chat_id, lang.translate("Database error :("))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            let text = match goal {
+                Some(n) => fill(lang.translate("Your weekly goal is now {}"), &[&n]),
+                None => lang.translate("Your weekly goal has been removed").into(),
+            };
+            bot.send_message(chat_id, text)
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::StartDate(arg) => {
+            let arg = arg.trim();
+            let date = match NaiveDate::parse_from_str(arg, "%Y-%m-%d") {
+                Ok(date) => Some(date),
+                Err(_) if arg == "off" => None,
+                Err(_) => {
+                    bot.send_message(
+                        chat_id,
+                        lang.translate("Usage: /startdate 2024-01-01 or /startdate off"),
+                    )
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                    return respond(());
+                }
+            };
+            let result = match db.get_user_settings(user_id).await {
+                Ok(settings) => {
+                    let offset = day_offset(settings.utc_offset, settings.day_end);
+                    let start = date.map(|d| local_midnight(d, offset));
+                    db.set_tracking_start(user_id, start).await
+                }
+                Err(err) => Err(err),
+            };
+            cache.invalidate(user_id);
+            if let Err(err) = result {
+                error!("Failed to set the tracking start for the user {user_id}: {err}");
+                bot.send_message(chat_id, lang.translate("Database error :("))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            let text = match date {
+                Some(date) => fill(
+                    lang.translate(
+                        "Your stats now start on {}. Earlier logs are kept, just not counted",
+                    ),
+                    &[&date],
+                ),
+                None => lang
+                    .translate("Your stats now include all your logs")
+                    .into(),
+            };
+            bot.send_message(chat_id, text)
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::Timezone(arg) => {
+            let Some(offset) = parse_utc_offset(&arg) else {
+                bot.send_message(
+                    chat_id,
+                    lang.translate("Usage: /timezone +3 or /timezone -5:30"),
+                )
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+                return respond(());
+            };
+            let updated = match db.set_utc_offset(user_id, offset).await {
+                Ok(()) => db.reset_local_day_markers(user_id).await,
+                Err(err) => Err(err),
+            };
+            cache.invalidate(user_id);
+            if let Err(err) = updated {
+                error!("Failed to set the UTC offset for the user {user_id}: {err}");
+                bot.send_message(chat_id, lang.translate("Database error :("))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            bot.send_message(
+                chat_id,
+                fill(
+                    lang.translate("Your timezone is now {}"),
+                    &[&format_utc_offset(offset)],
+                ),
+            )
+            .in_topic(thread)
+            .reply_markup(main_keyboard())
+            .await?;
+        }
+        Command::FixTimezone => {
+            // Chart images are the only materialized per-day data, everything else
+            // (scores, daily caps, streaks, weekly goals) already uses the current timezone
+            let reset = db.reset_local_day_markers(user_id).await;
+            cache.invalidate(user_id);
+            if let Err(err) = reset {
+                error!("Failed to reset the day markers for the user {user_id}: {err}");
+                bot.send_message(chat_id, lang.translate("Database error :("))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            bot.send_message(
+                chat_id,
+                "Your charts and streak reminders now follow your current timezone. \
+                 Scores, daily caps and streaks always do",
+            )
+            .in_topic(thread)
+            .reply_markup(main_keyboard())
+            .await?;
+        }
+        Command::AckMessage(arg) => {
+            let arg = arg.trim();
+            let messages = if arg.eq_ignore_ascii_case("off") {
+                Some(None)
+            } else {
+                let phrases: Vec<&str> = arg.split('|').map(str::trim).collect();
+                (arg.chars().count() <= MAX_ACK_LEN && phrases.iter().all(|p| !p.is_empty()))
+                    .then(|| Some(phrases.join("|")))
+            };
+            let Some(messages) = messages else {
+                bot.send_message(
+                    chat_id,
+                    fill(
+                        lang.translate(
+                            "Usage: /ackmessage 🔥 {today} or /ackmessage Nice!|Keep going|off, up to {} characters",
+                        ),
+                        &[&MAX_ACK_LEN],
+                    ),
+                ).in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+                return respond(());
+            };
+            if let Err(err) = db.set_ack_messages(user_id, messages.as_deref()).await {
+                error!("Failed to update the /done replies of the user {user_id}: {err}");
+                bot.send_message(chat_id, lang.translate("Database error :("))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            let text = match &messages {
+                Some(messages) => fill(
+                    lang.translate("Your /done replies are now: {}"),
+                    &[&messages.replace('|', " / ")],
+                ),
+                None => lang
+                    .translate("Your /done replies are back to the default")
+                    .to_string(),
+            };
+            bot.send_message(chat_id, text)
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::DayEnd(arg) => {
+            let arg = arg.trim();
+            let day_end = if arg.eq_ignore_ascii_case("off") {
+                Some(0)
+            } else {
+                NaiveTime::parse_from_str(arg, "%H:%M")
+                    .ok()
+                    .map(|time| i64::from(time.hour() * 60 + time.minute()))
+                    .filter(|&minute| minute <= MAX_DAY_END)
+            };
+            let Some(day_end) = day_end else {
+                bot.send_message(
+                    chat_id,
+                    lang.translate("Usage: /dayend HH:MM up to 06:00, or /dayend off"),
+                )
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+                return respond(());
+            };
+            let updated = match db.set_day_end(user_id, day_end).await {
+                Ok(()) => db.reset_local_day_markers(user_id).await,
+                Err(err) => Err(err),
+            };
+            cache.invalidate(user_id);
+            if let Err(err) = updated {
+                error!("Failed to set the day end for the user {user_id}: {err}");
+                bot.send_message(chat_id, lang.translate("Database error :("))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            let text = if day_end == 0 {
+                lang.translate("Your day now ends at midnight").to_string()
+            } else {
+                fill(
+                    lang.translate(
+                        "Your day now ends at {}, earlier logs count for the day before",
+                    ),
+                    &[&format!("{:02}:{:02}", day_end / 60, day_end % 60)],
+                )
+            };
+            bot.send_message(chat_id, text)
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::ShareSettings(arg) => {
+            let Some(enabled) = parse_toggle(&arg) else {
+                bot.send_message(chat_id, lang.translate("Usage: /sharesettings on|off"))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            };
+            if let Err(err) = db.set_share_settings(user_id, enabled).await {
+                error!("Failed to update settings sharing for the user {user_id}: {err}");
+                bot.send_message(chat_id, lang.translate("Database error :("))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            let text = if enabled {
+                "Others can now copy your settings with /copysettings"
+            } else {
+                "Your settings are no longer shared"
+            };
+            bot.send_message(chat_id, lang.translate(text))
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::Theme(arg) => {
+            let arg = arg.trim().to_lowercase();
+            let theme = match arg.as_str() {
+                "default" => None,
+                name if ChartTheme::named(name).is_some() => Some(name),
+                _ => {
+                    bot.send_message(
+                        chat_id,
+                        fill(
+                            lang.translate("Usage: /theme {}|default"),
+                            &[&ChartTheme::NAMES.join("|")],
+                        ),
+                    )
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                    return respond(());
+                }
+            };
+            if let Err(err) = db.set_chart_theme(user_id, theme).await {
+                error!("Failed to update the chart theme for the user {user_id}: {err}");
+                bot.send_message(chat_id, lang.translate("Database error :("))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            cache.invalidate(user_id);
+            bot.send_message(
+                chat_id,
+                fill(lang.translate("Your charts now use the {} theme"), &[&arg]),
+            )
+            .in_topic(thread)
+            .reply_markup(main_keyboard())
+            .await?;
+        }
+        Command::AllowCompare(arg) => {
+            let Some(enabled) = parse_toggle(&arg) else {
+                bot.send_message(chat_id, lang.translate("Usage: /allowcompare on|off"))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            };
+            if let Err(err) = db.set_allow_compare(user_id, enabled).await {
+                error!("Failed to update comparison consent for the user {user_id}: {err}");
+                bot.send_message(chat_id, lang.translate("Database error :("))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            let text = if enabled {
+                "Others can now compare their charts with yours using /compare"
+            } else {
+                "Others can no longer compare their charts with yours"
+            };
+            bot.send_message(chat_id, lang.translate(text))
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::Quiet(arg) => {
+            let Some(quiet) = parse_toggle(&arg) else {
+                bot.send_message(chat_id, lang.translate("Usage: /quiet on|off"))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            };
+            if let Err(err) = db.set_quiet(user_id, quiet).await {
+                error!("Failed to update quiet mode for the user {user_id}: {err}");
+                bot.send_message(chat_id, lang.translate("Database error :("))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            let text = lang.translate(if quiet {
+                "Reached goals, achievements and level-ups will no longer be announced"
+            } else {
+                "Reached goals, achievements and level-ups will be announced again"
+            });
+            bot.send_message(chat_id, text)
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::Language(arg) => {
+            let arg = arg.trim().to_lowercase();
+            let language = match Language::from_code(&arg) {
+                Some(language) => Some(language),
+                None if arg == "auto" => None,
+                None => {
+                    let codes: Vec<_> = Language::ALL.iter().map(|l| l.code()).collect();
+                    bot.send_message(
+                        chat_id,
+                        fill(
+                            lang.translate("Usage: /language {}|auto"),
+                            &[&codes.join("|")],
+                        ),
+                    )
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                    return respond(());
+                }
+            };
+            if let Err(err) = db.set_language(user_id, language.map(|l| l.code())).await {
+                error!("Failed to update the language for the user {user_id}: {err}");
+                bot.send_message(chat_id, lang.translate("Database error :("))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            let lang =
+                Language::for_user(language.map(|l| l.code()), user.language_code.as_deref());
+            let text = match language {
+                Some(language) => {
+                    fill(lang.translate("Replies are now in {}"), &[&language.name()])
+                }
+                None => lang
+                    .translate("Replies now follow the language of your Telegram app")
+                    .into(),
+            };
+            bot.send_message(chat_id, text)
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::Settings => match settings_menu(&db, user_id, &user).await {
+            Ok((text, buttons)) => {
+                bot.send_message(chat_id, text)
+                    .in_topic(thread)
+                    .reply_markup(buttons)
+                    .await?;
+            }
+            Err(err) => {
+                error!("Failed to get settings for the user {user_id}: {err}");
+                bot.send_message(chat_id, lang.translate("Database error :("))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+            }
+        },
+        Command::Privacy(arg) => {
+            let Some(hidden) = parse_toggle(&arg) else {
+                bot.send_message(chat_id, lang.translate("Usage: /privacy on|off"))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            };
+            if let Err(err) = db.set_hide_from_rankings(user_id, hidden).await {
+                error!("Failed to update the privacy of the user {user_id}: {err}");
+                bot.send_message(chat_id, lang.translate("Database error :("))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            let text = lang.translate(if hidden {
+                "You are now hidden from leaderboards, streak rankings and comparisons, your own stats are unchanged"
+            } else {
+                "You are now shown on leaderboards and streak rankings again"
+            });
+            bot.send_message(chat_id, text)
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::PlainLog(arg) => {
+            let Some(enabled) = parse_toggle(&arg) else {
+                bot.send_message(chat_id, lang.translate("Usage: /plainlog on|off"))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            };
+            if let Err(err) = db.set_plain_logging(user_id, enabled).await {
+                error!("Failed to update plain logging for the user {user_id}: {err}");
+                bot.send_message(chat_id, lang.translate("Database error :("))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            let text = lang.translate(if enabled {
+                "Sending done, +1 or ✅ to me in a private chat now logs like /done"
+            } else {
+                "Only /done logs again"
+            });
+            bot.send_message(chat_id, text)
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::Remind(arg) => {
+            let arg = arg.trim();
+            let minute = if arg.eq_ignore_ascii_case("off") {
+                None
+            } else {
+                match NaiveTime::parse_from_str(arg, "%H:%M") {
+                    Ok(time) => Some(i64::from(time.hour() * 60 + time.minute())),
+                    Err(_) => {
+                        bot.send_message(
+                            chat_id,
+                            lang.translate("Usage: /remind HH:MM or /remind off"),
+                        )
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                        return respond(());
+                    }
+                }
+            };
+            if let Err(err) = db.set_reminder(user_id, minute).await {
+                error!("Failed to update the reminder for the user {user_id}: {err}");
+                bot.send_message(chat_id, lang.translate("Database error :("))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            let text = match minute {
+                Some(_) => fill(
+                    lang.translate(
+                        "You'll get a private message at {} (your time, see /timezone) on days you haven't logged",
+                    ),
+                    &[&arg],
+                ),
+                None => lang.translate("Reminder turned off").into(),
+            };
+            bot.send_message(chat_id, text)
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::Digest(arg) => {
+            let Some(enabled) = parse_toggle(&arg) else {
+                bot.send_message(chat_id, lang.translate("Usage: /digest on|off"))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            };
+            let result = if enabled {
+                match db.get_user_settings(user_id).await {
+                    Ok(settings) => {
+                        let monday = local_monday(Utc::now(), user_offset(settings.utc_offset));
+                        db.subscribe_digest(user_id, &monday.to_string()).await
+                    }
+                    Err(err) => Err(err),
+                }
+            } else {
+                db.unsubscribe_digest(user_id).await
+            };
+            if let Err(err) = result {
+                error!("Failed to update the digest subscription for the user {user_id}: {err}");
+                bot.send_message(chat_id, lang.translate("Database error :("))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            let text = if enabled {
+                "You'll get a summary of your week every Monday in a private message"
+            } else {
+                "You won't get weekly summaries anymore"
+            };
+            bot.send_message(chat_id, lang.translate(text))
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::CopySettings(arg) => {
+            let source_id = match resolve_mention(&db, &msg, &arg).await {
+                Ok(Some(id)) => id,
+                Ok(None) => {
+                    bot.send_message(
+                        chat_id,
+                        lang.translate(
+                            "Usage: /copysettings @user (they must have used the bot before)",
+                        ),
+                    )
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                    return respond(());
+                }
+                Err(err) => {
+                    error!("Failed to resolve the mention {arg:?}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            let copied = db.copy_shared_settings(source_id, user_id).await;
+            cache.invalidate(user_id);
+            let text = match copied {
+                Ok(Some(settings)) => {
+                    let none = lang.translate("none");
+                    fill(
+                        lang.translate(
+                            "Copied their settings:\ntimezone: {}\nend of day: {}\nweekly goal: {}\nmonthly goal: {}\nchart theme: {}",
+                        ),
+                        &[
+                            &format_utc_offset(settings.utc_offset),
+                            &format!("{:02}:{:02}", settings.day_end / 60, settings.day_end % 60),
+                            &settings.weekly_goal.map_or(none.into(), |g| g.to_string()),
+                            &settings.monthly_goal.map_or(none.into(), |g| g.to_string()),
+                            &settings
+                                .chart_theme
+                                .as_deref()
+                                .unwrap_or(lang.translate("default")),
+                        ],
+                    )
+                }
+                Ok(None) => lang
+                    .translate("This user doesn't share their settings")
+                    .into(),
+                Err(err) => {
+                    error!("Failed to copy settings from {source_id} to {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            bot.send_message(chat_id, text)
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::Watch(arg) => {
+            let watched_id = match resolve_mention(&db, &msg, &arg).await {
+                Ok(Some(id)) if id == user_id => {
+                    bot.send_message(chat_id, lang.translate("You can't watch yourself"))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+                Ok(Some(id)) => id,
+                Ok(None) => {
+                    bot.send_message(
+                        chat_id,
+                        lang.translate("Usage: /watch @user (they must have used the bot before)"),
+                    )
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                    return respond(());
+                }
+                Err(err) => {
+                    error!("Failed to resolve the mention {arg:?}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            let requested = match db.request_watch(user_id, watched_id).await {
+                Ok(()) => {
+                    try_join(
+                        db.get_telegram_id(watched_id),
+                        db.get_user_settings(watched_id),
+                    )
+                    .await
+                }
+                Err(err) => Err(err),
+            };
+            // The request is a private message to them, so it is in their language
+            let (watched_tg_id, watched_lang) = match requested {
+                Ok((Some(id), settings)) => (
+                    UserId(id as u64),
+                    Language::for_user(settings.language.as_deref(), None),
+                ),
+                Ok((None, _)) => return respond(()),
+                Err(err) => {
+                    error!("Failed to request watching {watched_id} for {user_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            let buttons = InlineKeyboardMarkup::new(vec![vec![
+                InlineKeyboardButton::callback(
+                    watched_lang.translate("Allow"),
+                    CallbackAction::ApproveWatch(user_id).encode(watched_tg_id),
+                ),
+                InlineKeyboardButton::callback(
+                    watched_lang.translate("Decline"),
+                    CallbackAction::DeclineWatch(user_id).encode(watched_tg_id),
+                ),
+            ]]);
+            let asked = bot
+                .send_message(
+                    watched_tg_id,
+                    fill(
+                        watched_lang.translate("{} wants to be notified about your milestones"),
+                        &[&DisplayName::from(&user).plain()],
+                    ),
+                )
+                .reply_markup(buttons)
+                .await;
+            let text = match asked {
+                Ok(_) => "Request sent, you'll be notified once they allow it",
+                Err(err) => {
+                    debug!("Failed to ask {watched_tg_id} for watching approval: {err}");
+                    "Couldn't ask them, they need to start a private chat with the bot first"
+                }
+            };
+            bot.send_message(chat_id, lang.translate(text))
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::Unwatch(arg) => {
+            let watched_id = match resolve_mention(&db, &msg, &arg).await {
+                Ok(Some(id)) => id,
+                Ok(None) => {
+                    bot.send_message(chat_id, lang.translate("Usage: /unwatch @user"))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+                Err(err) => {
+                    error!("Failed to resolve the mention {arg:?}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            let text = match db.delete_watch(user_id, watched_id).await {
+                Ok(true) => "You no longer watch them",
+                Ok(false) => "You weren't watching them",
+                Err(err) => {
+                    error!("Failed to stop {user_id} watching {watched_id}: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            bot.send_message(chat_id, lang.translate(text))
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::Maintenance => {
+            if !config.is_admin(user.id.0 as i64) {
+                bot.send_message(chat_id, lang.translate("This command is for admins only"))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            audit(&db, &config, user.id, "maintenance", None).await;
+            bot.send_message(
+                chat_id,
+                lang.translate("Maintenance started, this may take a while"),
+            )
+            .in_topic(thread)
+            .reply_markup(main_keyboard())
+            .await?;
+            // VACUUM locks the database, so report back from a separate task
+            tokio::spawn(async move {
+                info!("Database maintenance started by {}", user.id);
+                let text = match db.maintenance().await {
+                    Ok((before, after)) => {
+                        info!("Database maintenance finished: {before} -> {after} bytes");
+                        fill(
+                            lang.translate("Maintenance finished\nDatabase size: {} KiB -> {} KiB"),
+                            &[
+                                &format!("{:.1}", before as f64 / 1024.0),
+                                &format!("{:.1}", after as f64 / 1024.0),
+                            ],
+                        )
+                    }
+                    Err(err) => {
+                        error!("Database maintenance failed: {err}");
+                        lang.translate("Maintenance failed :(").into()
+                    }
+                };
+                if let Err(err) = bot
+                    .send_message(chat_id, text)
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await
+                {
+                    error!("Failed to report the maintenance result: {err}");
+                }
+            });
+        }
+        Command::MaintenanceMode(arg) => {
+            if !config.is_admin(user.id.0 as i64) {
+                bot.send_message(chat_id, lang.translate("This command is for admins only"))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            let Some(enabled) = parse_toggle(&arg) else {
+                bot.send_message(chat_id, lang.translate("Usage: /maintenancemode on|off"))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            };
+            audit(&db, &config, user.id, "maintenancemode", Some(&arg)).await;
+            maintenance.set(enabled);
+            info!(
+                "Maintenance mode turned {} by {}",
+                if enabled { "on" } else { "off" },
+                user.id
+            );
+            let text = if enabled {
+                "Maintenance mode is on, only admins can use the bot"
+            } else {
+                "Maintenance mode is off"
+            };
+            bot.send_message(chat_id, lang.translate(text))
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::Percentiles => {
+            if !config.is_admin(user.id.0 as i64) {
+                bot.send_message(chat_id, lang.translate("This command is for admins only"))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            let counts = match db.get_user_log_counts().await {
+                Ok(c) => c,
+                Err(err) => {
+                    error!("Failed to get the log counts per user: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            let mut text = fill(lang.translate("Users with logs: {}"), &[&counts.len()]);
+            for p in [50, 75, 90, 99] {
+                if let Some(value) = percentile(&counts, p) {
+                    text.push('\n');
+                    text.push_str(&fill(lang.translate("p{}: {} logs"), &[&p, &value]));
+                }
+            }
+            if let Some(max) = counts.last() {
+                text.push('\n');
+                text.push_str(&fill(lang.translate("max: {} logs"), &[max]));
+            }
+            bot.send_message(chat_id, text)
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::Audit => {
+            if !config.is_admin(user.id.0 as i64) {
+                bot.send_message(chat_id, lang.translate("This command is for admins only"))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            let entries = match db.get_audit_entries(AUDIT_ENTRIES_SHOWN).await {
+                Ok(e) => e,
+                Err(err) => {
+                    error!("Failed to get the audit log: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            let mut text = String::new();
+            for entry in entries {
+                let time = DateTime::from_timestamp(entry.timestamp, 0).map_or_else(
+                    || entry.timestamp.to_string(),
+                    |t| t.format("%F %R").to_string(),
+                );
+                let target = entry.target.map(|t| format!(" {t}")).unwrap_or_default();
+                text.push_str(&format!(
+                    "{time} {}: /{}{target}\n",
+                    entry.actor_telegram_id, entry.action
+                ));
+            }
+            if text.is_empty() {
+                let empty = if config.audit_log {
+                    "The audit log is empty"
+                } else {
+                    "The audit log is empty, enable it with AUDIT_LOG=true"
+                };
+                text = lang.translate(empty).into();
+            }
+            bot.send_message(chat_id, text)
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::Snapshot => {
+            if !config.is_admin(user.id.0 as i64) {
+                bot.send_message(chat_id, lang.translate("This command is for admins only"))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            audit(&db, &config, user.id, "snapshot", None).await;
+            let json = match build_snapshot(&db, config.daily_cap).await {
+                Ok(snapshot) => serde_json::to_vec_pretty(&snapshot),
+                Err(err) => {
+                    error!("Failed to build the stats snapshot: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            match json {
+                Ok(bytes) => {
+                    bot.send_document(chat_id, InputFile::memory(bytes).file_name("snapshot.json"))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                }
+                Err(err) => {
+                    error!("Failed to serialize the stats snapshot: {err}");
+                    bot.send_message(chat_id, lang.translate("Error exporting the stats :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                }
+            }
+        }
+        Command::Diagnose => {
+            if !config.is_admin(user.id.0 as i64) {
+                bot.send_message(chat_id, lang.translate("This command is for admins only"))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            let mut report = String::from(lang.translate("Diagnostics:"));
+            let start = Instant::now();
+            let result = db.get_global_counts().await.map(|_| ());
+            report.push_str(&diagnostic_line(
+                lang.translate("Database read"),
+                start,
+                result,
+            ));
+            let start = Instant::now();
+            let result = db.check_writes(user_id).await;
+            report.push_str(&diagnostic_line(
+                lang.translate("Database write"),
+                start,
+                result,
+            ));
+            let start = Instant::now();
+            let theme = ChartTheme::for_user(None);
+            let result =
+                render(move || generate_personal_hourly_chart("diagnose", [1; 24], None, theme))
+                    .await
+                    .map(|_| ());
+            report.push_str(&diagnostic_line(
+                lang.translate("Chart generation"),
+                start,
+                result,
+            ));
+            let start = Instant::now();
+            let result = bot.get_me().await.map(|_| ()).map_err(Into::into);
+            report.push_str(&diagnostic_line(
+                lang.translate("Telegram API"),
+                start,
+                result,
+            ));
+            bot.send_message(chat_id, report)
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::DumpSchema => {
+            if !config.is_admin(user.id.0 as i64) {
+                bot.send_message(chat_id, lang.translate("This command is for admins only"))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            audit(&db, &config, user.id, "dumpschema", None).await;
+            let data = try_join(db.get_applied_migrations(), db.get_schema());
+            let (migrations, schema) = match data.await {
+                Ok(d) => d,
+                Err(err) => {
+                    error!("Failed to read the database schema: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            let mut text = format!("{}\n", lang.translate("Migrations:"));
+            for (version, description, success) in migrations {
+                let status = if success {
+                    ""
+                } else {
+                    lang.translate(" (failed)")
+                };
+                text.push_str(&format!("{version} {description}{status}\n"));
+            }
+            text.push_str(&format!("\n{}\n", lang.translate("Schema:")));
+            for sql in schema {
+                text.push_str(&format!("{sql};\n\n"));
+            }
+            if text.chars().count() > MAX_MESSAGE_LEN {
+                bot.send_document(chat_id, InputFile::memory(text).file_name("schema.txt"))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+            } else {
+                bot.send_message(chat_id, text)
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+            }
+        }
+        Command::MergeDuplicates => {
+            if !config.is_admin(user.id.0 as i64) {
+                bot.send_message(chat_id, lang.translate("This command is for admins only"))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            if !db.has_exact_timestamps() {
+                bot.send_message(
+                    chat_id,
+                    lang.translate(
+                        "Duplicates can't be told apart from separate logs with rounded timestamps",
+                    ),
+                )
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+                return respond(());
+            }
+            audit(&db, &config, user.id, "mergeduplicates", None).await;
+            let removed = match db.delete_duplicate_logs().await {
+                Ok(n) => n,
+                Err(err) => {
+                    error!("Failed to delete duplicate logs: {err}");
+                    bot.send_message(chat_id, lang.translate("Database error :("))
+                        .in_topic(thread)
+                        .reply_markup(main_keyboard())
+                        .await?;
+                    return respond(());
+                }
+            };
+            if removed > 0 {
+                info!("Removed {removed} duplicate logs");
+                cache.clear();
+            }
+            bot.send_message(
+                chat_id,
+                fill(lang.translate("Removed {} duplicate logs"), &[&removed]),
+            )
+            .in_topic(thread)
+            .reply_markup(main_keyboard())
+            .await?;
+        }
+        Command::ImportUrl(arg) => {
+            if !config.url_import {
+                bot.send_message(chat_id, lang.translate("Importing from URLs is disabled"))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            let Ok(url) = arg.trim().parse::<Url>() else {
+                bot.send_message(
+                    chat_id,
+                    lang.translate("Usage: /importurl https://example.com/logs.csv"),
+                )
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+                return respond(());
+            };
+            audit(&db, &config, user.id, "importurl", Some(url.as_str())).await;
+            let csv = match fetch_csv(&url).await {
+                Ok(csv) => csv,
+                Err(err) => {
+                    info!("Failed to download {url} for the user {user_id}: {err}");
+                    bot.send_message(
+                        chat_id,
+                        fill(lang.translate("Couldn't download the file: {}"), &[&err]),
+                    )
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                    return respond(());
+                }
+            };
+            let (timestamps, skipped) = parse_timestamps(&csv);
+            if let Err(err) = db.insert_logs(user_id, &timestamps).await {
+                error!("Failed to import logs for the user {user_id}: {err}");
+                bot.send_message(chat_id, lang.translate("Database error :("))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            cache.invalidate(user_id);
+            bot.send_message(
+                chat_id,
+                fill(
+                    lang.translate("Imported {} logs, skipped {} rows"),
+                    &[&timestamps.len(), &skipped],
+                ),
+            )
+            .in_topic(thread)
+            .reply_markup(main_keyboard())
+            .await?;
+        }
+        Command::Import => {
+            let Some(document) = msg.reply_to_message().and_then(|m| m.document()) else {
+                bot.send_message(
+                    chat_id,
+                    lang.translate("Usage: reply to a CSV or JSON file with /import"),
+                )
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+                return respond(());
+            };
+            let contents = match fetch_document(&bot, document).await {
+                Ok(contents) => contents,
+                Err(err) => {
+                    info!("Failed to download a document for the user {user_id}: {err}");
+                    bot.send_message(
+                        chat_id,
+                        fill(lang.translate("Couldn't download the file: {}"), &[&err]),
+                    )
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                    return respond(());
+                }
+            };
+            let parsed = if is_json(document) {
+                parse_json_timestamps(&contents)
+            } else {
+                Ok(parse_timestamps(&contents))
+            };
+            let (timestamps, skipped) = match parsed {
+                Ok(parsed) => parsed,
+                Err(err) => {
+                    bot.send_message(
+                        chat_id,
+                        fill(lang.translate("Couldn't read the file: {}"), &[&err]),
+                    )
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                    return respond(());
+                }
+            };
+            if let Err(err) = db.insert_logs(user_id, &timestamps).await {
+                error!("Failed to import logs for the user {user_id}: {err}");
+                bot.send_message(chat_id, lang.translate("Database error :("))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            cache.invalidate(user_id);
+            bot.send_message(
+                chat_id,
+                fill(
+                    lang.translate("Imported {} logs, skipped {} rows"),
+                    &[&timestamps.len(), &skipped],
+                ),
+            )
+            .in_topic(thread)
+            .reply_markup(main_keyboard())
+            .await?;
+        }
+        Command::Export => {
+            let csv = InputFile::read(export_csv(db.clone(), user_id)).file_name("logs.csv");
+            bot.send_document(chat_id, csv)
+                .in_topic(thread)
+                .reply_markup(main_keyboard())
+                .await?;
+        }
+        Command::ClearToday => {
+            let buttons = InlineKeyboardMarkup::new(vec![vec![
+                InlineKeyboardButton::callback(
+                    lang.translate("Yes, clear today"),
+                    CallbackAction::ClearToday.encode(user.id),
+                ),
+                InlineKeyboardButton::callback(
+                    lang.translate("Cancel"),
+                    CallbackAction::Cancel.encode(user.id),
+                ),
+            ]]);
+            bot.send_message(
+                chat_id,
+                lang.translate("Delete all of your logs from today?"),
+            )
+            .in_topic(thread)
+            .reply_markup(buttons)
+            .await?;
+        }
+        Command::Delete => {
+            if !msg.chat.is_private() {
+                bot.send_message(chat_id, lang.translate(DELETE_PRIVATE_TEXT))
+                    .in_topic(thread)
+                    .reply_markup(main_keyboard())
+                    .await?;
+                return respond(());
+            }
+            let buttons = InlineKeyboardMarkup::new(vec![vec![
+                InlineKeyboardButton::callback(
+                    lang.translate("Yes, delete everything"),
+                    CallbackAction::Delete.encode(user.id),
+                ),
+                InlineKeyboardButton::callback(
+                    lang.translate("Cancel"),
+                    CallbackAction::Cancel.encode(user.id),
+                ),
+            ]]);
+            bot.send_message(
+                chat_id,
+                lang.translate("Delete all of your logs and settings for good?"),
+            )
+            .in_topic(thread)
+            .reply_markup(buttons)
+            .await?;
+        }
+    }
+    respond(())
+}
+
+/// Waits for the owner to send the confirmation phrase in the chat, cancelling after a timeout.
+/// Returns the prompt to show.
+async fn start_delete_confirmation(
+    bot: Bot,
+    dialogue: DeleteDialogue,
+    owner: UserId,
+    chat_id: ChatId,
+    thread: Option<ThreadId>,
+    lang: Language,
+) -> anyhow::Result<String> {
+    let state = DeleteState::AwaitingConfirmation {
+        owner,
+        requested_at: Utc::now(),
+    };
+    dialogue.update(state.clone()).await?;
+    tokio::spawn(async move {
+        tokio::time::sleep(DELETE_CONFIRMATION_TIMEOUT).await;
+        if !matches!(dialogue.get().await, Ok(Some(current)) if current == state) {
+            return;
+        }
+        if let Err(err) = dialogue.exit().await {
+            error!("Failed to reset the delete confirmation in the chat {chat_id}: {err}");
+        }
+        let text = lang.translate("No confirmation received, your data has not been deleted");
+        if let Err(err) = bot
+            .send_message(chat_id, text)
+            .in_topic(thread)
+            .reply_markup(main_keyboard())
+            .await
+        {
+            error!("Failed to send the delete timeout to the chat {chat_id}: {err}");
+        }
+    });
+    Ok(fill(
+        lang.translate(
+            "This deletes all of your logs and settings for good. \
+             Send {} within {} seconds to confirm",
+        ),
+        &[
+            &DELETE_CONFIRMATION_PHRASE,
+            &DELETE_CONFIRMATION_TIMEOUT.as_secs(),
         ],
-    ])
-    .resize_keyboard();
-    ReplyMarkup::Keyboard(keyboard)
-}
-
-pub async fn run_bot(database: Database) -> anyhow::Result<()> {
-    let bot = Bot::from_env();
-
-    let handler = Update::filter_message()
-        .filter_command::<Command>()
-        .endpoint(handle_command);
-    Dispatcher::builder(bot, handler)
-        .dependencies(dptree::deps![database])
-        .enable_ctrlc_handler()
-        .build()
-        .dispatch()
-        .await;
-    Ok(())
+    ))
 }
 
-async fn handle_command(
+/// Handles the message following `/delete`, deleting the user's data if it is the
+/// confirmation phrase and cancelling otherwise.
+#[allow(clippy::too_many_arguments)]
+async fn handle_delete_confirmation(
     bot: Bot,
     msg: Message,
-    command: Command,
     db: Database,
+    config: Config,
+    cache: ChartCache,
+    maintenance: MaintenanceMode,
+    dialogue: DeleteDialogue,
+    (owner, requested_at): (UserId, DateTime<Utc>),
 ) -> ResponseResult<()> {
-    let user = match msg.from {
-        Some(u) => u,
-        None => return respond(()),
+    let Some(user) = msg.from.as_ref().filter(|u| u.id == owner) else {
+        return respond(());
     };
     let chat_id = msg.chat.id;
-    let user_id = match db.get_user_id(user.id.0 as i64).await {
+    let thread = topic(&msg);
+    // The user's data, their language choice included, is about to be deleted
+    let lang = Language::for_user(None, user.language_code.as_deref());
+    // The confirmation stays pending and times out if maintenance outlasts it
+    if maintenance.is_enabled() && !config.is_admin(user.id.0 as i64) {
+        bot.send_message(chat_id, lang.translate(MAINTENANCE_TEXT))
+            .in_topic(thread)
+            .reply_markup(main_keyboard())
+            .await?;
+        return respond(());
+    }
+    if let Err(err) = dialogue.exit().await {
+        error!("Failed to reset the delete confirmation in the chat {chat_id}: {err}");
+    }
+    let expired = Utc::now() - requested_at
+        > chrono::Duration::from_std(DELETE_CONFIRMATION_TIMEOUT).unwrap_or_default();
+    if expired || msg.text().map(str::trim) != Some(DELETE_CONFIRMATION_PHRASE) {
+        bot.send_message(
+            chat_id,
+            lang.translate("Deletion cancelled, your data has not been deleted"),
+        )
+        .in_topic(thread)
+        .reply_markup(main_keyboard())
+        .await?;
+        return respond(());
+    }
+    let user_id = match register_user(&db, user).await {
         Ok(id) => id,
         Err(err) => {
             error!("Failed to get user ID from the DB: {err}");
-            bot.send_message(chat_id, "Database error :(")
+            bot.send_message(chat_id, lang.translate("Database error :("))
+                .in_topic(thread)
                 .reply_markup(main_keyboard())
                 .await?;
             return respond(());
         }
     };
+    let deleted = db.delete_user_data(user_id).await;
+    cache.invalidate(user_id);
+    if let Err(err) = deleted {
+        error!("Failed to delete data for the user {user_id}: {err}");
+        bot.send_message(chat_id, lang.translate("Database error :("))
+            .in_topic(thread)
+            .reply_markup(main_keyboard())
+            .await?;
+        return respond(());
+    }
+    bot.send_message(chat_id, lang.translate("All your data has been deleted"))
+        .in_topic(thread)
+        .reply_markup(main_keyboard())
+        .await?;
+    respond(())
+}
 
-    match command {
-        Command::Start => {
-            bot.send_message(chat_id, &Command::descriptions().to_string())
-                .reply_markup(main_keyboard())
-                .await?;
+/// Keeps the file ID of a `/card` sent as a photo, so it can be shared from inline queries.
+async fn remember_card(db: &Database, user_id: i64, sent: &Message) {
+    let Some(photo) = sent.photo().and_then(|sizes| sizes.last()) else {
+        return;
+    };
+    if let Err(err) = db.set_card_file_id(user_id, &photo.file.id.0).await {
+        error!("Failed to remember the card of the user {user_id}: {err}");
+    }
+}
+
+/// Answers `@bot stats` typed in any chat with the user's counts and streak, and their
+/// latest `/card` if they have one, to share in that chat.
+async fn handle_inline_query(
+    bot: Bot,
+    q: InlineQuery,
+    db: Database,
+    config: Config,
+    maintenance: MaintenanceMode,
+) -> ResponseResult<()> {
+    let query = q.query.trim().to_lowercase();
+    let paused = maintenance.is_enabled() && !config.is_admin(q.from.id.0 as i64);
+    if paused || !"stats".starts_with(&query) {
+        bot.answer_inline_query(q.id, Vec::new()).await?;
+        return respond(());
+    }
+    let user_id = match register_user(&db, &q.from).await {
+        Ok(id) => id,
+        Err(err) => {
+            error!("Failed to get the user ID for {}: {err}", q.from.id);
+            return respond(());
         }
-        Command::Done => {
-            let ts = msg.date.timestamp();
-            if let Err(err) = db.insert_log(user_id, ts).await {
+    };
+    let lang = user_language(&db, user_id, &q.from).await;
+    let data = try_join3(
+        db.get_user_settings(user_id),
+        db.get_count_between(user_id, i64::MIN, i64::MAX),
+        db.get_card_file_id(user_id),
+    );
+    let (settings, total, card) = match data.await {
+        Ok(d) => d,
+        Err(err) => {
+            error!("Failed to get the stats of the user {user_id} to share: {err}");
+            return respond(());
+        }
+    };
+    let (today, _, streak) = match recent_counts(&db, user_id, &settings).await {
+        Ok(counts) => counts,
+        Err(err) => {
+            error!("Failed to count the logs of the user {user_id}: {err}");
+            return respond(());
+        }
+    };
+    let streak = match streak {
+        1 => lang.translate("1 day").to_string(),
+        n => fill(lang.translate("{} days"), &[&n]),
+    };
+    let text = fill(
+        lang.translate("📊 {}: {} logs, {} today, streak: {}"),
+        &[&DisplayName::from(&q.from).plain(), &total, &today, &streak],
+    );
+    let content = InputMessageContent::Text(InputMessageContentText::new(text.clone()));
+    let mut results = vec![InlineQueryResult::Article(
+        InlineQueryResultArticle::new("stats", lang.translate("Share my stats"), content)
+            .description(text),
+    )];
+    if let Some(file_id) = card {
+        results.push(InlineQueryResult::CachedPhoto(
+            InlineQueryResultCachedPhoto::new("card", FileId(file_id))
+                .title(lang.translate("Share my latest /card")),
+        ));
+    }
+    // The counts change with every log
+    bot.answer_inline_query(q.id, results)
+        .is_personal(true)
+        .cache_time(0)
+        .await?;
+    respond(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn handle_callback(
+    bot: Bot,
+    q: CallbackQuery,
+    db: Database,
+    config: Config,
+    cache: ChartCache,
+    maintenance: MaintenanceMode,
+    storage: Arc<InMemStorage<DeleteState>>,
+    pending: PendingLogs,
+) -> ResponseResult<()> {
+    if maintenance.is_enabled() && !config.is_admin(q.from.id.0 as i64) {
+        let lang = Language::for_user(None, q.from.language_code.as_deref());
+        bot.answer_callback_query(q.id)
+            .text(lang.translate(MAINTENANCE_TEXT))
+            .await?;
+        return respond(());
+    }
+    let Some((action, owner)) = q.data.as_deref().and_then(CallbackAction::parse) else {
+        bot.answer_callback_query(q.id).await?;
+        return respond(());
+    };
+    let lang = match db.find_user_by_telegram_id(q.from.id.0 as i64).await {
+        Ok(Some(user_id)) => user_language(&db, user_id, &q.from).await,
+        Ok(None) => Language::for_user(None, q.from.language_code.as_deref()),
+        Err(err) => {
+            error!("Failed to find the user {}: {err}", q.from.id);
+            Language::for_user(None, q.from.language_code.as_deref())
+        }
+    };
+    if owner != q.from.id {
+        bot.answer_callback_query(q.id)
+            .text(lang.translate("This button isn't for you"))
+            .await?;
+        return respond(());
+    }
+    bot.answer_callback_query(q.id.clone()).await?;
+    let Some(message) = q.message else {
+        return respond(());
+    };
+    let (chat_id, message_id) = (message.chat().id, message.id());
+    let thread = message.regular_message().and_then(topic);
+
+    let text = match action {
+        CallbackAction::Cancel => lang.translate("Cancelled").to_string(),
+        CallbackAction::LogAnyway => {
+            let user_id = match register_user(&db, &q.from).await {
+                Ok(id) => id,
+                Err(err) => {
+                    error!("Failed to get user ID from the DB: {err}");
+                    bot.edit_message_text(chat_id, message_id, lang.translate("Database error :("))
+                        .await?;
+                    return respond(());
+                }
+            };
+            let Some(log) = pending.take(user_id) else {
+                bot.edit_message_text(
+                    chat_id,
+                    message_id,
+                    lang.translate("This confirmation has expired, send /done again"),
+                )
+                .await?;
+                return respond(());
+            };
+            let inserted = db
+                .insert_log(
+                    user_id,
+                    log.chat_id,
+                    log.timestamp,
+                    log.category.as_deref(),
+                    log.note.as_deref(),
+                )
+                .await;
+            if let Err(err) = inserted {
                 error!("Failed to insert a log for the user {user_id}: {err}");
-                bot.send_message(chat_id, "Database error :(")
-                    .reply_markup(main_keyboard())
+                bot.edit_message_text(chat_id, message_id, lang.translate("Database error :("))
                     .await?;
                 return respond(());
             }
-            bot.send_message(chat_id, "👍")
-                .reply_markup(main_keyboard())
+            cache.invalidate(user_id);
+            bot.edit_message_text(chat_id, message_id, log_reply(&db, user_id, lang).await)
                 .await?;
+            celebrate_log(&bot, &db, ChatId(log.chat_id), thread, user_id, lang).await;
+            if let Err(err) = notify_watchers(&bot, &db, user_id, &q.from).await {
+                error!("Failed to notify the watchers of the user {user_id}: {err}");
+            }
+            return respond(());
         }
-        Command::Stats => {
-            let count = match db.get_user_stats(user_id).await {
-                Ok(c) => c,
+        CallbackAction::AnnualYear(year, category) => {
+            let name = chart_title(DisplayName::from(&q.from).title(), &category);
+            let chart = match register_user(&db, &q.from).await {
+                Ok(user_id) => {
+                    let language = user_language_code(&db, user_id, &q.from).await;
+                    annual_chart(&db, &cache, user_id, name, year, category.clone(), language).await
+                }
+                Err(err) => Err(err),
+            };
+            match chart {
+                Ok(png_bytes) => {
+                    let media =
+                        InputMedia::Photo(InputMediaPhoto::new(InputFile::memory(png_bytes)));
+                    bot.edit_message_media(chat_id, message_id, media)
+                        .reply_markup(annual_buttons(year, category.as_deref(), owner))
+                        .await?;
+                    return respond(());
+                }
                 Err(err) => {
-                    error!("Failed to get stats for the user {user_id}: {err}");
-                    bot.send_message(chat_id, "Database error :(")
+                    error!(
+                        "Failed to generate the annual chart for {}: {err}",
+                        q.from.id
+                    );
+                    bot.send_message(chat_id, lang.translate("Error generating the chart :("))
+                        .in_topic(thread)
                         .reply_markup(main_keyboard())
                         .await?;
                     return respond(());
                 }
-            };
-            bot.send_message(chat_id, format!("Your score: {count}"))
-                .reply_markup(main_keyboard())
-                .await?;
+            }
         }
-        Command::AnnualStats => {
-            let timestamps = match db.get_all_user_timestamps(user_id).await {
-                Ok(ts) => ts,
-                Err(err) => {
-                    error!("Failed to get timestamps for the user {user_id}: {err}");
-                    bot.send_message(chat_id, "Database error :(")
-                        .reply_markup(main_keyboard())
+        CallbackAction::Menu(page) => {
+            match menu_page(&db, &config, message.chat(), &q.from, page).await {
+                Ok((text, buttons)) => {
+                    bot.edit_message_text(chat_id, message_id, text)
+                        .parse_mode(ParseMode::Html)
+                        .reply_markup(buttons)
                         .await?;
                     return respond(());
                 }
-            };
-            let username = match bot.get_chat(user.id).await {
-                Ok(chat) => chat.username().map(|u| u.to_string()),
                 Err(err) => {
-                    debug!("Failed to get the username for {user_id}: {err}");
-                    None
+                    error!("Failed to show the menu to {}: {err}", q.from.id);
+                    lang.translate("Database error :(").into()
                 }
-            };
-            let name = username.unwrap_or_else(|| user.id.to_string());
-            match generate_personal_annual_chart(&name, timestamps, None) {
+            }
+        }
+        CallbackAction::MenuChart(chart) => {
+            match menu_chart(&db, &cache, &q.from, chart).await {
                 Ok(png_bytes) => {
-                    bot.send_photo(chat_id, InputFile::memory(png_bytes))
-                        .await?;
+                    send_chart(&bot, chat_id, thread, png_bytes).await?;
                 }
                 Err(err) => {
-                    error!("Failed to generate the chart for {user_id}: {err}");
-                    bot.send_message(chat_id, "Error generating the chart :(")
+                    error!("Failed to generate the chart for {}: {err}", q.from.id);
+                    bot.send_message(chat_id, lang.translate("Error generating the chart :("))
+                        .in_topic(thread)
                         .reply_markup(main_keyboard())
                         .await?;
-                    return respond(());
                 }
             }
+            return respond(());
         }
-        Command::HourlyStats => {
-            let timestamps = match db.get_all_user_timestamps(user_id).await {
-                Ok(ts) => ts,
-                Err(err) => {
-                    error!("Failed to get timestamps for the user {user_id}: {err}");
-                    bot.send_message(chat_id, "Database error :(")
-                        .reply_markup(main_keyboard())
+        CallbackAction::HistoryPage(offset) => {
+            let page = match register_user(&db, &q.from).await {
+                Ok(user_id) => history_page(&db, user_id, owner, offset, lang).await,
+                Err(err) => Err(err),
+            };
+            match page {
+                Ok((text, buttons)) => {
+                    bot.edit_message_text(chat_id, message_id, text)
+                        .reply_markup(buttons)
                         .await?;
                     return respond(());
                 }
-            };
-            let username = match bot.get_chat(user.id).await {
-                Ok(chat) => chat.username().map(|u| u.to_string()),
                 Err(err) => {
-                    debug!("Failed to get the username for {user_id}: {err}");
-                    None
+                    error!("Failed to get the history of {}: {err}", q.from.id);
+                    lang.translate("Database error :(").into()
                 }
+            }
+        }
+        CallbackAction::Settings(change) => {
+            let menu = match register_user(&db, &q.from).await {
+                Ok(user_id) => match apply_setting_change(&db, &cache, user_id, change).await {
+                    Ok(()) => settings_menu(&db, user_id, &q.from).await,
+                    Err(err) => Err(err),
+                },
+                Err(err) => Err(err),
             };
-            let name = username.unwrap_or_else(|| user.id.to_string());
-            match generate_personal_hourly_chart(&name, timestamps) {
-                Ok(png_bytes) => {
-                    bot.send_photo(chat_id, InputFile::memory(png_bytes))
+            match menu {
+                Ok((text, buttons)) => {
+                    bot.edit_message_text(chat_id, message_id, text)
+                        .reply_markup(buttons)
                         .await?;
+                    return respond(());
                 }
                 Err(err) => {
-                    error!("Failed to generate the chart for {user_id}: {err}");
-                    bot.send_message(chat_id, "Error generating the chart :(")
-                        .reply_markup(main_keyboard())
-                        .await?;
-                    return respond(());
+                    error!("Failed to change the settings of {}: {err}", q.from.id);
+                    lang.translate("Database error :(").into()
                 }
             }
         }
-        Command::Leaderboard => {
-            let leaderboard = match db.get_leaderboard().await {
-                Ok(lb) => lb,
+        // Buttons sent to groups before `/delete` became private-only
+        CallbackAction::Delete if !message.chat().is_private() => {
+            lang.translate(DELETE_PRIVATE_TEXT).to_string()
+        }
+        CallbackAction::Delete => {
+            let dialogue = DeleteDialogue::new(storage, chat_id);
+            match start_delete_confirmation(bot.clone(), dialogue, owner, chat_id, thread, lang)
+                .await
+            {
+                Ok(prompt) => prompt,
                 Err(err) => {
-                    error!("Failed to get the leaderboard: {err}");
-                    bot.send_message(chat_id, "Database error :(")
-                        .reply_markup(main_keyboard())
+                    error!("Failed to start the delete confirmation in the chat {chat_id}: {err}");
+                    lang.translate("Something went wrong, your data has not been deleted")
+                        .into()
+                }
+            }
+        }
+        CallbackAction::ApproveWatch(watcher_id) | CallbackAction::DeclineWatch(watcher_id) => {
+            let approve = matches!(action, CallbackAction::ApproveWatch(_));
+            let result = match db.find_user_by_telegram_id(q.from.id.0 as i64).await {
+                Ok(Some(user_id)) if approve => db.approve_watch(watcher_id, user_id).await,
+                Ok(Some(user_id)) => db.delete_watch(watcher_id, user_id).await,
+                Ok(None) => Ok(false),
+                Err(err) => Err(err),
+            };
+            match result {
+                Ok(true) if approve => {
+                    let watcher = try_join(
+                        db.get_telegram_id(watcher_id),
+                        db.get_user_settings(watcher_id),
+                    );
+                    match watcher.await {
+                        Ok((Some(tg_id), settings)) => {
+                            let watcher_lang =
+                                Language::for_user(settings.language.as_deref(), None);
+                            let text = fill(
+                                watcher_lang.translate("{} allowed you to watch their milestones"),
+                                &[&DisplayName::from(&q.from).plain()],
+                            );
+                            if let Err(err) = bot.send_message(ChatId(tg_id), text).await {
+                                debug!("Failed to tell {tg_id} about the approval: {err}");
+                            }
+                        }
+                        Ok((None, _)) => {}
+                        Err(err) => error!("Failed to get the watcher {watcher_id}: {err}"),
+                    }
+                    lang.translate("They will be notified about your milestones")
+                        .into()
+                }
+                Ok(true) => lang.translate("Declined").into(),
+                Ok(false) => lang.translate("This request no longer exists").into(),
+                Err(err) => {
+                    error!("Failed to answer the watch request of {watcher_id}: {err}");
+                    lang.translate("Database error :(").into()
+                }
+            }
+        }
+        CallbackAction::ClearToday => {
+            let user_id = match register_user(&db, &q.from).await {
+                Ok(id) => id,
+                Err(err) => {
+                    error!("Failed to get user ID from the DB: {err}");
+                    bot.edit_message_text(chat_id, message_id, lang.translate("Database error :("))
                         .await?;
                     return respond(());
                 }
             };
-            let futures = leaderboard.iter().enumerate().map(|(i, r)| {
-                let bot = bot.clone();
-                async move {
-                    let username = match bot.get_chat(ChatId(r.0)).await {
-                        Ok(chat) => chat.username().map(|u| u.to_string()),
-                        Err(err) => {
-                            debug!("Failed to get the username for {}: {err}", r.0);
-                            None
-                        }
-                    };
-
-                    let name = username.unwrap_or_else(|| r.0.to_string());
-                    format!("{}. @{name} - {}\n", i + 1, r.1)
+            let deleted = match db.get_user_settings(user_id).await {
+                Ok(settings) => {
+                    let offset = day_offset(settings.utc_offset, settings.day_end);
+                    let (from, to) =
+                        day_bounds(Utc::now().with_timezone(&offset).date_naive(), offset);
+                    let deleted = db.delete_logs_between(user_id, from, to).await;
+                    cache.invalidate(user_id);
+                    deleted
+                }
+                Err(err) => Err(err),
+            };
+            match deleted {
+                Ok(n) => fill(lang.translate("Cleared {} entries from today"), &[&n]),
+                Err(err) => {
+                    error!("Failed to clear today's logs for the user {user_id}: {err}");
+                    lang.translate("Database error :(").into()
                 }
-            });
-            let mut text: String = join_all(futures).await.concat();
-            if text.is_empty() {
-                text = "The leaderboard is empty".into();
             }
-            bot.send_message(chat_id, text)
-                .reply_markup(main_keyboard())
-                .await?;
         }
-        Command::Delete => {
-            if let Err(err) = db.delete_user_data(user_id).await {
-                error!("Failed to delete data for the user {user_id}: {err}");
-                bot.send_message(chat_id, "Database error :(")
-                    .reply_markup(main_keyboard())
-                    .await?;
-                return Ok(());
+    };
+    bot.edit_message_text(chat_id, message_id, text).await?;
+    respond(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranked(position: i64, telegram_id: i64, username: &str, logs: i64) -> RankedTotal {
+        RankedTotal {
+            user_id: telegram_id,
+            telegram_id,
+            username: Some(username.into()),
+            first_name: None,
+            last_name: None,
+            logs,
+            position,
+        }
+    }
+
+    #[tokio::test]
+    async fn anonymous_leaderboard_shows_the_caller_as_you() {
+        let db = Database::for_tests().await.unwrap();
+        let leaderboard = [
+            ranked(1, 10, "alice", 5),
+            ranked(2, 20, "bob", 4),
+            ranked(3, 30, "carol", 3),
+        ];
+
+        let text = format_leaderboard(&db, &leaderboard, true, 20, Language::English).await;
+        assert_eq!(text, "🥇 User 1 - 5\n👉 🥈 You - 4\n🥉 User 3 - 3\n");
+
+        let text = format_leaderboard(&db, &leaderboard, false, 20, Language::English).await;
+        assert_eq!(text, "🥇 @alice - 5\n👉 🥈 @bob - 4\n🥉 @carol - 3\n");
+    }
+
+    #[tokio::test]
+    async fn anonymous_leaderboard_sets_apart_the_caller_past_the_top_10() {
+        let db = Database::for_tests().await.unwrap();
+        let leaderboard = [ranked(1, 10, "alice", 5), ranked(12, 20, "bob", 1)];
+
+        let text = format_leaderboard(&db, &leaderboard, true, 20, Language::English).await;
+        assert_eq!(text, "🥇 User 1 - 5\n…\n👉 12. You - 1\n");
+    }
+
+    #[test]
+    fn motivation_matches_the_trend() {
+        let cases = [
+            (ActivityTrend::Improving, IMPROVING_MESSAGES),
+            (ActivityTrend::Steady, STEADY_MESSAGES),
+            (ActivityTrend::Slipping, SLIPPING_MESSAGES),
+        ];
+        for (trend, messages) in cases {
+            for day in 0..messages.len() as i64 {
+                assert!(messages.contains(&motivation_message(&trend, 7, day)));
             }
-            bot.send_message(chat_id, "All your data has been deleted")
-                .reply_markup(main_keyboard())
-                .await?;
+            assert_eq!(
+                motivation_message(&trend, 7, 100),
+                motivation_message(&trend, 7, 100)
+            );
         }
     }
-    respond(())
+
+    #[test]
+    fn motivation_changes_from_one_day_to_the_next() {
+        let today = motivation_message(&ActivityTrend::Steady, 7, 100);
+        let tomorrow = motivation_message(&ActivityTrend::Steady, 7, 101);
+        assert_ne!(today, tomorrow);
+    }
+
+    #[test]
+    fn annual_buttons_fit_in_the_callback_data() {
+        let owner = UserId(1234567890);
+        let year = MIN_CHART_YEAR + 1;
+        let buttons = |category| annual_buttons(year, Some(category), owner).inline_keyboard;
+
+        assert_eq!(buttons("gym")[0].len(), 2);
+        // The longest category allowed, but 4 bytes a character
+        let category = "🏋".repeat(MAX_CATEGORY_LEN);
+        assert!(buttons(&category)[0].is_empty());
+    }
 }
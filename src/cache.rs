@@ -0,0 +1,139 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+const CAPACITY: usize = 256;
+
+/// Identifies a rendered chart: the chart kind with its parameters,
+/// and the newest log of the user at the time it was rendered.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct ChartKey {
+    pub user_id: i64,
+    pub chart: String,
+    pub latest_log_id: i64,
+}
+
+struct Entry {
+    png_bytes: Vec<u8>,
+    generation: u64,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct Inner {
+    entries: HashMap<ChartKey, Entry>,
+    /// Bumped whenever the user's data changes in a way `latest_log_id` doesn't capture.
+    generations: HashMap<i64, u64>,
+    clock: u64,
+}
+
+/// In-memory LRU cache of rendered charts.
+#[derive(Clone, Default)]
+pub struct ChartCache {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl ChartCache {
+    pub fn get(&self, key: &ChartKey) -> Option<Vec<u8>> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.clock += 1;
+        let (clock, generation) = (inner.clock, inner.generation(key.user_id));
+        let entry = inner.entries.get_mut(key)?;
+        if entry.generation != generation {
+            inner.entries.remove(key);
+            return None;
+        }
+        entry.last_used = clock;
+        Some(entry.png_bytes.clone())
+    }
+
+    pub fn insert(&self, key: ChartKey, png_bytes: Vec<u8>) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.clock += 1;
+        if inner.entries.len() >= CAPACITY && !inner.entries.contains_key(&key) {
+            let oldest = inner
+                .entries
+                .iter()
+                .min_by_key(|(_, e)| e.last_used)
+                .map(|(k, _)| k.clone());
+            if let Some(oldest) = oldest {
+                inner.entries.remove(&oldest);
+            }
+        }
+        let entry = Entry {
+            png_bytes,
+            generation: inner.generation(key.user_id),
+            last_used: inner.clock,
+        };
+        inner.entries.insert(key, entry);
+    }
+
+    /// Makes all cached charts of the user stale.
+    pub fn invalidate(&self, user_id: i64) {
+        let mut inner = self.inner.lock().unwrap();
+        *inner.generations.entry(user_id).or_default() += 1;
+        inner.entries.retain(|k, _| k.user_id != user_id);
+    }
+
+    /// Drops all cached charts, e.g. after a cleanup touching many users.
+    pub fn clear(&self) {
+        self.inner.lock().unwrap().entries.clear();
+    }
+}
+
+impl Inner {
+    fn generation(&self, user_id: i64) -> u64 {
+        self.generations.get(&user_id).copied().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(user_id: i64, latest_log_id: i64) -> ChartKey {
+        ChartKey {
+            user_id,
+            chart: "annual:2024".into(),
+            latest_log_id,
+        }
+    }
+
+    #[test]
+    fn hit_on_the_same_key() {
+        let cache = ChartCache::default();
+        cache.insert(key(1, 10), vec![1, 2, 3]);
+        assert_eq!(cache.get(&key(1, 10)), Some(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn miss_after_a_new_log() {
+        let cache = ChartCache::default();
+        cache.insert(key(1, 10), vec![1]);
+        assert_eq!(cache.get(&key(1, 11)), None);
+    }
+
+    #[test]
+    fn invalidate_drops_only_that_user() {
+        let cache = ChartCache::default();
+        cache.insert(key(1, 10), vec![1]);
+        cache.insert(key(2, 10), vec![2]);
+        cache.invalidate(1);
+        assert_eq!(cache.get(&key(1, 10)), None);
+        assert_eq!(cache.get(&key(2, 10)), Some(vec![2]));
+        // Charts rendered after the change are cached again
+        cache.insert(key(1, 10), vec![3]);
+        assert_eq!(cache.get(&key(1, 10)), Some(vec![3]));
+    }
+
+    #[test]
+    fn clear_drops_everything() {
+        let cache = ChartCache::default();
+        cache.insert(key(1, 10), vec![1]);
+        cache.insert(key(2, 10), vec![2]);
+        cache.clear();
+        assert_eq!(cache.get(&key(1, 10)), None);
+        assert_eq!(cache.get(&key(2, 10)), None);
+    }
+}
@@ -1,64 +1,724 @@
-use std::io::Cursor;
+use std::{collections::HashMap, env, io::Cursor, sync::OnceLock};
 
 use anyhow::Context;
-use chrono::{DateTime, Datelike, Timelike, Utc};
-use plotters::prelude::*;
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, Timelike};
+use plotters::{
+    prelude::*,
+    style::text_anchor::{HPos, Pos, VPos},
+};
 
-use image::{ImageBuffer, Rgb};
+use image::{DynamicImage, ImageBuffer, Rgb, RgbaImage, imageops};
+use tracing::warn;
 
-const WIDTH: u32 = 640;
-const HEIGHT: u32 = 480;
+use crate::locale::{Language, fill, month_names, weekday_names};
 
+/// Default chart size, which the pixel lengths in the drawing code are written for.
+const BASE_WIDTH: u32 = 640;
+const BASE_HEIGHT: u32 = 480;
+/// Weekday labels for languages without their own.
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+static WATERMARK: OnceLock<Option<RgbaImage>> = OnceLock::new();
+static DEFAULT_THEME: OnceLock<ChartTheme> = OnceLock::new();
+static CHART_SIZE: OnceLock<(u32, u32)> = OnceLock::new();
+static AS_DOCUMENT: OnceLock<bool> = OnceLock::new();
+
+/// Chart size from `CHART_WIDTH` and `CHART_HEIGHT`, 640x480 by default.
+/// Smaller sizes would squeeze the labels together, so they are raised to the default.
+fn chart_size() -> (u32, u32) {
+    *CHART_SIZE.get_or_init(|| {
+        let dimension = |key: &str, default: u32| match env::var(key) {
+            Ok(value) => match value.parse::<u32>() {
+                Ok(n) => n.max(default),
+                Err(_) => {
+                    warn!("Invalid {key} {value:?}, using {default}");
+                    default
+                }
+            },
+            Err(_) => default,
+        };
+        (
+            dimension("CHART_WIDTH", BASE_WIDTH),
+            dimension("CHART_HEIGHT", BASE_HEIGHT),
+        )
+    })
+}
+
+/// Scales a length in pixels of a 640x480 chart to the configured size,
+/// so fonts and spacing keep their proportions.
+fn px(length: i32) -> i32 {
+    let (width, height) = chart_size();
+    let scale = f64::min(
+        f64::from(width) / f64::from(BASE_WIDTH),
+        f64::from(height) / f64::from(BASE_HEIGHT),
+    );
+    (f64::from(length) * scale).round() as i32
+}
+
+fn new_buffer() -> Vec<u8> {
+    let (width, height) = chart_size();
+    vec![0u8; (width * height * 3) as usize]
+}
+
+/// Whether charts should be sent as documents, which Telegram doesn't recompress,
+/// set with `CHART_AS_DOCUMENT=1`.
+pub fn send_as_document() -> bool {
+    *AS_DOCUMENT.get_or_init(|| {
+        env::var("CHART_AS_DOCUMENT").is_ok_and(|v| matches!(v.as_str(), "1" | "true"))
+    })
+}
+
+/// Colors of a chart.
+#[derive(Clone, Copy)]
+pub struct ChartTheme {
+    pub background: RGBColor,
+    pub bar: RGBColor,
+    pub text: RGBColor,
+    pub grid: RGBColor,
+    /// Bars that stand out, like a streak that is the best one.
+    pub highlight: RGBColor,
+    /// The series the bars are compared against, like last year.
+    pub comparison: RGBColor,
+    /// Heatmap colors from one log to the most logs in a day.
+    pub heat: [RGBColor; 4],
+}
+
+/// Greens like GitHub's contribution graph.
+const GREEN_HEAT: [RGBColor; 4] = [
+    RGBColor(14, 68, 41),
+    RGBColor(0, 109, 50),
+    RGBColor(38, 166, 65),
+    RGBColor(57, 211, 83),
+];
+
+impl ChartTheme {
+    pub const DARK: Self = Self {
+        background: BLACK,
+        bar: WHITE,
+        text: WHITE,
+        grid: RGBColor(40, 40, 40),
+        highlight: GREEN,
+        comparison: RGBColor(128, 128, 128),
+        heat: GREEN_HEAT,
+    };
+    pub const LIGHT: Self = Self {
+        background: WHITE,
+        bar: RGBColor(60, 60, 60),
+        text: BLACK,
+        grid: RGBColor(225, 225, 225),
+        highlight: RGBColor(0, 150, 60),
+        comparison: RGBColor(170, 170, 170),
+        heat: GREEN_HEAT,
+    };
+    /// Blue and orange from the Okabe-Ito palette, which stay apart with red-green color blindness.
+    pub const COLORBLIND: Self = Self {
+        background: BLACK,
+        bar: RGBColor(86, 180, 233),
+        text: WHITE,
+        grid: RGBColor(40, 40, 40),
+        highlight: RGBColor(230, 159, 0),
+        comparison: RGBColor(213, 94, 0),
+        heat: [
+            RGBColor(20, 70, 140),
+            RGBColor(33, 113, 181),
+            RGBColor(107, 174, 214),
+            RGBColor(198, 219, 239),
+        ],
+    };
+    /// Pure colors on black with a visible grid, for low vision.
+    pub const HIGH_CONTRAST: Self = Self {
+        background: BLACK,
+        bar: WHITE,
+        text: WHITE,
+        grid: RGBColor(110, 110, 110),
+        highlight: YELLOW,
+        comparison: CYAN,
+        heat: [
+            RGBColor(90, 90, 0),
+            RGBColor(160, 160, 0),
+            RGBColor(220, 220, 0),
+            RGBColor(255, 255, 128),
+        ],
+    };
+
+    /// Names users can pick with `/theme`.
+    pub const NAMES: [&str; 4] = ["dark", "light", "colorblind", "highcontrast"];
+
+    pub fn named(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::DARK),
+            "light" => Some(Self::LIGHT),
+            "colorblind" => Some(Self::COLORBLIND),
+            "highcontrast" => Some(Self::HIGH_CONTRAST),
+            _ => None,
+        }
+    }
+
+    /// The user's theme if they picked a known one, the deployment's default otherwise.
+    pub fn for_user(name: Option<&str>) -> Self {
+        name.and_then(Self::named)
+            .unwrap_or_else(|| *DEFAULT_THEME.get_or_init(Self::from_env))
+    }
+
+    /// Reads `CHART_THEME`, either a theme name or four comma-separated hex colors
+    /// for the background, bars, text and grid, e.g. `#202020,#ffcc00,#ffffff,#404040`.
+    fn from_env() -> Self {
+        let Ok(value) = env::var("CHART_THEME") else {
+            return Self::DARK;
+        };
+        if let Some(theme) = Self::named(&value) {
+            return theme;
+        }
+        let colors: Option<Vec<RGBColor>> = value.split(',').map(parse_hex_color).collect();
+        match colors.as_deref() {
+            Some(&[background, bar, text, grid]) => Self {
+                background,
+                bar,
+                text,
+                grid,
+                ..Self::DARK
+            },
+            _ => {
+                warn!("Invalid CHART_THEME {value:?}, using the dark theme");
+                Self::DARK
+            }
+        }
+    }
+}
+
+/// Parses a color like `#ffcc00`.
+fn parse_hex_color(input: &str) -> Option<RGBColor> {
+    let hex = input.trim().strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some(RGBColor(channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// Runs a chart function on the blocking thread pool,
+/// since drawing and PNG encoding would otherwise stall the async executor.
+pub async fn render<F>(draw: F) -> anyhow::Result<Vec<u8>>
+where
+    F: FnOnce() -> anyhow::Result<Vec<u8>> + Send + 'static,
+{
+    tokio::task::spawn_blocking(draw).await?
+}
+
+/// Draws the logs per month of `year`, `monthly` as returned by `Database::get_monthly_counts`,
+/// with a line at the monthly `goal` if there is one.
+/// Labels are in `language` if known, English otherwise.
 pub fn generate_personal_annual_chart(
     username: &str,
-    timestamps: Vec<i64>,
-    year: Option<i32>,
+    monthly: [i64; 12],
+    year: i32,
+    language: Option<&str>,
+    goal: Option<i64>,
+    theme: ChartTheme,
 ) -> anyhow::Result<Vec<u8>> {
-    let mut buffer = vec![0u8; (WIDTH * HEIGHT * 3) as usize];
-    let year = match year {
-        Some(y) => y,
-        None => Utc::now().year(),
-    };
-    let data = prepare_annual_data(timestamps, year);
+    let lang = Language::for_user(language, None);
+    let mut buffer = new_buffer();
+    let mut data = prepare_counts(monthly);
+    if let Some(names) = language.and_then(month_names).or_else(|| month_names("en")) {
+        for (d, name) in data.iter_mut().zip(names) {
+            d.label = Some(name.to_string());
+        }
+    }
     draw_chart(
         ChartParams {
             caption: &format!("{username} - {year}"),
-            x_desc: "Month",
-            y_desc: "Score",
+            x_desc: lang.translate("Month"),
+            y_desc: lang.translate("Score"),
+            highlight: None,
+            target: goal.map(|g| g.max(0) as usize),
+            theme,
+            lang,
         },
         &data,
         &mut buffer,
     )?;
-    Ok(make_png(buffer)?)
+    make_png(buffer)
 }
 
+/// Draws the logs per hour, `hourly` as returned by `Database::get_hourly_counts`.
 pub fn generate_personal_hourly_chart(
+    username: &str,
+    hourly: [i64; 24],
+    language: Option<&str>,
+    theme: ChartTheme,
+) -> anyhow::Result<Vec<u8>> {
+    let lang = Language::for_user(language, None);
+    let mut buffer = new_buffer();
+    let data = prepare_counts(hourly);
+    draw_chart(
+        ChartParams {
+            caption: username,
+            x_desc: lang.translate("Hour"),
+            y_desc: lang.translate("Score"),
+            highlight: None,
+            target: None,
+            theme,
+            lang,
+        },
+        &data,
+        &mut buffer,
+    )?;
+    make_png(buffer)
+}
+
+pub fn generate_daily_distribution_chart(
     username: &str,
     timestamps: Vec<i64>,
+    language: Option<&str>,
+    theme: ChartTheme,
+) -> anyhow::Result<Vec<u8>> {
+    let lang = Language::for_user(language, None);
+    let mut buffer = new_buffer();
+    let data = prepare_daily_distribution_data(timestamps);
+    draw_chart(
+        ChartParams {
+            caption: username,
+            x_desc: lang.translate("Logs in a day"),
+            y_desc: lang.translate("Number of such days"),
+            highlight: None,
+            target: None,
+            theme,
+            lang,
+        },
+        &data,
+        &mut buffer,
+    )?;
+    make_png(buffer)
+}
+
+/// Draws the current streak next to the best one, highlighted if the user is on their best streak.
+pub fn generate_streak_comparison_chart(
+    username: &str,
+    current: u32,
+    best: u32,
+    language: Option<&str>,
+    theme: ChartTheme,
 ) -> anyhow::Result<Vec<u8>> {
-    let mut buffer = vec![0u8; (WIDTH * HEIGHT * 3) as usize];
-    let data = prepare_hourly_data(timestamps);
+    let lang = Language::for_user(language, None);
+    let mut buffer = new_buffer();
+    let caption = if best == 0 {
+        fill(lang.translate("{} - no streak yet"), &[&username])
+    } else {
+        username.to_string()
+    };
+    let data = [
+        ChartData {
+            value: current as usize,
+            label: Some(lang.translate("Current").into()),
+        },
+        ChartData {
+            value: best as usize,
+            label: Some(lang.translate("Best").into()),
+        },
+    ];
     draw_chart(
         ChartParams {
-            caption: &username,
-            x_desc: "Hour, UTC",
-            y_desc: "Score",
+            caption: &caption,
+            x_desc: lang.translate("Streak"),
+            y_desc: lang.translate("Days"),
+            highlight: (current == best && best > 0).then_some(0),
+            target: None,
+            theme,
+            lang,
         },
         &data,
         &mut buffer,
     )?;
-    Ok(make_png(buffer)?)
+    make_png(buffer)
+}
+
+/// Plots cumulative logs of `year` against the same year-to-date window of the previous year.
+pub fn generate_year_over_year_pace_chart(
+    username: &str,
+    this_year: Vec<i64>,
+    last_year: Vec<i64>,
+    year: i32,
+    day_of_year: u32,
+    language: Option<&str>,
+    theme: ChartTheme,
+) -> anyhow::Result<Vec<u8>> {
+    let lang = Language::for_user(language, None);
+    let mut buffer = new_buffer();
+    let current = prepare_cumulative_data(this_year, year, day_of_year);
+    let mut series = vec![Series {
+        label: year.to_string(),
+        color: theme.bar,
+        values: current,
+    }];
+    let caption = if last_year.is_empty() {
+        fill(
+            lang.translate("{} - {} (no data for {})"),
+            &[&username, &year, &(year - 1)],
+        )
+    } else {
+        series.push(Series {
+            label: (year - 1).to_string(),
+            color: theme.comparison,
+            values: prepare_cumulative_data(last_year, year - 1, day_of_year),
+        });
+        format!("{username} - {year} vs {}", year - 1)
+    };
+    draw_line_chart(
+        ChartParams {
+            caption: &caption,
+            x_desc: lang.translate("Day of year"),
+            y_desc: lang.translate("Total score"),
+            highlight: None,
+            target: None,
+            theme,
+            lang,
+        },
+        &series,
+        &mut buffer,
+    )?;
+    make_png(buffer)
+}
+
+/// Draws the logs per month of `year` next to those of the year before,
+/// `this_year` and `last_year` as returned by `Database::get_monthly_counts`.
+/// Labels are in `language` if known, English otherwise.
+pub fn generate_year_comparison_chart(
+    username: &str,
+    this_year: [i64; 12],
+    last_year: [i64; 12],
+    year: i32,
+    language: Option<&str>,
+    theme: ChartTheme,
+) -> anyhow::Result<Vec<u8>> {
+    draw_monthly_comparison(
+        &format!("{username} - {year} vs {}", year - 1),
+        [
+            ((year - 1).to_string(), last_year),
+            (year.to_string(), this_year),
+        ],
+        language,
+        theme,
+    )
+}
+
+/// Draws the user's logs per month of `year` next to those of `other`.
+/// Labels are in `language` if known, English otherwise.
+pub fn generate_user_comparison_chart(
+    username: &str,
+    other: &str,
+    mine: [i64; 12],
+    theirs: [i64; 12],
+    year: i32,
+    language: Option<&str>,
+    theme: ChartTheme,
+) -> anyhow::Result<Vec<u8>> {
+    draw_monthly_comparison(
+        &format!("{username} vs {other} - {year}"),
+        [(other.to_string(), theirs), (username.to_string(), mine)],
+        language,
+        theme,
+    )
+}
+
+/// Draws two labeled series of monthly counts, the second one in the bar color.
+fn draw_monthly_comparison(
+    caption: &str,
+    [(first_label, first), (second_label, second)]: [(String, [i64; 12]); 2],
+    language: Option<&str>,
+    theme: ChartTheme,
+) -> anyhow::Result<Vec<u8>> {
+    let lang = Language::for_user(language, None);
+    let mut buffer = new_buffer();
+    let labels: Vec<String> = match language.and_then(month_names).or_else(|| month_names("en")) {
+        Some(names) => names.iter().map(|n| n.to_string()).collect(),
+        None => (1..=12).map(|m| m.to_string()).collect(),
+    };
+    let counts = |monthly: [i64; 12]| monthly.iter().map(|&v| v.max(0) as usize).collect();
+    let series = [
+        Series {
+            label: first_label,
+            color: theme.comparison,
+            values: counts(first),
+        },
+        Series {
+            label: second_label,
+            color: theme.bar,
+            values: counts(second),
+        },
+    ];
+    draw_grouped_chart(
+        ChartParams {
+            caption,
+            x_desc: lang.translate("Month"),
+            y_desc: lang.translate("Score"),
+            highlight: None,
+            target: None,
+            theme,
+            lang,
+        },
+        &labels,
+        &series,
+        &mut buffer,
+    )?;
+    make_png(buffer)
+}
+
+/// Draws every log of `year` as a dot, by day of year and local time of day.
+pub fn generate_timeline_chart(
+    username: &str,
+    timestamps: Vec<i64>,
+    year: i32,
+    offset: FixedOffset,
+    language: Option<&str>,
+    theme: ChartTheme,
+) -> anyhow::Result<Vec<u8>> {
+    let lang = Language::for_user(language, None);
+    let mut buffer = new_buffer();
+    let points: Vec<(u32, f64)> = timestamps
+        .iter()
+        .filter_map(|&ts| DateTime::from_timestamp(ts, 0))
+        .map(|dt| dt.with_timezone(&offset))
+        .filter(|dt| dt.year() == year)
+        .map(|dt| {
+            (
+                dt.ordinal(),
+                f64::from(dt.num_seconds_from_midnight()) / 3600.0,
+            )
+        })
+        .collect();
+
+    draw_timeline(
+        &format!("{username} - {year}"),
+        &points,
+        lang,
+        theme,
+        &mut buffer,
+    )?;
+    make_png(buffer)
+}
+
+/// Draws the logs per day of one week, Monday first.
+pub fn generate_week_chart(
+    caption: &str,
+    per_day: [i64; 7],
+    language: Option<&str>,
+    theme: ChartTheme,
+) -> anyhow::Result<Vec<u8>> {
+    let lang = Language::for_user(language, None);
+    let mut buffer = new_buffer();
+    let mut data = prepare_counts(per_day);
+    for (d, name) in data.iter_mut().zip(weekday_labels(language)) {
+        d.label = Some(name.to_string());
+    }
+    draw_chart(
+        ChartParams {
+            caption,
+            x_desc: lang.translate("Day"),
+            y_desc: lang.translate("Score"),
+            highlight: None,
+            target: None,
+            theme,
+            lang,
+        },
+        &data,
+        &mut buffer,
+    )?;
+    make_png(buffer)
+}
+
+/// Draws all logs per (local) day of the week, Monday first.
+pub fn generate_weekday_chart(
+    username: &str,
+    weekday: [i64; 7],
+    language: Option<&str>,
+    theme: ChartTheme,
+) -> anyhow::Result<Vec<u8>> {
+    let lang = Language::for_user(language, None);
+    let mut buffer = new_buffer();
+    let mut data = prepare_counts(weekday);
+    for (d, name) in data.iter_mut().zip(weekday_labels(language)) {
+        d.label = Some(name.to_string());
+    }
+    draw_chart(
+        ChartParams {
+            caption: username,
+            x_desc: lang.translate("Day"),
+            y_desc: lang.translate("Score"),
+            highlight: None,
+            target: None,
+            theme,
+            lang,
+        },
+        &data,
+        &mut buffer,
+    )?;
+    make_png(buffer)
+}
+
+/// Draws the logs per (local) day of the month starting on `month`.
+pub fn generate_month_chart(
+    username: &str,
+    timestamps: Vec<i64>,
+    month: NaiveDate,
+    offset: FixedOffset,
+    language: Option<&str>,
+    theme: ChartTheme,
+) -> anyhow::Result<Vec<u8>> {
+    let lang = Language::for_user(language, None);
+    let mut buffer = new_buffer();
+    let data = prepare_month_data(timestamps, month, offset);
+    draw_chart(
+        ChartParams {
+            caption: &format!("{username} - {}", month.format("%Y-%m")),
+            x_desc: lang.translate("Day"),
+            y_desc: lang.translate("Score"),
+            highlight: None,
+            target: None,
+            theme,
+            lang,
+        },
+        &data,
+        &mut buffer,
+    )?;
+    make_png(buffer)
+}
+
+/// Draws the logs per (local) day of `year` as a calendar grid, one column per week.
+/// Labels are in `language` if known, English otherwise.
+pub fn generate_heatmap_chart(
+    username: &str,
+    timestamps: Vec<i64>,
+    year: i32,
+    offset: FixedOffset,
+    language: Option<&str>,
+    theme: ChartTheme,
+) -> anyhow::Result<Vec<u8>> {
+    let mut buffer = new_buffer();
+    let per_day = timestamps
+        .iter()
+        .filter_map(|&ts| DateTime::from_timestamp(ts, 0))
+        .map(|dt| dt.with_timezone(&offset).date_naive())
+        .filter(|day| day.year() == year)
+        .fold(HashMap::new(), |mut acc, day| {
+            *acc.entry(day).or_insert(0usize) += 1;
+            acc
+        });
+    let months: Vec<String> = match language.and_then(month_names).or_else(|| month_names("en")) {
+        Some(names) => names.iter().map(|n| n.to_string()).collect(),
+        None => (1..=12).map(|m| m.to_string()).collect(),
+    };
+    draw_heatmap(
+        &format!("{username} - {year}"),
+        &per_day,
+        year,
+        &months,
+        language,
+        theme,
+        &mut buffer,
+    )?;
+    make_png(buffer)
+}
+
+/// Everything shown on a profile card.
+pub struct ProfileData {
+    pub username: String,
+    pub total: i64,
+    pub current_streak: u32,
+    pub best_streak: u32,
+    /// Position on the global leaderboard, `None` without logs.
+    pub rank: Option<i64>,
+    /// The (local) day with the most logs and their number.
+    pub best_day: Option<(NaiveDate, usize)>,
+    pub year: i32,
+    pub timestamps: Vec<i64>,
+}
+
+/// Draws the user's key numbers above a small chart of this year's logs per month.
+/// Labels are in `language` if known, English otherwise.
+pub fn generate_profile_card(
+    data: ProfileData,
+    language: Option<&str>,
+    theme: ChartTheme,
+) -> anyhow::Result<Vec<u8>> {
+    let lang = Language::for_user(language, None);
+    let mut buffer = new_buffer();
+    let months = prepare_annual_data(data.timestamps, data.year)
+        .map(|d| d.value)
+        .to_vec();
+    let rank = data.rank.map_or("-".into(), |r| format!("#{r}"));
+    let best_day = data.best_day.map_or("-".into(), |(day, n)| {
+        fill(lang.translate("{} on {}"), &[&n, &day])
+    });
+    let days = lang.translate("{} days");
+    let lines = [
+        (lang.translate("Total"), data.total.to_string()),
+        (lang.translate("Rank"), rank),
+        (
+            lang.translate("Current streak"),
+            fill(days, &[&data.current_streak]),
+        ),
+        (
+            lang.translate("Best streak"),
+            fill(days, &[&data.best_streak]),
+        ),
+        (lang.translate("Best day"), best_day),
+    ];
+    draw_card(
+        &data.username,
+        &lines,
+        data.year,
+        &months,
+        lang,
+        theme,
+        &mut buffer,
+    )?;
+    make_png(buffer)
+}
+
+/// Weekday labels in `language` if known, English otherwise.
+fn weekday_labels(language: Option<&str>) -> &'static [&'static str; 7] {
+    language.and_then(weekday_names).unwrap_or(&WEEKDAYS)
 }
 
 fn make_png(buffer: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+    let (width, height) = chart_size();
     let image: ImageBuffer<Rgb<u8>, _> =
-        ImageBuffer::from_raw(WIDTH, HEIGHT, buffer).context("Failed to create an image buffer")?;
+        ImageBuffer::from_raw(width, height, buffer).context("Failed to create an image buffer")?;
+    let mut image = DynamicImage::ImageRgb8(image);
+    if let Some(watermark) = watermark() {
+        let mut canvas = image.into_rgba8();
+        let x = (width - watermark.width()) / 2;
+        let y = (height - watermark.height()) / 2;
+        imageops::overlay(&mut canvas, watermark, x.into(), y.into());
+        image = DynamicImage::ImageRgba8(canvas).into_rgb8().into();
+    }
     let mut png_bytes = Vec::new();
     let mut cursor = Cursor::new(&mut png_bytes);
-    image::DynamicImage::ImageRgb8(image).write_to(&mut cursor, image::ImageFormat::Png)?;
+    image.write_to(&mut cursor, image::ImageFormat::Png)?;
     Ok(png_bytes)
 }
 
+/// Returns the watermark from `CHART_WATERMARK`, loaded once and scaled down to fit the chart.
+fn watermark() -> Option<&'static RgbaImage> {
+    WATERMARK
+        .get_or_init(|| {
+            let path = env::var("CHART_WATERMARK").ok()?;
+            let (width, height) = chart_size();
+            match image::open(&path) {
+                Ok(img) if img.width() > width || img.height() > height => Some(
+                    img.resize(width, height, imageops::FilterType::Triangle)
+                        .into_rgba8(),
+                ),
+                Ok(img) => Some(img.into_rgba8()),
+                Err(err) => {
+                    warn!("Failed to load the chart watermark from {path}, skipping it: {err}");
+                    None
+                }
+            }
+        })
+        .as_ref()
+}
+
 fn prepare_annual_data(timestamps: Vec<i64>, year: i32) -> [ChartData; 12] {
     timestamps
         .iter()
@@ -74,24 +734,100 @@ fn prepare_annual_data(timestamps: Vec<i64>, year: i32) -> [ChartData; 12] {
         })
 }
 
-fn prepare_hourly_data(timestamps: Vec<i64>) -> [ChartData; 24] {
-    timestamps
+/// Counts the logs on each (local) day of the month starting on `month`, labeled by day number.
+fn prepare_month_data(
+    timestamps: Vec<i64>,
+    month: NaiveDate,
+    offset: FixedOffset,
+) -> Vec<ChartData> {
+    let mut counts = vec![0usize; month.num_days_in_month() as usize];
+    for day in timestamps
+        .iter()
+        .filter_map(|&ts| DateTime::from_timestamp(ts, 0))
+        .map(|dt| dt.with_timezone(&offset).date_naive())
+        .filter(|day| day.year() == month.year() && day.month() == month.month())
+    {
+        counts[day.day0() as usize] += 1;
+    }
+    counts
+        .into_iter()
+        .enumerate()
+        .map(|(i, v)| ChartData {
+            value: v,
+            label: Some((i + 1).to_string()),
+        })
+        .collect()
+}
+
+fn prepare_counts<const N: usize>(counts: [i64; N]) -> [ChartData; N] {
+    counts.map(|v| ChartData {
+        value: v.max(0) as usize,
+        label: None,
+    })
+}
+
+/// Buckets days (UTC) by their log count:
+/// the bar at index `k - 1` is the number of days with exactly `k` logs.
+fn prepare_daily_distribution_data(timestamps: Vec<i64>) -> Vec<ChartData> {
+    let per_day = timestamps
         .iter()
         .filter_map(|&ts| DateTime::from_timestamp(ts, 0))
-        .fold([0usize; 24], |mut acc, dt| {
-            acc[dt.hour() as usize] += 1;
+        .fold(HashMap::new(), |mut acc, dt| {
+            *acc.entry(dt.date_naive()).or_insert(0usize) += 1;
+            acc
+        });
+    let max = per_day.values().copied().max().unwrap_or(1);
+    per_day
+        .values()
+        .fold(vec![0usize; max], |mut acc, &count| {
+            acc[count - 1] += 1;
             acc
         })
-        .map(|v| ChartData {
+        .into_iter()
+        .enumerate()
+        .map(|(i, v)| ChartData {
             value: v,
-            label: None,
+            label: Some(format!("{}", i + 1)),
+        })
+        .collect()
+}
+
+/// Running totals per day of `year` (UTC), from January 1st up to `days` days.
+fn prepare_cumulative_data(timestamps: Vec<i64>, year: i32, days: u32) -> Vec<usize> {
+    let per_day = timestamps
+        .iter()
+        .filter_map(|&ts| DateTime::from_timestamp(ts, 0))
+        .filter(|dt| dt.year() == year && dt.ordinal() <= days)
+        .fold(vec![0usize; days as usize], |mut acc, dt| {
+            acc[(dt.ordinal() - 1) as usize] += 1;
+            acc
+        });
+    per_day
+        .iter()
+        .scan(0, |total, v| {
+            *total += v;
+            Some(*total)
         })
+        .collect()
 }
 
 struct ChartParams<'a> {
     caption: &'a str,
     x_desc: &'a str,
     y_desc: &'a str,
+    /// Index of a bar drawn in the highlight color instead of the bar color.
+    highlight: Option<usize>,
+    /// Value of a goal, drawn as a dashed line across the bars.
+    target: Option<usize>,
+    theme: ChartTheme,
+    /// Language of the texts drawn by the chart itself, like the goal label.
+    lang: Language,
+}
+
+struct Series {
+    label: String,
+    color: RGBColor,
+    values: Vec<usize>,
 }
 
 #[derive(Debug)]
@@ -100,46 +836,435 @@ struct ChartData {
     label: Option<String>,
 }
 
-fn draw_chart(
-    params: ChartParams,
-    data: &[ChartData],
-    mut buffer: &mut Vec<u8>,
-) -> anyhow::Result<()> {
-    let root = BitMapBackend::with_buffer(&mut buffer, (WIDTH, HEIGHT)).into_drawing_area();
-    root.fill(&BLACK)?;
+fn draw_chart(params: ChartParams, data: &[ChartData], buffer: &mut [u8]) -> anyhow::Result<()> {
+    let theme = params.theme;
+    let root = BitMapBackend::with_buffer(buffer, chart_size()).into_drawing_area();
+    root.fill(&theme.background)?;
 
+    let max_value = data
+        .iter()
+        .map(|d| d.value)
+        .chain(params.target)
+        .max()
+        .unwrap_or(1)
+        .max(1);
     let mut chart = ChartBuilder::on(&root)
-        .margin(10)
-        .caption(params.caption, ("sans-serif", 30).into_font().color(&WHITE))
-        .x_label_area_size(50)
-        .y_label_area_size(50)
+        .margin(px(10))
+        .caption(
+            params.caption,
+            ("sans-serif", px(30)).into_font().color(&theme.text),
+        )
+        .x_label_area_size(px(50))
+        .y_label_area_size(px(50))
         .build_cartesian_2d(
-            0..data.len(),
-            0..(data.iter().map(|d| d.value).max().unwrap_or(1)),
+            // Segmented ranges include their end
+            (0..data.len().saturating_sub(1)).into_segmented(),
+            // Headroom for the value above the tallest bar
+            0..max_value + max_value / 10 + 1,
         )?;
 
     chart
         .configure_mesh()
-        .axis_style(WHITE.filled())
-        .axis_desc_style(("sans-serif", 15).into_font().color(&WHITE))
+        .axis_style(theme.text.filled())
+        .bold_line_style(theme.grid)
+        .light_line_style(theme.background)
+        .axis_desc_style(("sans-serif", px(15)).into_font().color(&theme.text))
         .x_desc(params.x_desc)
         .y_desc(params.y_desc)
-        .label_style(("sans-serif", 15).into_font().color(&WHITE))
+        .label_style(("sans-serif", px(15)).into_font().color(&theme.text))
         .x_labels(data.len())
-        .x_label_formatter(&|i| {
-            data.get(*i)
+        // Labels are centered under their bars
+        .x_label_formatter(&|x| match x {
+            SegmentValue::CenterOf(i) => data
+                .get(*i)
                 .and_then(|d| d.label.clone())
-                .or_else(|| Some(format!("{i}")))
-                .unwrap()
+                .unwrap_or_else(|| format!("{i}")),
+            _ => String::new(),
         })
         .draw()?;
 
     chart.draw_series(
         Histogram::vertical(&chart)
-            .style(WHITE.filled())
-            .data(data.iter().enumerate().map(|(i, d)| (i, d.value))),
+            .style_func(|i, _| {
+                if matches!(i, SegmentValue::Exact(i) if params.highlight == Some(*i)) {
+                    theme.highlight.filled()
+                } else {
+                    theme.bar.filled()
+                }
+            })
+            .data(
+                data.iter()
+                    .enumerate()
+                    .map(|(i, d)| (SegmentValue::Exact(i), d.value)),
+            ),
+    )?;
+
+    let bar_width = chart.plotting_area().dim_in_pixel().0 as f64 / data.len().max(1) as f64;
+    chart.draw_series(
+        data.iter()
+            .enumerate()
+            .filter(|(_, d)| d.value > 0)
+            .map(|(i, d)| {
+                let text = d.value.to_string();
+                // Shrink the font until the value fits over its bar, a digit is about 0.6 em wide
+                let size = (bar_width / (0.6 * text.len() as f64))
+                    .clamp(f64::from(px(6)), f64::from(px(15)));
+                let style = ("sans-serif", size)
+                    .into_font()
+                    .color(&theme.text)
+                    .pos(Pos::new(HPos::Center, VPos::Bottom));
+                EmptyElement::at((SegmentValue::CenterOf(i), d.value))
+                    + Text::new(text, (0, -px(2)), style)
+            }),
     )?;
 
+    if let Some(target) = params.target {
+        chart.draw_series(DashedLineSeries::new(
+            [
+                (SegmentValue::Exact(0), target),
+                (SegmentValue::Last, target),
+            ],
+            px(8),
+            px(4),
+            theme.highlight.stroke_width(px(2) as u32),
+        ))?;
+        let style = ("sans-serif", px(15))
+            .into_font()
+            .color(&theme.highlight)
+            .pos(Pos::new(HPos::Right, VPos::Bottom));
+        chart.draw_series(std::iter::once(
+            EmptyElement::at((SegmentValue::Last, target))
+                + Text::new(params.lang.translate("Goal"), (0, -px(2)), style),
+        ))?;
+    }
+
+    root.present()?;
+    Ok(())
+}
+
+fn draw_line_chart(
+    params: ChartParams,
+    series: &[Series],
+    buffer: &mut [u8],
+) -> anyhow::Result<()> {
+    let theme = params.theme;
+    let root = BitMapBackend::with_buffer(buffer, chart_size()).into_drawing_area();
+    root.fill(&theme.background)?;
+
+    let max_len = series.iter().map(|s| s.values.len()).max().unwrap_or(1);
+    let max_value = series
+        .iter()
+        .flat_map(|s| s.values.iter().copied())
+        .max()
+        .unwrap_or(1)
+        .max(1);
+    let mut chart = ChartBuilder::on(&root)
+        .margin(px(10))
+        .caption(
+            params.caption,
+            ("sans-serif", px(30)).into_font().color(&theme.text),
+        )
+        .x_label_area_size(px(50))
+        .y_label_area_size(px(50))
+        .build_cartesian_2d(1..max_len.max(2), 0..max_value)?;
+
+    chart
+        .configure_mesh()
+        .axis_style(theme.text.filled())
+        .bold_line_style(theme.grid)
+        .light_line_style(theme.background)
+        .axis_desc_style(("sans-serif", px(15)).into_font().color(&theme.text))
+        .x_desc(params.x_desc)
+        .y_desc(params.y_desc)
+        .label_style(("sans-serif", px(15)).into_font().color(&theme.text))
+        .draw()?;
+
+    for s in series {
+        let color = s.color;
+        chart
+            .draw_series(LineSeries::new(
+                s.values.iter().enumerate().map(|(i, &v)| (i + 1, v)),
+                color.stroke_width(px(2) as u32),
+            ))?
+            .label(s.label.as_str())
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + px(20), y)], color));
+    }
+
+    chart
+        .configure_series_labels()
+        .position(SeriesLabelPosition::UpperLeft)
+        .background_style(theme.background.mix(0.8))
+        .border_style(theme.text)
+        .label_font(("sans-serif", px(15)).into_font().color(&theme.text))
+        .draw()?;
+
+    root.present()?;
+    Ok(())
+}
+
+/// Draws one group of bars per label, with a bar for each series side by side.
+fn draw_grouped_chart(
+    params: ChartParams,
+    labels: &[String],
+    series: &[Series],
+    buffer: &mut [u8],
+) -> anyhow::Result<()> {
+    let theme = params.theme;
+    let root = BitMapBackend::with_buffer(buffer, chart_size()).into_drawing_area();
+    root.fill(&theme.background)?;
+
+    let max_value = series
+        .iter()
+        .flat_map(|s| s.values.iter().copied())
+        .max()
+        .unwrap_or(1)
+        .max(1);
+    // Groups are centered on whole numbers, where the labels go
+    let groups = labels.len().max(1);
+    let mut chart = ChartBuilder::on(&root)
+        .margin(px(10))
+        .caption(
+            params.caption,
+            ("sans-serif", px(30)).into_font().color(&theme.text),
+        )
+        .x_label_area_size(px(50))
+        .y_label_area_size(px(50))
+        .build_cartesian_2d(-0.5..groups as f64 - 0.5, 0..max_value)?;
+
+    chart
+        .configure_mesh()
+        .axis_style(theme.text.filled())
+        .bold_line_style(theme.grid)
+        .light_line_style(theme.background)
+        .axis_desc_style(("sans-serif", px(15)).into_font().color(&theme.text))
+        .x_desc(params.x_desc)
+        .y_desc(params.y_desc)
+        .label_style(("sans-serif", px(15)).into_font().color(&theme.text))
+        .x_labels(groups)
+        .x_label_formatter(&|x| {
+            if x.fract() == 0.0 && *x >= 0.0 {
+                labels.get(*x as usize).cloned().unwrap_or_default()
+            } else {
+                String::new()
+            }
+        })
+        .draw()?;
+
+    let width = 0.8 / series.len().max(1) as f64;
+    for (k, s) in series.iter().enumerate() {
+        let color = s.color;
+        chart
+            .draw_series(s.values.iter().enumerate().map(|(i, &v)| {
+                let left = i as f64 - 0.4 + k as f64 * width;
+                Rectangle::new([(left, 0), (left + width, v)], color.filled())
+            }))?
+            .label(s.label.as_str())
+            .legend(move |(x, y)| {
+                Rectangle::new([(x, y - px(5)), (x + px(10), y + px(5))], color.filled())
+            });
+    }
+
+    chart
+        .configure_series_labels()
+        .position(SeriesLabelPosition::UpperLeft)
+        .background_style(theme.background.mix(0.8))
+        .border_style(theme.text)
+        .label_font(("sans-serif", px(15)).into_font().color(&theme.text))
+        .draw()?;
+
+    root.present()?;
+    Ok(())
+}
+
+fn draw_card(
+    caption: &str,
+    lines: &[(&str, String)],
+    year: i32,
+    months: &[usize],
+    lang: Language,
+    theme: ChartTheme,
+    buffer: &mut [u8],
+) -> anyhow::Result<()> {
+    let root = BitMapBackend::with_buffer(buffer, chart_size()).into_drawing_area();
+    root.fill(&theme.background)?;
+    let root = root.titled(
+        caption,
+        ("sans-serif", px(30)).into_font().color(&theme.text),
+    )?;
+    let (text_area, chart_area) = root.split_vertically(chart_size().1 as i32 / 2 - px(40));
+
+    let label_style = ("sans-serif", px(20))
+        .into_font()
+        .color(&theme.text.mix(0.7));
+    let value_style = ("sans-serif", px(20)).into_font().color(&theme.text);
+    for (i, (label, value)) in lines.iter().enumerate() {
+        let y = px(10 + i as i32 * 32);
+        text_area.draw(&Text::new(*label, (px(40), y), label_style.clone()))?;
+        text_area.draw(&Text::new(
+            value.as_str(),
+            (px(300), y),
+            value_style.clone(),
+        ))?;
+    }
+
+    let mut chart = ChartBuilder::on(&chart_area)
+        .margin(px(10))
+        .x_label_area_size(px(40))
+        .y_label_area_size(px(40))
+        .build_cartesian_2d(
+            0..months.len(),
+            0..months.iter().copied().max().unwrap_or(1).max(1),
+        )?;
+    chart
+        .configure_mesh()
+        .axis_style(theme.text.filled())
+        .axis_desc_style(("sans-serif", px(15)).into_font().color(&theme.text))
+        .x_desc(fill(lang.translate("Logs per month, {}"), &[&year]))
+        .label_style(("sans-serif", px(12)).into_font().color(&theme.text))
+        .x_labels(months.len())
+        .x_label_formatter(&|i| format!("{}", i + 1))
+        .disable_mesh()
+        .draw()?;
+    chart.draw_series(
+        Histogram::vertical(&chart)
+            .style(theme.bar.filled())
+            .data(months.iter().enumerate().map(|(i, &v)| (i, v))),
+    )?;
+
+    root.present()?;
+    Ok(())
+}
+
+fn draw_timeline(
+    caption: &str,
+    points: &[(u32, f64)],
+    lang: Language,
+    theme: ChartTheme,
+    buffer: &mut [u8],
+) -> anyhow::Result<()> {
+    let root = BitMapBackend::with_buffer(buffer, chart_size()).into_drawing_area();
+    root.fill(&theme.background)?;
+    let mut chart = ChartBuilder::on(&root)
+        .margin(px(10))
+        .caption(
+            caption,
+            ("sans-serif", px(30)).into_font().color(&theme.text),
+        )
+        .x_label_area_size(px(50))
+        .y_label_area_size(px(50))
+        .build_cartesian_2d(1u32..367u32, 0f64..24f64)?;
+    chart
+        .configure_mesh()
+        .axis_style(theme.text.filled())
+        .axis_desc_style(("sans-serif", px(15)).into_font().color(&theme.text))
+        .bold_line_style(theme.grid)
+        .light_line_style(theme.background)
+        .x_desc(lang.translate("Day of year"))
+        .y_desc(lang.translate("Hour"))
+        .label_style(("sans-serif", px(15)).into_font().color(&theme.text))
+        .y_labels(13)
+        .draw()?;
+    // Small translucent dots keep dense areas distinguishable
+    chart.draw_series(
+        points
+            .iter()
+            .map(|&p| Circle::new(p, px(2), theme.bar.mix(0.35).filled())),
+    )?;
+    root.present()?;
+    Ok(())
+}
+
+fn draw_heatmap(
+    caption: &str,
+    per_day: &HashMap<NaiveDate, usize>,
+    year: i32,
+    months: &[String],
+    language: Option<&str>,
+    theme: ChartTheme,
+    buffer: &mut [u8],
+) -> anyhow::Result<()> {
+    let lang = Language::for_user(language, None);
+    let weekdays = weekday_labels(language);
+    let cell = px(10);
+    let left = px(45);
+    let top = px(180);
+    let root = BitMapBackend::with_buffer(buffer, chart_size()).into_drawing_area();
+    root.fill(&theme.background)?;
+    let root = root.titled(
+        caption,
+        ("sans-serif", px(30)).into_font().color(&theme.text),
+    )?;
+    let Some(jan1) = NaiveDate::from_ymd_opt(year, 1, 1) else {
+        anyhow::bail!("Invalid year {year}");
+    };
+    let first_weekday = jan1.weekday().num_days_from_monday() as i32;
+    let cell_origin = |day: NaiveDate| {
+        let week = (day.ordinal0() as i32 + first_weekday) / 7;
+        let weekday = day.weekday().num_days_from_monday() as i32;
+        (left + week * cell, top + weekday * cell)
+    };
+    let max = per_day.values().copied().max().unwrap_or(1).max(1);
+    // Days without logs are drawn in the grid color
+    let colors: Vec<RGBColor> = std::iter::once(theme.grid).chain(theme.heat).collect();
+    let level = |n: usize| {
+        if n == 0 {
+            0
+        } else {
+            (n * theme.heat.len()).div_ceil(max)
+        }
+    };
+
+    let label_style = ("sans-serif", px(12)).into_font().color(&theme.text);
+    for day in [0, 2, 4] {
+        root.draw(&Text::new(
+            weekdays[day as usize],
+            (left - px(35), top + day * cell - px(1)),
+            label_style.clone(),
+        ))?;
+    }
+    for (month, label) in months.iter().enumerate() {
+        if let Some(first) = NaiveDate::from_ymd_opt(year, month as u32 + 1, 1) {
+            let (x, _) = cell_origin(first);
+            root.draw(&Text::new(
+                label.as_str(),
+                (x, top - px(18)),
+                label_style.clone(),
+            ))?;
+        }
+    }
+    for day in jan1.iter_days().take_while(|d| d.year() == year) {
+        let (x, y) = cell_origin(day);
+        let color = colors[level(per_day.get(&day).copied().unwrap_or(0))];
+        root.draw(&Rectangle::new(
+            [(x, y), (x + cell - px(2), y + cell - px(2))],
+            color.filled(),
+        ))?;
+    }
+
+    let legend_y = top + 8 * cell + px(10);
+    let legend_x = left + 53 * cell - colors.len() as i32 * cell - px(70);
+    // Right-aligned, translations are longer than "Less"
+    root.draw(&Text::new(
+        lang.translate("Less"),
+        (legend_x + px(25), legend_y - px(1)),
+        label_style.clone().pos(Pos::new(HPos::Right, VPos::Top)),
+    ))?;
+    for (i, color) in colors.iter().enumerate() {
+        let x = legend_x + px(30) + i as i32 * cell;
+        root.draw(&Rectangle::new(
+            [(x, legend_y), (x + cell - px(2), legend_y + cell - px(2))],
+            color.filled(),
+        ))?;
+    }
+    root.draw(&Text::new(
+        fill(lang.translate("More (max {})"), &[&max]),
+        (
+            legend_x + px(35) + colors.len() as i32 * cell,
+            legend_y - px(1),
+        ),
+        label_style,
+    ))?;
+
     root.present()?;
     Ok(())
 }
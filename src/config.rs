@@ -0,0 +1,142 @@
+use std::{env, path::PathBuf, str::FromStr, time::Duration};
+
+use url::Url;
+
+#[derive(Clone)]
+pub struct Config {
+    /// One bot runs per token, all sharing the same database.
+    pub bot_tokens: Vec<String>,
+    /// Telegram IDs of the users allowed to run admin commands.
+    pub admin_ids: Vec<i64>,
+    /// Minimum streak length (in days) worth warning the user about.
+    pub streak_warning_threshold: u32,
+    /// How many hours before the end of the user's day the warning is sent.
+    pub streak_warning_hours: u32,
+    /// Minimum time between two runs of the same expensive command (e.g. a chart) by a user.
+    pub command_cooldown: Duration,
+    /// A `/done` this soon after the user's last log asks for confirmation, zero turns it off.
+    pub duplicate_cooldown: Duration,
+    /// Whether `/importurl` may download files.
+    pub url_import: bool,
+    /// Directory for periodic database backups, none are made if unset.
+    pub backup_dir: Option<PathBuf>,
+    pub backup_interval: Duration,
+    /// Number of backups kept, older ones are deleted.
+    pub backup_retention: usize,
+    /// Plain messages that log like `/done`, lowercase without a leading slash.
+    pub done_aliases: Vec<String>,
+    /// Whether privileged actions are recorded in the audit log.
+    pub audit_log: bool,
+    /// Logs per user per (local) day counted toward scores in chats without their own `/dailycap`.
+    pub daily_cap: Option<i64>,
+    /// How many logs a user may grant others per day by replying with `/done` or `/kudos`.
+    pub kudos_daily_limit: i64,
+    /// Public URL to receive updates on instead of long polling.
+    pub webhook_url: Option<Url>,
+    pub port: u16,
+    /// Expected in the `X-Telegram-Bot-Api-Secret-Token` header of webhook requests,
+    /// random if unset.
+    pub webhook_secret: Option<String>,
+}
+
+impl Config {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let admin_ids = match env::var("ADMIN_IDS") {
+            Ok(ids) => ids
+                .split(',')
+                .map(|id| id.trim().parse())
+                .collect::<Result<_, _>>()?,
+            Err(_) => Vec::new(),
+        };
+        let bot_tokens: Vec<String> = env::var("TELOXIDE_TOKEN")?
+            .split(',')
+            .map(|token| token.trim().to_string())
+            .filter(|token| !token.is_empty())
+            .collect();
+        anyhow::ensure!(!bot_tokens.is_empty(), "TELOXIDE_TOKEN is empty");
+        let daily_cap: Option<i64> = env::var("DAILY_CAP")
+            .ok()
+            .map(|cap| cap.parse())
+            .transpose()?;
+        anyhow::ensure!(
+            daily_cap.is_none_or(|cap| cap > 0),
+            "DAILY_CAP must be positive"
+        );
+        let webhook_url: Option<Url> = env::var("WEBHOOK_URL")
+            .ok()
+            .map(|u| u.parse())
+            .transpose()?;
+        anyhow::ensure!(
+            webhook_url.is_none() || bot_tokens.len() == 1,
+            "WEBHOOK_URL only supports a single bot token"
+        );
+        let webhook_secret = env::var("WEBHOOK_SECRET").ok();
+        anyhow::ensure!(
+            webhook_secret.as_deref().is_none_or(is_valid_secret),
+            "WEBHOOK_SECRET must be 1-256 characters of A-Z, a-z, 0-9, _ and -"
+        );
+        let backup_dir = env::var("BACKUP_DIR").ok().map(PathBuf::from);
+        #[cfg(feature = "postgres")]
+        anyhow::ensure!(
+            backup_dir.is_none(),
+            "BACKUP_DIR needs SQLite, back up Postgres with pg_dump instead"
+        );
+        Ok(Self {
+            bot_tokens,
+            admin_ids,
+            streak_warning_threshold: var_or("STREAK_WARNING_THRESHOLD", 3)?,
+            streak_warning_hours: var_or("STREAK_WARNING_HOURS", 2)?,
+            command_cooldown: Duration::from_secs(var_or("COMMAND_COOLDOWN_SECS", 5)?),
+            duplicate_cooldown: Duration::from_secs(var_or("DUPLICATE_COOLDOWN_SECS", 60)?),
+            url_import: var_or("ALLOW_URL_IMPORT", false)?,
+            backup_dir,
+            backup_interval: Duration::from_secs(
+                var_or("BACKUP_INTERVAL_HOURS", 24u64)?.max(1) * 3600,
+            ),
+            backup_retention: var_or("BACKUP_RETENTION", 7usize)?.max(1),
+            done_aliases: env::var("DONE_ALIASES")
+                .map(|aliases| {
+                    aliases
+                        .split(',')
+                        .map(|a| a.trim().trim_start_matches('/').to_lowercase())
+                        .filter(|a| !a.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            audit_log: var_or("AUDIT_LOG", false)?,
+            daily_cap,
+            kudos_daily_limit: var_or("KUDOS_DAILY_LIMIT", 3)?,
+            webhook_url,
+            port: var_or("PORT", 8080)?,
+            webhook_secret,
+        })
+    }
+
+    pub fn is_admin(&self, tg_id: i64) -> bool {
+        self.admin_ids.contains(&tg_id)
+    }
+
+    /// The chat's own daily cap, otherwise the deployment's.
+    pub fn daily_cap(&self, chat_cap: Option<i64>) -> Option<i64> {
+        chat_cap.or(self.daily_cap)
+    }
+}
+
+/// Whether Telegram accepts `secret` as a webhook secret token.
+fn is_valid_secret(secret: &str) -> bool {
+    (1..=256).contains(&secret.len())
+        && secret
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-')
+}
+
+fn var_or<T>(key: &str, default: T) -> anyhow::Result<T>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    match env::var(key) {
+        Ok(value) => Ok(value.parse()?),
+        Err(_) => Ok(default),
+    }
+}
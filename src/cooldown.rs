@@ -0,0 +1,44 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Remembers when each user last ran each rate-limited command.
+#[derive(Clone)]
+pub struct Cooldowns {
+    period: Duration,
+    last_used: Arc<Mutex<HashMap<(i64, &'static str), Instant>>>,
+}
+
+impl Cooldowns {
+    pub fn new(period: Duration) -> Self {
+        Self {
+            period,
+            last_used: Arc::default(),
+        }
+    }
+
+    /// Records that the user ran the command,
+    /// returning `false` if they already did so within the cooldown period.
+    pub fn try_acquire(&self, tg_id: i64, command: &'static str) -> bool {
+        let mut last_used = self.last_used.lock().unwrap();
+        let now = Instant::now();
+        match last_used.get(&(tg_id, command)) {
+            Some(&at) if now.duration_since(at) < self.period => false,
+            _ => {
+                last_used.insert((tg_id, command), now);
+                true
+            }
+        }
+    }
+
+    /// Forgets invocations whose cooldown has run out.
+    pub fn prune(&self) {
+        let period = self.period;
+        self.last_used
+            .lock()
+            .unwrap()
+            .retain(|_, at| at.elapsed() < period);
+    }
+}
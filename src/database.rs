@@ -1,95 +1,2539 @@
-use std::env;
+use std::{
+    env,
+    path::Path,
+    time::{Duration, Instant},
+};
 
-use sqlx::{SqlitePool, migrate};
+use chrono::{DateTime, Datelike, NaiveDate};
+use sqlx::{Pool, migrate::Migrator, pool::PoolOptions};
+use tracing::warn;
+
+/// The database the bot is built for. The queries are written to run on both SQLite and
+/// Postgres, except where `cfg` picks one.
+#[cfg(not(feature = "postgres"))]
+type Db = sqlx::Sqlite;
+#[cfg(feature = "postgres")]
+type Db = sqlx::Postgres;
+
+#[cfg(not(feature = "postgres"))]
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations/");
+#[cfg(feature = "postgres")]
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations/postgres/");
+
+#[cfg(not(feature = "postgres"))]
+const URL_SCHEMES: &[&str] = &["sqlite"];
+#[cfg(feature = "postgres")]
+const URL_SCHEMES: &[&str] = &["postgres", "postgresql"];
+
+/// Connections are recycled so that a replaced database file (e.g. restored from a backup)
+/// is picked up without restarting the bot.
+const CONNECTION_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+const CONNECTION_MAX_LIFETIME: Duration = Duration::from_secs(10 * 60);
+
+/// Queries taking longer than this are logged, unless overridden by `SLOW_QUERY_MS`.
+const DEFAULT_SLOW_QUERY_THRESHOLD: Duration = Duration::from_millis(200);
 
 #[derive(Clone)]
 pub struct Database {
-    pool: SqlitePool,
+    pool: Pool<Db>,
+    slow_query_threshold: Duration,
+    /// Stored timestamps are rounded down to a multiple of this many seconds.
+    timestamp_precision: i64,
+}
+
+#[derive(Default)]
+pub struct ChatSettings {
+    pub anonymous_leaderboard: bool,
+    /// Only the first log of each day counts.
+    pub habit_mode: bool,
+    /// At most this many logs per user per day count toward scores.
+    pub daily_cap: Option<i64>,
+    /// Monthly number of logs the chat members aim for together.
+    pub team_goal: Option<i64>,
+    /// Only logs from this timestamp on count toward scores in this chat.
+    pub season_start: Option<i64>,
+    /// How new logs are acknowledged, `None` for a reply.
+    pub acknowledgement: Option<String>,
+}
+
+pub struct UserSettings {
+    pub utc_offset: i64,
+    pub weekly_goal: Option<i64>,
+    pub monthly_goal: Option<i64>,
+    pub tracking_start: Option<i64>,
+    pub chart_theme: Option<String>,
+    /// Left out of leaderboards, streak rankings and comparisons.
+    pub hide_from_rankings: bool,
+    /// Goals, achievements and level-ups are recorded without announcing them.
+    pub quiet: bool,
+    /// Minutes after local midnight of the daily reminder, if any.
+    pub reminder_minute: Option<i64>,
+    /// Language code of the replies, `None` to follow the user's Telegram app.
+    pub language: Option<String>,
+    /// Minutes after local midnight at which the user's day ends, see `stats::day_offset`.
+    pub day_end: i64,
+    /// The user's own replies to `/done`, phrases separated by `|`, `None` for the default one.
+    pub ack_messages: Option<String>,
+    /// Messages like "done" or "+1" in a private chat log like `/done`.
+    pub plain_logging: bool,
+}
+
+pub struct SharedSettings {
+    pub utc_offset: i64,
+    pub day_end: i64,
+    pub weekly_goal: Option<i64>,
+    pub monthly_goal: Option<i64>,
+    pub chart_theme: Option<String>,
+}
+
+pub struct UserTotal {
+    pub telegram_id: i64,
+    pub username: Option<String>,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub logs: i64,
+}
+
+/// A leaderboard row with the user's 1-based position.
+pub struct RankedTotal {
+    pub user_id: i64,
+    pub telegram_id: i64,
+    pub username: Option<String>,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub logs: i64,
+    pub position: i64,
+}
+
+pub struct UserName {
+    pub id: i64,
+    pub telegram_id: i64,
+    pub username: Option<String>,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+}
+
+pub struct LogEntry {
+    pub id: i64,
+    pub timestamp: i64,
+    pub category: Option<String>,
+    pub note: Option<String>,
+}
+
+/// What became of kudos.
+#[derive(Debug, PartialEq)]
+pub enum Kudos {
+    Given,
+    /// The recipient already has a log in the given range.
+    AlreadyLogged,
+    /// The giver has given all their kudos of the last 24 hours.
+    LimitReached,
+}
+
+pub struct AuditEntry {
+    pub actor_telegram_id: i64,
+    pub action: String,
+    pub target: Option<String>,
+    pub timestamp: i64,
+}
+
+pub struct StreakWarningCandidate {
+    pub user_id: i64,
+    pub telegram_id: i64,
+    pub utc_offset: i64,
+    pub day_end: i64,
+    pub language: Option<String>,
+    pub streak_warned_on: Option<String>,
+}
+
+pub struct DigestSubscriber {
+    pub user_id: i64,
+    pub telegram_id: i64,
+    pub utc_offset: i64,
+    pub day_end: i64,
+    pub chart_theme: Option<String>,
+    pub language: Option<String>,
+    pub sent_for_week: String,
+}
+
+pub struct Reminder {
+    pub user_id: i64,
+    pub telegram_id: i64,
+    pub utc_offset: i64,
+    pub day_end: i64,
+    pub minute: i64,
+    pub language: Option<String>,
+    pub reminded_on: Option<String>,
 }
 
 impl Database {
     pub async fn new() -> anyhow::Result<Self> {
         let url = env::var("DATABASE_URL")?;
-        let pool = SqlitePool::connect(&url).await?;
-        migrate!("./migrations/").run(&pool).await?;
-        Ok(Self { pool })
-    }
-
-    pub async fn get_user_id(&self, tg_id: i64) -> anyhow::Result<i64> {
-        Ok(sqlx::query_scalar!(
-            r#"
-            INSERT INTO users (telegram_id) VALUES (?)
-            ON CONFLICT(telegram_id) DO UPDATE SET telegram_id = telegram_id
-            RETURNING id;
-            "#,
-            tg_id,
-        )
-        .fetch_one(&self.pool)
-        .await?)
-    }
-
-    pub async fn insert_log(&self, user_id: i64, ts: i64) -> anyhow::Result<()> {
-        sqlx::query!(
-            "INSERT INTO logs (user_id, timestamp) VALUES (?, ?)",
-            user_id,
-            ts,
-        )
-        .execute(&self.pool)
-        .await?;
-        Ok(())
-    }
-
-    pub async fn get_user_stats(&self, user_id: i64) -> anyhow::Result<i64> {
-        Ok(
-            sqlx::query_scalar!("SELECT COUNT(*) FROM logs WHERE user_id = ?;", user_id)
-                .fetch_one(&self.pool)
-                .await?,
-        )
+        // The queries are checked against one database at compile time, chosen by the features
+        let scheme = url.split_once(':').map_or("", |(scheme, _)| scheme);
+        if !URL_SCHEMES.contains(&scheme) {
+            anyhow::bail!(
+                "Unsupported database {scheme:?}, DATABASE_URL must be a {}: URL",
+                URL_SCHEMES[0]
+            );
+        }
+        let pool = pool_options(CONNECTION_IDLE_TIMEOUT, CONNECTION_MAX_LIFETIME)
+            .connect(&url)
+            .await?;
+        MIGRATOR.run(&pool).await?;
+        let slow_query_threshold = env::var("SLOW_QUERY_MS")
+            .ok()
+            .and_then(|ms| ms.parse().ok())
+            .map_or(DEFAULT_SLOW_QUERY_THRESHOLD, Duration::from_millis);
+        let timestamp_precision = match env::var("TIMESTAMP_PRECISION").as_deref() {
+            Err(_) | Ok("second") => 1,
+            Ok("minute") => 60,
+            Ok("hour") => 60 * 60,
+            Ok(other) => anyhow::bail!("Unknown TIMESTAMP_PRECISION: {other}"),
+        };
+        Ok(Self {
+            pool,
+            slow_query_threshold,
+            timestamp_precision,
+        })
+    }
+
+    /// A fresh migrated database in memory, for tests. It has a single connection that is
+    /// never recycled, as every connection to `sqlite::memory:` gets its own database.
+    #[cfg(all(test, not(feature = "postgres")))]
+    pub async fn for_tests() -> anyhow::Result<Self> {
+        let pool = PoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect("sqlite::memory:")
+            .await?;
+        Self::migrated_for_tests(pool).await
+    }
+
+    /// A fresh migrated database for tests, in a new schema of the `DATABASE_URL` database.
+    #[cfg(all(test, feature = "postgres"))]
+    pub async fn for_tests() -> anyhow::Result<Self> {
+        use sqlx::{Connection, PgConnection, postgres::PgConnectOptions};
+
+        let url = env::var("DATABASE_URL")?;
+        let schema = format!("test_{:08x}", rand::random::<u32>());
+        let mut conn = PgConnection::connect(&url).await?;
+        sqlx::query(&format!("CREATE SCHEMA {schema};"))
+            .execute(&mut conn)
+            .await?;
+        let options = url
+            .parse::<PgConnectOptions>()?
+            .options([("search_path", schema)]);
+        let pool = PoolOptions::new().connect_with(options).await?;
+        Self::migrated_for_tests(pool).await
+    }
+
+    #[cfg(test)]
+    async fn migrated_for_tests(pool: Pool<Db>) -> anyhow::Result<Self> {
+        MIGRATOR.run(&pool).await?;
+        Ok(Self {
+            pool,
+            slow_query_threshold: DEFAULT_SLOW_QUERY_THRESHOLD,
+            timestamp_precision: 1,
+        })
+    }
+
+    /// Awaits a query, logging it if it took longer than the slow query threshold.
+    async fn timed<T>(&self, name: &str, query: impl Future<Output = T>) -> T {
+        let start = Instant::now();
+        let result = query.await;
+        let elapsed = start.elapsed();
+        if elapsed > self.slow_query_threshold {
+            warn!("Slow query in Database::{name}: {elapsed:?}");
+        }
+        result
+    }
+
+    /// Runs `VACUUM` and `ANALYZE` on a dedicated connection,
+    /// returning the database size in bytes before and after.
+    pub async fn maintenance(&self) -> anyhow::Result<(i64, i64)> {
+        self.timed("maintenance", async {
+            let mut conn = self.pool.acquire().await?;
+            #[cfg(not(feature = "postgres"))]
+            let size_query =
+                "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size();";
+            #[cfg(feature = "postgres")]
+            let size_query = "SELECT pg_database_size(current_database());";
+            let before: i64 = sqlx::query_scalar(size_query).fetch_one(&mut *conn).await?;
+            sqlx::query("VACUUM;").execute(&mut *conn).await?;
+            sqlx::query("ANALYZE;").execute(&mut *conn).await?;
+            let after: i64 = sqlx::query_scalar(size_query).fetch_one(&mut *conn).await?;
+            Ok((before, after))
+        })
+        .await
+    }
+
+    /// Writes a consistent copy of the database to `path` with `VACUUM INTO`,
+    /// which doesn't block writers.
+    #[cfg(not(feature = "postgres"))]
+    pub async fn backup_to(&self, path: &Path) -> anyhow::Result<()> {
+        let path = path
+            .to_str()
+            .ok_or_else(|| anyhow::anyhow!("The backup path is not valid UTF-8"))?;
+        self.timed("backup_to", async {
+            sqlx::query("VACUUM INTO ?;")
+                .bind(path)
+                .execute(&self.pool)
+                .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Postgres is backed up with its own tools, so there's no copy to write.
+    #[cfg(feature = "postgres")]
+    pub async fn backup_to(&self, _path: &Path) -> anyhow::Result<()> {
+        anyhow::bail!("Backups need SQLite, back up Postgres with pg_dump instead")
+    }
+
+    /// Whether timestamps are stored to the second, i.e. logs sharing one are duplicates.
+    pub fn has_exact_timestamps(&self) -> bool {
+        self.timestamp_precision == 1
+    }
+
+    /// Deletes all but the first of logs with the same user, timestamp, chat and category,
+    /// e.g. from redelivered updates. Returns how many were deleted.
+    pub async fn delete_duplicate_logs(&self) -> anyhow::Result<u64> {
+        self.timed("delete_duplicate_logs", async {
+            let mut tx = self.pool.begin().await?;
+            let result = sqlx::query!(
+                r#"
+                DELETE FROM logs
+                WHERE id NOT IN (
+                    SELECT MIN(id) FROM logs GROUP BY user_id, timestamp, chat_id, category
+                );
+                "#,
+            )
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+            Ok(result.rows_affected())
+        })
+        .await
+    }
+
+    /// Inserts and deletes a throwaway log for the user in a transaction,
+    /// which is rolled back if any step fails.
+    pub async fn check_writes(&self, user_id: i64) -> anyhow::Result<()> {
+        self.timed("check_writes", async {
+            let mut tx = self.pool.begin().await?;
+            let id = sqlx::query_scalar!(
+                r#"INSERT INTO logs (user_id, timestamp) VALUES ($1, 0) RETURNING id as "id!";"#,
+                user_id,
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            let deleted = sqlx::query!("DELETE FROM logs WHERE id = $1;", id)
+                .execute(&mut *tx)
+                .await?;
+            anyhow::ensure!(
+                deleted.rows_affected() == 1,
+                "The throwaway log was not found"
+            );
+            tx.commit().await?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Returns the `CREATE` statements of all tables and indexes.
+    #[cfg(not(feature = "postgres"))]
+    pub async fn get_schema(&self) -> anyhow::Result<Vec<String>> {
+        self.timed("get_schema", async {
+            Ok(sqlx::query_scalar(
+                "SELECT sql FROM sqlite_master WHERE sql IS NOT NULL ORDER BY type DESC, name;",
+            )
+            .fetch_all(&self.pool)
+            .await?)
+        })
+        .await
+    }
+
+    /// Returns the `CREATE` statements of all tables and indexes. Postgres doesn't keep them,
+    /// so the tables are rebuilt from their columns.
+    #[cfg(feature = "postgres")]
+    pub async fn get_schema(&self) -> anyhow::Result<Vec<String>> {
+        self.timed("get_schema", async {
+            Ok(sqlx::query_scalar(
+                r#"
+                SELECT sql FROM (
+                    SELECT 0 AS kind, table_name::text AS name,
+                        'CREATE TABLE ' || table_name || ' (' || string_agg(
+                            column_name || ' ' || data_type, ', ' ORDER BY ordinal_position
+                        ) || ')' AS sql
+                    FROM information_schema.columns
+                    WHERE table_schema = current_schema()
+                    GROUP BY table_name
+                    UNION ALL
+                    SELECT 1, indexname::text, indexdef
+                    FROM pg_indexes
+                    WHERE schemaname = current_schema()
+                ) AS schema
+                ORDER BY kind, name;
+                "#,
+            )
+            .fetch_all(&self.pool)
+            .await?)
+        })
+        .await
+    }
+
+    /// Returns the version, description and success flag of every applied migration.
+    pub async fn get_applied_migrations(&self) -> anyhow::Result<Vec<(i64, String, bool)>> {
+        self.timed("get_applied_migrations", async {
+            Ok(sqlx::query_as(
+                "SELECT version, description, success FROM _sqlx_migrations ORDER BY version;",
+            )
+            .fetch_all(&self.pool)
+            .await?)
+        })
+        .await
+    }
+
+    /// Returns the user's ID, creating the user if needed, and caches their current
+    /// username and name.
+    pub async fn get_user_id(
+        &self,
+        tg_id: i64,
+        username: Option<&str>,
+        first_name: &str,
+        last_name: Option<&str>,
+    ) -> anyhow::Result<i64> {
+        self.timed("get_user_id", async {
+            Ok(sqlx::query_scalar!(
+                r#"
+                INSERT INTO users (telegram_id, username, first_name, last_name) VALUES ($1, $2, $3, $4)
+                ON CONFLICT(telegram_id) DO UPDATE SET
+                    username = excluded.username,
+                    first_name = excluded.first_name,
+                    last_name = excluded.last_name
+                RETURNING id;
+                "#,
+                tg_id,
+                username,
+                first_name,
+                last_name,
+            )
+            .fetch_one(&self.pool)
+            .await?)
+        })
+        .await
+    }
+
+    pub async fn insert_log(
+        &self,
+        user_id: i64,
+        chat_id: i64,
+        ts: i64,
+        category: Option<&str>,
+        note: Option<&str>,
+    ) -> anyhow::Result<()> {
+        let ts = round_down(ts, self.timestamp_precision);
+        self.timed("insert_log", async {
+            sqlx::query!(
+                r#"
+                INSERT INTO logs (user_id, chat_id, timestamp, category, note)
+                VALUES ($1, $2, $3, $4, $5)
+                "#,
+                user_id,
+                chat_id,
+                ts,
+                category,
+                note,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Inserts logs without a chat or category in one transaction.
+    pub async fn insert_logs(&self, user_id: i64, timestamps: &[i64]) -> anyhow::Result<()> {
+        self.timed("insert_logs", async {
+            let mut tx = self.pool.begin().await?;
+            for &ts in timestamps {
+                let ts = round_down(ts, self.timestamp_precision);
+                sqlx::query!(
+                    "INSERT INTO logs (user_id, timestamp) VALUES ($1, $2)",
+                    user_id,
+                    ts,
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+            tx.commit().await?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Inserts a log unless the user already has one in `[from, to)`.
+    /// Returns whether the log was inserted.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn insert_log_once_between(
+        &self,
+        user_id: i64,
+        chat_id: i64,
+        ts: i64,
+        category: Option<&str>,
+        note: Option<&str>,
+        from: i64,
+        to: i64,
+    ) -> anyhow::Result<bool> {
+        let ts = round_down(ts, self.timestamp_precision);
+        self.timed("insert_log_once_between", async {
+            let result = sqlx::query!(
+                r#"
+                INSERT INTO logs (user_id, chat_id, timestamp, category, note)
+                SELECT $1, $2, $3, $4, $5
+                WHERE NOT EXISTS (
+                    SELECT 1 FROM logs WHERE user_id = $6 AND timestamp >= $7 AND timestamp < $8
+                );
+                "#,
+                user_id,
+                chat_id,
+                ts,
+                category,
+                note,
+                user_id,
+                from,
+                to,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(result.rows_affected() > 0)
+        })
+        .await
+    }
+
+    /// Logs an entry for `user_id` on behalf of `granted_by`, unless `granted_by` has given
+    /// `daily_limit` kudos in the 24 hours before `ts`. With `once_between`, the entry is also
+    /// skipped if the user already has a log in that range.
+    pub async fn insert_kudos(
+        &self,
+        user_id: i64,
+        chat_id: i64,
+        ts: i64,
+        granted_by: i64,
+        daily_limit: i64,
+        once_between: Option<(i64, i64)>,
+    ) -> anyhow::Result<Kudos> {
+        const DAY: i64 = 24 * 60 * 60;
+        let rounded = round_down(ts, self.timestamp_precision);
+        let since = ts - DAY;
+        let (from, to) = once_between.unzip();
+        self.timed("insert_kudos", async {
+            // One statement, so concurrent kudos can't both pass the limit
+            let result = sqlx::query!(
+                r#"
+                INSERT INTO logs (user_id, chat_id, timestamp, granted_by, granted_at)
+                SELECT $1, $2, $3, $4, $5
+                WHERE NOT EXISTS (
+                    SELECT 1 FROM logs WHERE user_id = $1 AND timestamp >= $6 AND timestamp < $7
+                ) AND (
+                    SELECT COUNT(*) FROM logs WHERE granted_by = $4 AND granted_at > $8
+                ) < $9;
+                "#,
+                user_id,
+                chat_id,
+                rounded,
+                granted_by,
+                ts,
+                from,
+                to,
+                since,
+                daily_limit,
+            )
+            .execute(&self.pool)
+            .await?;
+            if result.rows_affected() > 0 {
+                return Ok(Kudos::Given);
+            }
+            let given = sqlx::query_scalar!(
+                r#"SELECT COUNT(*) as "count!" FROM logs WHERE granted_by = $1 AND granted_at > $2;"#,
+                granted_by,
+                since,
+            )
+            .fetch_one(&self.pool)
+            .await?;
+            Ok(if given >= daily_limit {
+                Kudos::LimitReached
+            } else {
+                Kudos::AlreadyLogged
+            })
+        })
+        .await
+    }
+
+    /// Deletes the user's most recent log, returning whether there was one.
+    pub async fn delete_last_log(&self, user_id: i64) -> anyhow::Result<bool> {
+        self.timed("delete_last_log", async {
+            let result = sqlx::query!(
+                r#"
+                DELETE FROM logs
+                WHERE id = (
+                    SELECT id FROM logs WHERE user_id = $1 ORDER BY timestamp DESC, id DESC LIMIT 1
+                );
+                "#,
+                user_id,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(result.rows_affected() > 0)
+        })
+        .await
+    }
+
+    /// Deletes the user's logs in `[from, to)`, returning how many were deleted.
+    pub async fn delete_logs_between(
+        &self,
+        user_id: i64,
+        from: i64,
+        to: i64,
+    ) -> anyhow::Result<u64> {
+        self.timed("delete_logs_between", async {
+            let result = sqlx::query!(
+                "DELETE FROM logs WHERE user_id = $1 AND timestamp >= $2 AND timestamp < $3;",
+                user_id,
+                from,
+                to,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(result.rows_affected())
+        })
+        .await
+    }
+
+    // The per-user queries below skip logs before the user's `tracking_start`.
+
+    /// Returns the user's score, counting at most `daily_cap` logs per (local) day
+    /// and only logs from `since` on.
+    pub async fn get_user_stats(
+        &self,
+        user_id: i64,
+        daily_cap: Option<i64>,
+        since: Option<i64>,
+    ) -> anyhow::Result<i64> {
+        self.timed("get_user_stats", async {
+            Ok(sqlx::query_scalar!(
+                r#"
+                SELECT COALESCE(CAST(SUM(CASE WHEN daily.n > $1 THEN $1 ELSE daily.n END) AS BIGINT), 0) as "score!: i64"
+                FROM (
+                    SELECT COUNT(*) as n
+                    FROM logs l
+                    JOIN users u on u.id = l.user_id
+                    WHERE l.user_id = $2 AND l.timestamp >= COALESCE(u.tracking_start, l.timestamp)
+                        AND l.timestamp >= COALESCE($3, l.timestamp)
+                    GROUP BY (l.timestamp + (u.utc_offset - u.day_end) * 60) / 86400
+                ) daily;
+                "#,
+                daily_cap,
+                user_id,
+                since,
+            )
+            .fetch_one(&self.pool)
+            .await?)
+        })
+        .await
+    }
+
+    pub async fn get_count_between(&self, user_id: i64, from: i64, to: i64) -> anyhow::Result<i64> {
+        self.timed("get_count_between", async {
+            Ok(sqlx::query_scalar!(
+                r#"
+                SELECT COUNT(*) as "count!"
+                FROM logs l
+                JOIN users u on u.id = l.user_id
+                WHERE l.user_id = $1 AND l.timestamp >= COALESCE(u.tracking_start, l.timestamp)
+                    AND l.timestamp >= $2 AND l.timestamp < $3;
+                "#,
+                user_id,
+                from,
+                to,
+            )
+            .fetch_one(&self.pool)
+            .await?)
+        })
+        .await
+    }
+
+    /// Returns the number of logs in `[from, to)` and on how many (local) days they happened.
+    pub async fn get_activity_between(
+        &self,
+        user_id: i64,
+        from: i64,
+        to: i64,
+    ) -> anyhow::Result<(i64, i64)> {
+        self.timed("get_activity_between", async {
+            let row = sqlx::query!(
+                r#"
+                SELECT
+                    COUNT(*) as "logs!: i64",
+                    COUNT(DISTINCT (l.timestamp + (u.utc_offset - u.day_end) * 60) / 86400) as "days!: i64"
+                FROM logs l
+                JOIN users u on u.id = l.user_id
+                WHERE l.user_id = $1 AND l.timestamp >= COALESCE(u.tracking_start, l.timestamp)
+                    AND l.timestamp >= $2 AND l.timestamp < $3;
+                "#,
+                user_id,
+                from,
+                to,
+            )
+            .fetch_one(&self.pool)
+            .await?;
+            Ok((row.logs, row.days))
+        })
+        .await
+    }
+
+    /// Returns the ID of the user's newest log, 0 if there are none.
+    pub async fn get_latest_log_id(&self, user_id: i64) -> anyhow::Result<i64> {
+        self.timed("get_latest_log_id", async {
+            Ok(sqlx::query_scalar!(
+                r#"SELECT COALESCE(MAX(id), 0) as "id!: i64" FROM logs WHERE user_id = $1;"#,
+                user_id,
+            )
+            .fetch_one(&self.pool)
+            .await?)
+        })
+        .await
+    }
+
+    /// Returns the time of the user's newest log, `None` if there are none.
+    pub async fn get_latest_log_timestamp(&self, user_id: i64) -> anyhow::Result<Option<i64>> {
+        self.timed("get_latest_log_timestamp", async {
+            Ok(sqlx::query_scalar!(
+                r#"SELECT MAX(timestamp) as "timestamp?: i64" FROM logs WHERE user_id = $1;"#,
+                user_id,
+            )
+            .fetch_one(&self.pool)
+            .await?)
+        })
+        .await
+    }
+
+    pub async fn get_user_timestamps_between(
+        &self,
+        user_id: i64,
+        from: i64,
+        to: i64,
+    ) -> anyhow::Result<Vec<i64>> {
+        self.timed("get_user_timestamps_between", async {
+            Ok(sqlx::query_scalar!(
+                r#"
+                SELECT l.timestamp
+                FROM logs l
+                JOIN users u on u.id = l.user_id
+                WHERE l.user_id = $1 AND l.timestamp >= COALESCE(u.tracking_start, l.timestamp)
+                    AND l.timestamp >= $2 AND l.timestamp < $3;
+                "#,
+                user_id,
+                from,
+                to,
+            )
+            .fetch_all(&self.pool)
+            .await?)
+        })
+        .await
     }
 
     pub async fn get_all_user_timestamps(&self, user_id: i64) -> anyhow::Result<Vec<i64>> {
-        Ok(
-            sqlx::query_scalar!("SELECT timestamp FROM logs WHERE user_id = ?;", user_id)
-                .fetch_all(&self.pool)
-                .await?,
-        )
-    }
-
-    pub async fn get_leaderboard(&self) -> anyhow::Result<Vec<(i64, i64)>> {
-        Ok(sqlx::query!(
-            r#"
-            SELECT u.telegram_id, COUNT(l.id) as logs
-            FROM users u
-            JOIN logs l on l.user_id = u.id
-            GROUP BY u.id
-            ORDER BY logs DESC
-            LIMIT 10;
-            "#,
-        )
-        .fetch_all(&self.pool)
-        .await?
-        .iter()
-        .map(|r| (r.telegram_id, r.logs))
-        .collect())
+        self.timed("get_all_user_timestamps", async {
+            Ok(sqlx::query_scalar!(
+                r#"
+                SELECT l.timestamp
+                FROM logs l
+                JOIN users u on u.id = l.user_id
+                WHERE l.user_id = $1 AND l.timestamp >= COALESCE(u.tracking_start, l.timestamp);
+                "#,
+                user_id,
+            )
+            .fetch_all(&self.pool)
+            .await?)
+        })
+        .await
+    }
+
+    /// Returns the user's number of logs since `day_start` and since the earlier `month_start`.
+    pub async fn get_day_and_month_counts(
+        &self,
+        user_id: i64,
+        day_start: i64,
+        month_start: i64,
+    ) -> anyhow::Result<(i64, i64)> {
+        self.timed("get_day_and_month_counts", async {
+            let row = sqlx::query!(
+                r#"
+                SELECT COALESCE(SUM(CASE WHEN timestamp >= $2 THEN 1 ELSE 0 END), 0) as "day!: i64", COUNT(*) as "month!: i64"
+                FROM logs
+                WHERE user_id = $1 AND timestamp >= $3;
+                "#,
+                user_id,
+                day_start,
+                month_start,
+            )
+            .fetch_one(&self.pool)
+            .await?;
+            Ok((row.day, row.month))
+        })
+        .await
+    }
+
+    /// Returns the (local) days on which the user logged since their tracking start,
+    /// as day numbers since 1970-01-01.
+    pub async fn get_active_days(&self, user_id: i64) -> anyhow::Result<Vec<i64>> {
+        self.timed("get_active_days", async {
+            Ok(sqlx::query_scalar!(
+                r#"
+                SELECT DISTINCT (l.timestamp + (u.utc_offset - u.day_end) * 60) / 86400 as "day!: i64"
+                FROM logs l
+                JOIN users u on u.id = l.user_id
+                WHERE l.user_id = $1 AND l.timestamp >= COALESCE(u.tracking_start, l.timestamp);
+                "#,
+                user_id,
+            )
+            .fetch_all(&self.pool)
+            .await?)
+        })
+        .await
+    }
+
+    /// Returns up to `limit` of the user's logs with an ID above `after_id`, oldest first,
+    /// including logs before the tracking start.
+    pub async fn get_logs_page(
+        &self,
+        user_id: i64,
+        after_id: i64,
+        limit: i64,
+    ) -> anyhow::Result<Vec<LogEntry>> {
+        self.timed("get_logs_page", async {
+            Ok(sqlx::query_as!(
+                LogEntry,
+                r#"
+                SELECT id, timestamp, category, note
+                FROM logs
+                WHERE user_id = $1 AND id > $2
+                ORDER BY id
+                LIMIT $3;
+                "#,
+                user_id,
+                after_id,
+                limit,
+            )
+            .fetch_all(&self.pool)
+            .await?)
+        })
+        .await
+    }
+
+    /// Returns up to `limit` of the user's logs, newest first, skipping the `offset` newest.
+    pub async fn get_recent_logs(
+        &self,
+        user_id: i64,
+        offset: i64,
+        limit: i64,
+    ) -> anyhow::Result<Vec<LogEntry>> {
+        self.timed("get_recent_logs", async {
+            Ok(sqlx::query_as!(
+                LogEntry,
+                r#"
+                SELECT l.id, l.timestamp, l.category, l.note
+                FROM logs l
+                JOIN users u on u.id = l.user_id
+                WHERE l.user_id = $1 AND l.timestamp >= COALESCE(u.tracking_start, l.timestamp)
+                ORDER BY l.timestamp DESC, l.id DESC
+                LIMIT $2 OFFSET $3;
+                "#,
+                user_id,
+                limit,
+                offset,
+            )
+            .fetch_all(&self.pool)
+            .await?)
+        })
+        .await
+    }
+
+    /// Returns the user's number of logs in each month of `year` by their local days, January
+    /// first, optionally only in one `category`.
+    pub async fn get_monthly_counts(
+        &self,
+        user_id: i64,
+        year: i32,
+        category: Option<&str>,
+    ) -> anyhow::Result<[i64; 12]> {
+        // Counted per day and summed up here, as SQLite and Postgres format dates differently
+        let epoch = DateTime::UNIX_EPOCH.date_naive();
+        let day_number =
+            |year| NaiveDate::from_ymd_opt(year, 1, 1).map_or(0, |date| (date - epoch).num_days());
+        let (from, to) = (day_number(year), day_number(year + 1));
+        self.timed("get_monthly_counts", async {
+            let rows = sqlx::query!(
+                r#"
+                SELECT (l.timestamp + (u.utc_offset - u.day_end) * 60) / 86400 as "day!: i64", COUNT(*) as "logs!: i64"
+                FROM logs l
+                JOIN users u on u.id = l.user_id
+                WHERE l.user_id = $1 AND l.timestamp >= COALESCE(u.tracking_start, l.timestamp)
+                    AND (l.timestamp + (u.utc_offset - u.day_end) * 60) / 86400 >= $2
+                    AND (l.timestamp + (u.utc_offset - u.day_end) * 60) / 86400 < $3
+                    AND (l.category = $4 OR $4 IS NULL)
+                GROUP BY 1;
+                "#,
+                user_id,
+                from,
+                to,
+                category,
+            )
+            .fetch_all(&self.pool)
+            .await?;
+            let mut counts = [0; 12];
+            for r in rows {
+                let date = epoch + chrono::Days::new(r.day as u64);
+                counts[date.month0() as usize] += r.logs;
+            }
+            Ok(counts)
+        })
+        .await
+    }
+
+    /// Returns the user's number of logs in each hour of their local time, midnight first,
+    /// optionally only in one `category`.
+    pub async fn get_hourly_counts(
+        &self,
+        user_id: i64,
+        category: Option<&str>,
+    ) -> anyhow::Result<[i64; 24]> {
+        self.timed("get_hourly_counts", async {
+            let rows = sqlx::query!(
+                r#"
+                SELECT ((l.timestamp + u.utc_offset * 60) % 86400 + 86400) % 86400 / 3600 as "hour!: i64", COUNT(*) as "logs!: i64"
+                FROM logs l
+                JOIN users u on u.id = l.user_id
+                WHERE l.user_id = $1 AND l.timestamp >= COALESCE(u.tracking_start, l.timestamp)
+                    AND (l.category = $2 OR $2 IS NULL)
+                GROUP BY 1;
+                "#,
+                user_id,
+                category,
+            )
+            .fetch_all(&self.pool)
+            .await?;
+            let mut counts = [0; 24];
+            for r in rows {
+                counts[r.hour as usize] = r.logs;
+            }
+            Ok(counts)
+        })
+        .await
+    }
+
+    /// Returns the user's number of logs on each day of the week of their local days,
+    /// Monday first.
+    pub async fn get_weekday_counts(&self, user_id: i64) -> anyhow::Result<[i64; 7]> {
+        self.timed("get_weekday_counts", async {
+            // 1970-01-01, day 0, was a Thursday
+            let rows = sqlx::query!(
+                r#"
+                SELECT (((l.timestamp + (u.utc_offset - u.day_end) * 60) / 86400 + 3) % 7 + 7) % 7 as "weekday!: i64", COUNT(*) as "logs!: i64"
+                FROM logs l
+                JOIN users u on u.id = l.user_id
+                WHERE l.user_id = $1 AND l.timestamp >= COALESCE(u.tracking_start, l.timestamp)
+                GROUP BY 1;
+                "#,
+                user_id,
+            )
+            .fetch_all(&self.pool)
+            .await?;
+            let mut counts = [0; 7];
+            for r in rows {
+                counts[r.weekday as usize] = r.logs;
+            }
+            Ok(counts)
+        })
+        .await
+    }
+
+    /// Returns the top 10 users, counting at most `daily_cap` logs per user per (local) day,
+    /// optionally only in one `category` and only logs in `[since, until)`.
+    /// With a `chat`, only users who have logged in that chat are included.
+    /// Users hidden from rankings are left out.
+    /// The `caller` is included even outside the top 10, so they can see their position.
+    pub async fn get_leaderboard(
+        &self,
+        daily_cap: Option<i64>,
+        category: Option<&str>,
+        since: Option<i64>,
+        until: Option<i64>,
+        chat: Option<i64>,
+        caller: Option<i64>,
+    ) -> anyhow::Result<Vec<RankedTotal>> {
+        self.timed("get_leaderboard", async {
+            Ok(sqlx::query_as!(
+                RankedTotal,
+                r#"
+                WITH totals AS (
+                    SELECT daily.id, daily.telegram_id, daily.username, daily.first_name, daily.last_name,
+                        CAST(SUM(CASE WHEN daily.n > $1 THEN $1 ELSE daily.n END) AS BIGINT) as logs
+                    FROM (
+                        SELECT u.id, u.telegram_id, u.username, u.first_name, u.last_name, COUNT(l.id) as n
+                        FROM users u
+                        JOIN logs l on l.user_id = u.id
+                        WHERE l.timestamp >= COALESCE(u.tracking_start, l.timestamp)
+                            AND u.hide_from_rankings = FALSE
+                            AND (l.category = $2 OR $2 IS NULL)
+                            AND l.timestamp >= COALESCE($3, l.timestamp)
+                            AND l.timestamp < COALESCE($4, l.timestamp + 1)
+                            AND (u.id IN (SELECT user_id FROM logs WHERE chat_id = $5) OR $5 IS NULL)
+                        GROUP BY u.id, (l.timestamp + (u.utc_offset - u.day_end) * 60) / 86400
+                    ) daily
+                    GROUP BY daily.id, daily.telegram_id, daily.username, daily.first_name, daily.last_name
+                ), ranked AS (
+                    SELECT *, ROW_NUMBER() OVER (ORDER BY logs DESC) as position FROM totals
+                )
+                SELECT id as "user_id!", telegram_id as "telegram_id!", username, first_name, last_name,
+                    logs as "logs!: i64", position as "position!: i64"
+                FROM ranked
+                WHERE position <= 10 OR id = $6
+                ORDER BY position;
+                "#,
+                daily_cap,
+                category,
+                since,
+                until,
+                chat,
+                caller,
+            )
+            .fetch_all(&self.pool)
+            .await?)
+        })
+        .await
+    }
+
+    /// Returns the user's position on the leaderboard and the number of users on it,
+    /// counted like `get_leaderboard`. `None` if the user isn't on it.
+    pub async fn get_leaderboard_rank(
+        &self,
+        user_id: i64,
+        daily_cap: Option<i64>,
+        since: Option<i64>,
+        chat: Option<i64>,
+    ) -> anyhow::Result<Option<(i64, i64)>> {
+        self.timed("get_leaderboard_rank", async {
+            Ok(sqlx::query!(
+                r#"
+                WITH totals AS (
+                    SELECT daily.id, CAST(SUM(CASE WHEN daily.n > $1 THEN $1 ELSE daily.n END) AS BIGINT) as logs
+                    FROM (
+                        SELECT u.id, COUNT(l.id) as n
+                        FROM users u
+                        JOIN logs l on l.user_id = u.id
+                        WHERE l.timestamp >= COALESCE(u.tracking_start, l.timestamp)
+                            AND u.hide_from_rankings = FALSE
+                            AND l.timestamp >= COALESCE($2, l.timestamp)
+                            AND (u.id IN (SELECT user_id FROM logs WHERE chat_id = $3) OR $3 IS NULL)
+                        GROUP BY u.id, (l.timestamp + (u.utc_offset - u.day_end) * 60) / 86400
+                    ) daily
+                    GROUP BY daily.id
+                )
+                SELECT
+                    (SELECT COUNT(*) FROM totals WHERE logs > t.logs) + 1 as "rank!: i64",
+                    (SELECT COUNT(*) FROM totals) as "total!: i64"
+                FROM totals t
+                WHERE t.id = $4;
+                "#,
+                daily_cap,
+                since,
+                chat,
+                user_id,
+            )
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|r| (r.rank, r.total)))
+        })
+        .await
     }
 
+    /// Returns the user's position among all users by number of logs,
+    /// sharing the position on ties. Other users hidden from rankings are skipped.
+    pub async fn get_user_rank(&self, user_id: i64) -> anyhow::Result<i64> {
+        self.timed("get_user_rank", async {
+            Ok(sqlx::query_scalar!(
+                r#"
+                WITH totals AS (
+                    SELECT u.id, COUNT(l.id) as n
+                    FROM users u
+                    JOIN logs l on l.user_id = u.id
+                    WHERE l.timestamp >= COALESCE(u.tracking_start, l.timestamp)
+                        AND (u.hide_from_rankings = FALSE OR u.id = $1)
+                    GROUP BY u.id
+                )
+                SELECT COUNT(*) + 1 as "rank!: i64"
+                FROM totals
+                WHERE n > (SELECT n FROM totals WHERE id = $1);
+                "#,
+                user_id,
+            )
+            .fetch_one(&self.pool)
+            .await?)
+        })
+        .await
+    }
+
+    /// Returns the number of logs of every user with any, smallest first.
+    pub async fn get_user_log_counts(&self) -> anyhow::Result<Vec<i64>> {
+        self.timed("get_user_log_counts", async {
+            Ok(sqlx::query_scalar!(
+                r#"
+                SELECT COUNT(l.id) as "n!: i64"
+                FROM users u
+                JOIN logs l on l.user_id = u.id
+                WHERE l.timestamp >= COALESCE(u.tracking_start, l.timestamp)
+                GROUP BY u.id
+                ORDER BY 1;
+                "#,
+            )
+            .fetch_all(&self.pool)
+            .await?)
+        })
+        .await
+    }
+
+    /// Returns all categories with their number of logs, most used first.
+    pub async fn get_categories(&self) -> anyhow::Result<Vec<(String, i64)>> {
+        self.timed("get_categories", async {
+            Ok(sqlx::query!(
+                r#"
+                SELECT category as "category!", COUNT(*) as "logs!"
+                FROM logs
+                WHERE category IS NOT NULL
+                GROUP BY category
+                ORDER BY 2 DESC;
+                "#,
+            )
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|r| (r.category, r.logs))
+            .collect())
+        })
+        .await
+    }
+
+    /// Returns the categories of the user's logs with their number, most used first.
+    pub async fn get_user_categories(&self, user_id: i64) -> anyhow::Result<Vec<(String, i64)>> {
+        self.timed("get_user_categories", async {
+            Ok(sqlx::query!(
+                r#"
+                SELECT l.category as "category!", COUNT(*) as "logs!"
+                FROM logs l
+                JOIN users u on u.id = l.user_id
+                WHERE l.user_id = $1 AND l.category IS NOT NULL
+                    AND l.timestamp >= COALESCE(u.tracking_start, l.timestamp)
+                GROUP BY l.category
+                ORDER BY 2 DESC;
+                "#,
+                user_id,
+            )
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|r| (r.category, r.logs))
+            .collect())
+        })
+        .await
+    }
+
+    /// Records that the user was told they reached `level`.
+    /// Returns `false` if they were already told about it or a higher level.
+    pub async fn mark_level_announced(&self, user_id: i64, level: i64) -> anyhow::Result<bool> {
+        self.timed("mark_level_announced", async {
+            let result = sqlx::query!(
+                "UPDATE users SET announced_level = $1 WHERE id = $2 AND announced_level < $3;",
+                level,
+                user_id,
+                level,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(result.rows_affected() > 0)
+        })
+        .await
+    }
+
+    /// Records that the user earned the achievement at `earned_at`.
+    /// Returns `false` if they already had it.
+    pub async fn award_achievement(
+        &self,
+        user_id: i64,
+        achievement: &str,
+        earned_at: i64,
+    ) -> anyhow::Result<bool> {
+        self.timed("award_achievement", async {
+            let result = sqlx::query!(
+                r#"
+                INSERT INTO achievements (user_id, achievement, earned_at) VALUES ($1, $2, $3)
+                ON CONFLICT(user_id, achievement) DO NOTHING;
+                "#,
+                user_id,
+                achievement,
+                earned_at,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(result.rows_affected() > 0)
+        })
+        .await
+    }
+
+    /// Returns the achievements of all the given users as `(user_id, achievement)` pairs.
+    pub async fn get_users_achievements(
+        &self,
+        user_ids: &[i64],
+    ) -> anyhow::Result<Vec<(i64, String)>> {
+        self.timed("get_users_achievements", async {
+            // SQLite takes the list as a JSON array, Postgres as an array
+            #[cfg(not(feature = "postgres"))]
+            let ids = serde_json::to_string(user_ids)?;
+            #[cfg(not(feature = "postgres"))]
+            let rows = sqlx::query!(
+                r#"
+                SELECT user_id, achievement
+                FROM achievements
+                WHERE user_id IN (SELECT value FROM json_each($1));
+                "#,
+                ids,
+            )
+            .fetch_all(&self.pool)
+            .await?;
+            #[cfg(feature = "postgres")]
+            let rows = sqlx::query!(
+                "SELECT user_id, achievement FROM achievements WHERE user_id = ANY($1);",
+                user_ids,
+            )
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(rows
+                .into_iter()
+                .map(|r| (r.user_id, r.achievement))
+                .collect())
+        })
+        .await
+    }
+
+    /// Returns the user's achievements with the timestamps they were earned at.
+    pub async fn get_achievements(&self, user_id: i64) -> anyhow::Result<Vec<(String, i64)>> {
+        self.timed("get_achievements", async {
+            Ok(sqlx::query!(
+                "SELECT achievement, earned_at FROM achievements WHERE user_id = $1;",
+                user_id,
+            )
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|r| (r.achievement, r.earned_at))
+            .collect())
+        })
+        .await
+    }
+
+    /// Returns the number of users and logs in the whole database.
+    pub async fn get_global_counts(&self) -> anyhow::Result<(i64, i64)> {
+        self.timed("get_global_counts", async {
+            let row = sqlx::query!(
+                r#"
+                SELECT
+                    (SELECT COUNT(*) FROM users) as "users!: i64",
+                    (SELECT COUNT(*) FROM logs) as "logs!: i64";
+                "#,
+            )
+            .fetch_one(&self.pool)
+            .await?;
+            Ok((row.users, row.logs))
+        })
+        .await
+    }
+
+    /// Returns every user's days with logs as `(user_id, utc_offset, local day number)`,
+    /// where day 0 is 1970-01-01. With a `chat`, only users who have logged in that chat are included.
+    /// Users hidden from rankings are left out.
+    pub async fn get_all_active_days(
+        &self,
+        chat: Option<i64>,
+    ) -> anyhow::Result<Vec<(i64, i64, i64)>> {
+        self.timed("get_all_active_days", async {
+            Ok(sqlx::query!(
+                r#"
+                SELECT DISTINCT
+                    l.user_id,
+                    u.utc_offset,
+                    (l.timestamp + (u.utc_offset - u.day_end) * 60) / 86400 as "day!: i64"
+                FROM logs l
+                JOIN users u on u.id = l.user_id
+                WHERE l.timestamp >= COALESCE(u.tracking_start, l.timestamp)
+                    AND u.hide_from_rankings = FALSE
+                    AND (u.id IN (SELECT user_id FROM logs WHERE chat_id = $1) OR $1 IS NULL);
+                "#,
+                chat,
+            )
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|r| (r.user_id, r.utc_offset, r.day))
+            .collect())
+        })
+        .await
+    }
+
+    /// Returns the Telegram IDs and cached names of the given users.
+    pub async fn get_user_names(&self, user_ids: &[i64]) -> anyhow::Result<Vec<UserName>> {
+        self.timed("get_user_names", async {
+            #[cfg(not(feature = "postgres"))]
+            let ids = serde_json::to_string(user_ids)?;
+            #[cfg(not(feature = "postgres"))]
+            let names = sqlx::query_as!(
+                UserName,
+                r#"
+                SELECT id as "id!", telegram_id, username, first_name, last_name
+                FROM users
+                WHERE id IN (SELECT value FROM json_each($1));
+                "#,
+                ids,
+            )
+            .fetch_all(&self.pool)
+            .await?;
+            #[cfg(feature = "postgres")]
+            let names = sqlx::query_as!(
+                UserName,
+                r#"
+                SELECT id as "id!", telegram_id, username, first_name, last_name
+                FROM users
+                WHERE id = ANY($1);
+                "#,
+                user_ids,
+            )
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(names)
+        })
+        .await
+    }
+
+    /// Returns the total score of up to `limit` users with the most logs,
+    /// with their cached names. Users hidden from rankings are left out.
+    /// With a `chat`, only users who have logged in that chat are included.
+    pub async fn get_user_totals(
+        &self,
+        limit: i64,
+        chat: Option<i64>,
+    ) -> anyhow::Result<Vec<UserTotal>> {
+        self.timed("get_user_totals", async {
+            Ok(sqlx::query_as!(
+                UserTotal,
+                r#"
+                SELECT u.telegram_id, u.username, u.first_name, u.last_name, COUNT(l.id) as "logs!"
+                FROM users u
+                JOIN logs l on l.user_id = u.id
+                WHERE l.timestamp >= COALESCE(u.tracking_start, l.timestamp)
+                    AND u.hide_from_rankings = FALSE
+                    AND (u.id IN (SELECT user_id FROM logs WHERE chat_id = $2) OR $2 IS NULL)
+                GROUP BY u.id
+                ORDER BY 5 DESC
+                LIMIT $1;
+                "#,
+                limit,
+                chat,
+            )
+            .fetch_all(&self.pool)
+            .await?)
+        })
+        .await
+    }
+
+    /// Deletes the user with everything that refers to them, all or nothing.
     pub async fn delete_user_data(&self, user_id: i64) -> anyhow::Result<()> {
-        sqlx::query!(
-            r#"
-            DELETE FROM logs WHERE user_id = ?;
-            "#,
-            user_id,
-        )
-        .execute(&self.pool)
-        .await?;
-        sqlx::query!(
-            r#"
-            DELETE FROM users WHERE id = ?;
-            "#,
-            user_id,
-        )
-        .execute(&self.pool)
-        .await?;
-        Ok(())
+        self.timed("delete_user_data", async {
+            let mut tx = self.pool.begin().await?;
+            sqlx::query!(
+                r#"
+                DELETE FROM logs WHERE user_id = $1;
+                "#,
+                user_id,
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query!(
+                r#"
+                DELETE FROM watchers WHERE watcher_id = $1 OR watched_id = $1;
+                "#,
+                user_id,
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query!(
+                r#"
+                DELETE FROM digest_subscriptions WHERE user_id = $1;
+                "#,
+                user_id,
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query!(
+                r#"
+                DELETE FROM reminders WHERE user_id = $1;
+                "#,
+                user_id,
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query!(
+                r#"
+                DELETE FROM achievements WHERE user_id = $1;
+                "#,
+                user_id,
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query!(
+                r#"
+                UPDATE logs SET granted_by = NULL WHERE granted_by = $1;
+                "#,
+                user_id,
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query!(
+                r#"
+                DELETE FROM users WHERE id = $1;
+                "#,
+                user_id,
+            )
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn get_user_settings(&self, user_id: i64) -> anyhow::Result<UserSettings> {
+        self.timed("get_user_settings", async {
+            Ok(sqlx::query_as!(
+                UserSettings,
+                r#"
+                SELECT u.utc_offset, u.weekly_goal, u.monthly_goal, u.tracking_start, u.chart_theme,
+                    u.hide_from_rankings as "hide_from_rankings: bool",
+                    u.quiet as "quiet: bool",
+                    r.minute as "reminder_minute?",
+                    u.language,
+                    u.day_end,
+                    u.ack_messages,
+                    u.plain_logging as "plain_logging: bool"
+                FROM users u
+                LEFT JOIN reminders r on r.user_id = u.id
+                WHERE u.id = $1;
+                "#,
+                user_id,
+            )
+            .fetch_one(&self.pool)
+            .await?)
+        })
+        .await
+    }
+
+    pub async fn set_chart_theme(&self, user_id: i64, theme: Option<&str>) -> anyhow::Result<()> {
+        self.timed("set_chart_theme", async {
+            sqlx::query!(
+                "UPDATE users SET chart_theme = $1 WHERE id = $2;",
+                theme,
+                user_id,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn set_monthly_goal(&self, user_id: i64, goal: Option<i64>) -> anyhow::Result<()> {
+        self.timed("set_monthly_goal", async {
+            sqlx::query!(
+                "UPDATE users SET monthly_goal = $1, monthly_goal_reached = NULL WHERE id = $2;",
+                goal,
+                user_id,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Records that the user reached their weekly goal in the week starting at `week_start`.
+    /// Returns `false` if it was already recorded for that week.
+    pub async fn mark_weekly_goal_reached(
+        &self,
+        user_id: i64,
+        week_start: i64,
+    ) -> anyhow::Result<bool> {
+        self.timed("mark_weekly_goal_reached", async {
+            let result = sqlx::query!(
+                r#"
+                UPDATE users SET weekly_goal_reached = $1
+                WHERE id = $2 AND (weekly_goal_reached IS NULL OR weekly_goal_reached < $3);
+                "#,
+                week_start,
+                user_id,
+                week_start,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(result.rows_affected() > 0)
+        })
+        .await
+    }
+
+    /// Records that the user reached their monthly goal in the month starting at `month_start`.
+    /// Returns `false` if it was already recorded for that month.
+    pub async fn mark_monthly_goal_reached(
+        &self,
+        user_id: i64,
+        month_start: i64,
+    ) -> anyhow::Result<bool> {
+        self.timed("mark_monthly_goal_reached", async {
+            let result = sqlx::query!(
+                r#"
+                UPDATE users SET monthly_goal_reached = $1
+                WHERE id = $2 AND (monthly_goal_reached IS NULL OR monthly_goal_reached < $3);
+                "#,
+                month_start,
+                user_id,
+                month_start,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(result.rows_affected() > 0)
+        })
+        .await
+    }
+
+    pub async fn set_weekly_goal(&self, user_id: i64, goal: Option<i64>) -> anyhow::Result<()> {
+        self.timed("set_weekly_goal", async {
+            sqlx::query!(
+                "UPDATE users SET weekly_goal = $1, weekly_goal_reached = NULL WHERE id = $2;",
+                goal,
+                user_id,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Logs before `tracking_start` are not deleted, only excluded from the stats.
+    pub async fn set_tracking_start(
+        &self,
+        user_id: i64,
+        tracking_start: Option<i64>,
+    ) -> anyhow::Result<()> {
+        self.timed("set_tracking_start", async {
+            sqlx::query!(
+                "UPDATE users SET tracking_start = $1 WHERE id = $2;",
+                tracking_start,
+                user_id,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn set_utc_offset(&self, user_id: i64, utc_offset: i64) -> anyhow::Result<()> {
+        self.timed("set_utc_offset", async {
+            sqlx::query!(
+                "UPDATE users SET utc_offset = $1 WHERE id = $2;",
+                utc_offset,
+                user_id,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn get_streak_warning_candidates(
+        &self,
+    ) -> anyhow::Result<Vec<StreakWarningCandidate>> {
+        self.timed("get_streak_warning_candidates", async {
+            Ok(sqlx::query_as!(
+                StreakWarningCandidate,
+                r#"
+                SELECT DISTINCT u.id as user_id, u.telegram_id, u.utc_offset, u.day_end,
+                    u.language, u.streak_warned_on
+                FROM users u
+                JOIN logs l on l.user_id = u.id;
+                "#,
+            )
+            .fetch_all(&self.pool)
+            .await?)
+        })
+        .await
+    }
+
+    pub async fn set_streak_warned_on(&self, user_id: i64, date: &str) -> anyhow::Result<()> {
+        self.timed("set_streak_warned_on", async {
+            sqlx::query!(
+                "UPDATE users SET streak_warned_on = $1 WHERE id = $2;",
+                date,
+                user_id,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Subscribes the user to the weekly digest, the first one is sent after `week` ends.
+    pub async fn subscribe_digest(&self, user_id: i64, week: &str) -> anyhow::Result<()> {
+        self.timed("subscribe_digest", async {
+            sqlx::query!(
+                r#"
+                INSERT INTO digest_subscriptions (user_id, sent_for_week) VALUES ($1, $2)
+                ON CONFLICT(user_id) DO NOTHING;
+                "#,
+                user_id,
+                week,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn unsubscribe_digest(&self, user_id: i64) -> anyhow::Result<()> {
+        self.timed("unsubscribe_digest", async {
+            sqlx::query!(
+                "DELETE FROM digest_subscriptions WHERE user_id = $1;",
+                user_id,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn get_digest_subscribers(&self) -> anyhow::Result<Vec<DigestSubscriber>> {
+        self.timed("get_digest_subscribers", async {
+            Ok(sqlx::query_as!(
+                DigestSubscriber,
+                r#"
+                SELECT u.id as user_id, u.telegram_id, u.utc_offset, u.day_end, u.chart_theme,
+                    u.language, d.sent_for_week
+                FROM digest_subscriptions d
+                JOIN users u on u.id = d.user_id;
+                "#,
+            )
+            .fetch_all(&self.pool)
+            .await?)
+        })
+        .await
+    }
+
+    pub async fn set_digest_sent_for_week(&self, user_id: i64, week: &str) -> anyhow::Result<()> {
+        self.timed("set_digest_sent_for_week", async {
+            sqlx::query!(
+                "UPDATE digest_subscriptions SET sent_for_week = $1 WHERE user_id = $2;",
+                week,
+                user_id,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Sets the user's daily reminder to `minute` after local midnight, `None` to remove it.
+    pub async fn set_reminder(&self, user_id: i64, minute: Option<i64>) -> anyhow::Result<()> {
+        self.timed("set_reminder", async {
+            match minute {
+                Some(minute) => {
+                    sqlx::query!(
+                        r#"
+                        INSERT INTO reminders (user_id, minute) VALUES ($1, $2)
+                        ON CONFLICT(user_id) DO UPDATE SET minute = excluded.minute, reminded_on = NULL;
+                        "#,
+                        user_id,
+                        minute,
+                    )
+                    .execute(&self.pool)
+                    .await?;
+                }
+                None => {
+                    sqlx::query!("DELETE FROM reminders WHERE user_id = $1;", user_id)
+                        .execute(&self.pool)
+                        .await?;
+                }
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn get_reminders(&self) -> anyhow::Result<Vec<Reminder>> {
+        self.timed("get_reminders", async {
+            Ok(sqlx::query_as!(
+                Reminder,
+                r#"
+                SELECT u.id as user_id, u.telegram_id, u.utc_offset, u.day_end, r.minute,
+                    u.language, r.reminded_on
+                FROM reminders r
+                JOIN users u on u.id = r.user_id;
+                "#,
+            )
+            .fetch_all(&self.pool)
+            .await?)
+        })
+        .await
+    }
+
+    pub async fn set_reminded_on(&self, user_id: i64, date: &str) -> anyhow::Result<()> {
+        self.timed("set_reminded_on", async {
+            sqlx::query!(
+                "UPDATE reminders SET reminded_on = $1 WHERE user_id = $2;",
+                date,
+                user_id,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn get_chat_settings(&self, chat_id: i64) -> anyhow::Result<ChatSettings> {
+        self.timed("get_chat_settings", async {
+            Ok(sqlx::query_as!(
+                ChatSettings,
+                r#"
+                SELECT
+                    anonymous_leaderboard as "anonymous_leaderboard: bool",
+                    habit_mode as "habit_mode: bool",
+                    daily_cap,
+                    team_goal,
+                    season_start,
+                    acknowledgement
+                FROM chat_settings
+                WHERE chat_id = $1;
+                "#,
+                chat_id,
+            )
+            .fetch_optional(&self.pool)
+            .await?
+            .unwrap_or_default())
+        })
+        .await
+    }
+
+    pub async fn set_anonymous_leaderboard(
+        &self,
+        chat_id: i64,
+        enabled: bool,
+    ) -> anyhow::Result<()> {
+        self.timed("set_anonymous_leaderboard", async {
+            sqlx::query!(
+                r#"
+                INSERT INTO chat_settings (chat_id, anonymous_leaderboard) VALUES ($1, $2)
+                ON CONFLICT(chat_id) DO UPDATE SET anonymous_leaderboard = excluded.anonymous_leaderboard;
+                "#,
+                chat_id,
+                enabled,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn find_user_by_telegram_id(&self, tg_id: i64) -> anyhow::Result<Option<i64>> {
+        self.timed("find_user_by_telegram_id", async {
+            Ok(sqlx::query_scalar!(
+                r#"SELECT id as "id!" FROM users WHERE telegram_id = $1;"#,
+                tg_id
+            )
+            .fetch_optional(&self.pool)
+            .await?)
+        })
+        .await
+    }
+
+    /// Whether the user with this Telegram ID logs with plain messages, `false` for unknown users.
+    pub async fn has_plain_logging(&self, tg_id: i64) -> anyhow::Result<bool> {
+        self.timed("has_plain_logging", async {
+            Ok(sqlx::query_scalar!(
+                r#"SELECT plain_logging as "plain_logging: bool" FROM users WHERE telegram_id = $1;"#,
+                tg_id
+            )
+            .fetch_optional(&self.pool)
+            .await?
+            .unwrap_or(false))
+        })
+        .await
+    }
+
+    pub async fn get_telegram_id(&self, user_id: i64) -> anyhow::Result<Option<i64>> {
+        self.timed("get_telegram_id", async {
+            Ok(
+                sqlx::query_scalar!("SELECT telegram_id FROM users WHERE id = $1;", user_id)
+                    .fetch_optional(&self.pool)
+                    .await?,
+            )
+        })
+        .await
+    }
+
+    pub async fn find_user_by_username(&self, username: &str) -> anyhow::Result<Option<i64>> {
+        self.timed("find_user_by_username", async {
+            Ok(sqlx::query_scalar!(
+                r#"SELECT id as "id!" FROM users WHERE LOWER(username) = LOWER($1);"#,
+                username,
+            )
+            .fetch_optional(&self.pool)
+            .await?)
+        })
+        .await
+    }
+
+    pub async fn set_share_settings(&self, user_id: i64, enabled: bool) -> anyhow::Result<()> {
+        self.timed("set_share_settings", async {
+            sqlx::query!(
+                "UPDATE users SET share_settings = $1 WHERE id = $2;",
+                enabled,
+                user_id,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn set_hide_from_rankings(&self, user_id: i64, hidden: bool) -> anyhow::Result<()> {
+        self.timed("set_hide_from_rankings", async {
+            sqlx::query!(
+                "UPDATE users SET hide_from_rankings = $1 WHERE id = $2;",
+                hidden,
+                user_id,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn set_language(&self, user_id: i64, language: Option<&str>) -> anyhow::Result<()> {
+        self.timed("set_language", async {
+            sqlx::query!(
+                "UPDATE users SET language = $1 WHERE id = $2;",
+                language,
+                user_id,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Remembers the Telegram file ID of the user's latest `/card`.
+    pub async fn set_card_file_id(&self, user_id: i64, file_id: &str) -> anyhow::Result<()> {
+        self.timed("set_card_file_id", async {
+            sqlx::query!(
+                "UPDATE users SET card_file_id = $1 WHERE id = $2;",
+                file_id,
+                user_id,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn get_card_file_id(&self, user_id: i64) -> anyhow::Result<Option<String>> {
+        self.timed("get_card_file_id", async {
+            Ok(
+                sqlx::query_scalar!("SELECT card_file_id FROM users WHERE id = $1;", user_id)
+                    .fetch_one(&self.pool)
+                    .await?,
+            )
+        })
+        .await
+    }
+
+    pub async fn set_plain_logging(&self, user_id: i64, enabled: bool) -> anyhow::Result<()> {
+        self.timed("set_plain_logging", async {
+            sqlx::query!(
+                "UPDATE users SET plain_logging = $1 WHERE id = $2;",
+                enabled,
+                user_id,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn set_quiet(&self, user_id: i64, quiet: bool) -> anyhow::Result<()> {
+        self.timed("set_quiet", async {
+            sqlx::query!("UPDATE users SET quiet = $1 WHERE id = $2;", quiet, user_id)
+                .execute(&self.pool)
+                .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn set_ack_messages(
+        &self,
+        user_id: i64,
+        messages: Option<&str>,
+    ) -> anyhow::Result<()> {
+        self.timed("set_ack_messages", async {
+            sqlx::query!(
+                "UPDATE users SET ack_messages = $1 WHERE id = $2;",
+                messages,
+                user_id,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn set_day_end(&self, user_id: i64, day_end: i64) -> anyhow::Result<()> {
+        self.timed("set_day_end", async {
+            sqlx::query!(
+                "UPDATE users SET day_end = $1 WHERE id = $2;",
+                day_end,
+                user_id,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn set_allow_compare(&self, user_id: i64, enabled: bool) -> anyhow::Result<()> {
+        self.timed("set_allow_compare", async {
+            sqlx::query!(
+                "UPDATE users SET allow_compare = $1 WHERE id = $2;",
+                enabled,
+                user_id,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Returns whether the user has opted into others comparing their charts with theirs
+    /// and isn't hidden from rankings.
+    pub async fn allows_compare(&self, user_id: i64) -> anyhow::Result<bool> {
+        self.timed("allows_compare", async {
+            Ok(sqlx::query_scalar!(
+                r#"
+                SELECT allow_compare AND NOT hide_from_rankings as "allow_compare!: bool"
+                FROM users WHERE id = $1;
+                "#,
+                user_id,
+            )
+            .fetch_optional(&self.pool)
+            .await?
+            .unwrap_or(false))
+        })
+        .await
+    }
+
+    /// Copies the shareable preferences of `from_user_id` to `to_user_id`.
+    /// Returns `None` without changing anything if the source user hasn't opted into sharing.
+    pub async fn copy_shared_settings(
+        &self,
+        from_user_id: i64,
+        to_user_id: i64,
+    ) -> anyhow::Result<Option<SharedSettings>> {
+        self.timed("copy_shared_settings", async {
+            let mut tx = self.pool.begin().await?;
+            let settings = sqlx::query_as!(
+                SharedSettings,
+                r#"
+                SELECT utc_offset, day_end, weekly_goal, monthly_goal, chart_theme
+                FROM users WHERE id = $1 AND share_settings = TRUE;
+                "#,
+                from_user_id,
+            )
+            .fetch_optional(&mut *tx)
+            .await?;
+            if let Some(settings) = &settings {
+                sqlx::query!(
+                    r#"
+                    UPDATE users SET utc_offset = $1, day_end = $2, weekly_goal = $3,
+                        weekly_goal_reached = NULL, monthly_goal = $4, monthly_goal_reached = NULL,
+                        chart_theme = $5
+                    WHERE id = $6;
+                    "#,
+                    settings.utc_offset,
+                    settings.day_end,
+                    settings.weekly_goal,
+                    settings.monthly_goal,
+                    settings.chart_theme,
+                    to_user_id,
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+            tx.commit().await?;
+            Ok(settings)
+        })
+        .await
+    }
+
+    pub async fn set_habit_mode(&self, chat_id: i64, enabled: bool) -> anyhow::Result<()> {
+        self.timed("set_habit_mode", async {
+            sqlx::query!(
+                r#"
+                INSERT INTO chat_settings (chat_id, habit_mode) VALUES ($1, $2)
+                ON CONFLICT(chat_id) DO UPDATE SET habit_mode = excluded.habit_mode;
+                "#,
+                chat_id,
+                enabled,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn set_acknowledgement(
+        &self,
+        chat_id: i64,
+        acknowledgement: Option<&str>,
+    ) -> anyhow::Result<()> {
+        self.timed("set_acknowledgement", async {
+            sqlx::query!(
+                r#"
+                INSERT INTO chat_settings (chat_id, acknowledgement) VALUES ($1, $2)
+                ON CONFLICT(chat_id) DO UPDATE SET acknowledgement = excluded.acknowledgement;
+                "#,
+                chat_id,
+                acknowledgement,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn set_daily_cap(&self, chat_id: i64, cap: Option<i64>) -> anyhow::Result<()> {
+        self.timed("set_daily_cap", async {
+            sqlx::query!(
+                r#"
+                INSERT INTO chat_settings (chat_id, daily_cap) VALUES ($1, $2)
+                ON CONFLICT(chat_id) DO UPDATE SET daily_cap = excluded.daily_cap;
+                "#,
+                chat_id,
+                cap,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Clears the per-day data stored in the user's local dates, which goes stale when their
+    /// timezone changes. Scores, daily caps and streaks are computed on the fly and need no
+    /// rebuilding.
+    pub async fn reset_local_day_markers(&self, user_id: i64) -> anyhow::Result<()> {
+        self.timed("reset_local_day_markers", async {
+            sqlx::query!(
+                "UPDATE users SET streak_warned_on = NULL WHERE id = $1;",
+                user_id,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn set_team_goal(&self, chat_id: i64, goal: Option<i64>) -> anyhow::Result<()> {
+        self.timed("set_team_goal", async {
+            sqlx::query!(
+                r#"
+                INSERT INTO chat_settings (chat_id, team_goal) VALUES ($1, $2)
+                ON CONFLICT(chat_id) DO UPDATE SET team_goal = excluded.team_goal;
+                "#,
+                chat_id,
+                goal,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn set_season_start(&self, chat_id: i64, ts: i64) -> anyhow::Result<()> {
+        self.timed("set_season_start", async {
+            sqlx::query!(
+                r#"
+                INSERT INTO chat_settings (chat_id, season_start) VALUES ($1, $2)
+                ON CONFLICT(chat_id) DO UPDATE SET season_start = excluded.season_start;
+                "#,
+                chat_id,
+                ts,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Returns how many logs were sent in the chat in `[from, to)`, per user, highest first.
+    pub async fn get_chat_contributions(
+        &self,
+        chat_id: i64,
+        from: i64,
+        to: i64,
+    ) -> anyhow::Result<Vec<UserTotal>> {
+        self.timed("get_chat_contributions", async {
+            Ok(sqlx::query_as!(
+                UserTotal,
+                r#"
+                SELECT u.telegram_id, u.username, u.first_name, u.last_name, COUNT(l.id) as "logs!: i64"
+                FROM users u
+                JOIN logs l on l.user_id = u.id
+                WHERE l.chat_id = $1 AND l.timestamp >= $2 AND l.timestamp < $3
+                GROUP BY u.id
+                ORDER BY 5 DESC;
+                "#,
+                chat_id,
+                from,
+                to,
+            )
+            .fetch_all(&self.pool)
+            .await?)
+        })
+        .await
+    }
+
+    /// Records a pending request to watch another user's milestones,
+    /// keeping an existing request or approval as is.
+    pub async fn request_watch(&self, watcher_id: i64, watched_id: i64) -> anyhow::Result<()> {
+        self.timed("request_watch", async {
+            sqlx::query!(
+                r#"
+                INSERT INTO watchers (watcher_id, watched_id) VALUES ($1, $2)
+                ON CONFLICT DO NOTHING;
+                "#,
+                watcher_id,
+                watched_id,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Approves a pending watch request, returning whether there was one.
+    pub async fn approve_watch(&self, watcher_id: i64, watched_id: i64) -> anyhow::Result<bool> {
+        self.timed("approve_watch", async {
+            let result = sqlx::query!(
+                "UPDATE watchers SET approved = TRUE WHERE watcher_id = $1 AND watched_id = $2;",
+                watcher_id,
+                watched_id,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(result.rows_affected() > 0)
+        })
+        .await
+    }
+
+    /// Removes a watch request or approval, returning whether there was one.
+    pub async fn delete_watch(&self, watcher_id: i64, watched_id: i64) -> anyhow::Result<bool> {
+        self.timed("delete_watch", async {
+            let result = sqlx::query!(
+                "DELETE FROM watchers WHERE watcher_id = $1 AND watched_id = $2;",
+                watcher_id,
+                watched_id,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(result.rows_affected() > 0)
+        })
+        .await
+    }
+
+    /// Returns the Telegram ID and chosen language of each approved watcher of the user.
+    pub async fn get_watchers_to_notify(
+        &self,
+        watched_id: i64,
+    ) -> anyhow::Result<Vec<(i64, Option<String>)>> {
+        self.timed("get_watchers_to_notify", async {
+            let rows = sqlx::query!(
+                r#"
+                SELECT u.telegram_id, u.language
+                FROM watchers w
+                JOIN users u on u.id = w.watcher_id
+                WHERE w.watched_id = $1 AND w.approved = TRUE;
+                "#,
+                watched_id,
+            )
+            .fetch_all(&self.pool)
+            .await?;
+            Ok(rows
+                .into_iter()
+                .map(|r| (r.telegram_id, r.language))
+                .collect())
+        })
+        .await
+    }
+
+    pub async fn insert_audit_entry(
+        &self,
+        actor_tg_id: i64,
+        action: &str,
+        target: Option<&str>,
+        ts: i64,
+    ) -> anyhow::Result<()> {
+        self.timed("insert_audit_entry", async {
+            sqlx::query!(
+                r#"
+                INSERT INTO audit_log (actor_telegram_id, action, target, timestamp)
+                VALUES ($1, $2, $3, $4);
+                "#,
+                actor_tg_id,
+                action,
+                target,
+                ts,
+            )
+            .execute(&self.pool)
+            .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Returns the newest audit log entries, newest first.
+    pub async fn get_audit_entries(&self, limit: i64) -> anyhow::Result<Vec<AuditEntry>> {
+        self.timed("get_audit_entries", async {
+            Ok(sqlx::query_as!(
+                AuditEntry,
+                r#"
+                SELECT actor_telegram_id, action, target, timestamp
+                FROM audit_log
+                ORDER BY id DESC
+                LIMIT $1;
+                "#,
+                limit,
+            )
+            .fetch_all(&self.pool)
+            .await?)
+        })
+        .await
+    }
+}
+
+/// Rounds a timestamp down, so a log never moves into the next hour or day.
+fn round_down(ts: i64, precision: i64) -> i64 {
+    ts - ts.rem_euclid(precision)
+}
+
+/// Connections are closed once idle or old and checked before being handed out,
+/// so that a connection dropped underneath the pool is replaced instead of failing a query.
+fn pool_options(idle_timeout: Duration, max_lifetime: Duration) -> PoolOptions<Db> {
+    PoolOptions::new()
+        .min_connections(0)
+        .idle_timeout(idle_timeout)
+        .max_lifetime(max_lifetime)
+        .test_before_acquire(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn test_database() -> Database {
+        Database::for_tests().await.unwrap()
+    }
+
+    async fn test_user(db: &Database, tg_id: i64) -> i64 {
+        db.get_user_id(tg_id, None, "Test", None).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn delete_user_data_removes_achievements() {
+        let db = test_database().await;
+        let user_id = test_user(&db, 1).await;
+        db.insert_log(user_id, 1, 1_000, None, None).await.unwrap();
+        db.award_achievement(user_id, "logs_10", 1_000)
+            .await
+            .unwrap();
+
+        db.delete_user_data(user_id).await.unwrap();
+
+        assert_eq!(db.find_user_by_telegram_id(1).await.unwrap(), None);
+        assert_eq!(
+            db.get_count_between(user_id, i64::MIN, i64::MAX)
+                .await
+                .unwrap(),
+            0
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_users_and_logs_are_all_counted() {
+        const USERS: i64 = 8;
+        const LOGS: i64 = 25;
+        let db = test_database().await;
+        let tasks: Vec<_> = (1..=USERS)
+            .map(|tg_id| {
+                let db = db.clone();
+                tokio::spawn(async move {
+                    for i in 0..LOGS {
+                        // Looked up again each time, like every incoming /done
+                        let user_id = db.get_user_id(tg_id, None, "Test", None).await.unwrap();
+                        db.insert_log(user_id, tg_id, i * 60, None, None)
+                            .await
+                            .unwrap();
+                        let count = db.get_user_stats(user_id, None, None).await.unwrap();
+                        assert!(count > i);
+                    }
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        assert_eq!(db.get_global_counts().await.unwrap(), (USERS, USERS * LOGS));
+        for tg_id in 1..=USERS {
+            let user_id = db.find_user_by_telegram_id(tg_id).await.unwrap().unwrap();
+            assert_eq!(db.get_user_stats(user_id, None, None).await.unwrap(), LOGS);
+        }
+    }
+
+    async fn log_ids(db: &Database) -> Vec<i64> {
+        sqlx::query_scalar("SELECT id FROM logs ORDER BY id;")
+            .fetch_all(&db.pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn delete_duplicate_logs_keeps_the_first_of_exact_duplicates() {
+        let db = test_database().await;
+        let user_id = test_user(&db, 1).await;
+        for _ in 0..3 {
+            db.insert_log(user_id, 1, 100, None, None).await.unwrap();
+        }
+        // Same timestamp, but another chat or category
+        db.insert_log(user_id, 2, 100, None, None).await.unwrap();
+        db.insert_log(user_id, 1, 100, Some("gym"), None)
+            .await
+            .unwrap();
+        db.insert_log(user_id, 1, 100, Some("gym"), None)
+            .await
+            .unwrap();
+        let ids = log_ids(&db).await;
+
+        assert_eq!(db.delete_duplicate_logs().await.unwrap(), 3);
+        assert_eq!(log_ids(&db).await, [ids[0], ids[3], ids[4]]);
+        assert_eq!(db.delete_duplicate_logs().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn daily_cap_limits_each_local_day() {
+        const DAY: i64 = 24 * 60 * 60;
+        let db = test_database().await;
+        let user_id = test_user(&db, 1).await;
+        db.set_utc_offset(user_id, 120).await.unwrap();
+        // 5 logs on the first local day, then 2 on the next one, which starts at 22:00 UTC
+        for ts in [0, 60, 120, 180, 21 * 3600] {
+            db.insert_log(user_id, 1, ts, None, None).await.unwrap();
+        }
+        for ts in [22 * 3600 + 1800, DAY + 3600] {
+            db.insert_log(user_id, 1, ts, None, None).await.unwrap();
+        }
+
+        assert_eq!(db.get_user_stats(user_id, None, None).await.unwrap(), 7);
+        assert_eq!(db.get_user_stats(user_id, Some(3), None).await.unwrap(), 5);
+        assert_eq!(db.get_user_stats(user_id, Some(10), None).await.unwrap(), 7);
+    }
+
+    #[test]
+    fn round_down_to_precision() {
+        // Exact multiples stay
+        assert_eq!(round_down(7200, 3600), 7200);
+        assert_eq!(round_down(120, 60), 120);
+        // Everything inside a bucket goes to its start
+        assert_eq!(round_down(7201, 3600), 7200);
+        assert_eq!(round_down(10_799, 3600), 7200);
+        assert_eq!(round_down(179, 60), 120);
+        // Before 1970 too, never into the next bucket
+        assert_eq!(round_down(-1, 60), -60);
+        // Second precision keeps the timestamp
+        assert_eq!(round_down(1_700_000_123, 1), 1_700_000_123);
+        assert_eq!(round_down(-5, 1), -5);
+    }
+
+    #[tokio::test]
+    async fn tracking_start_counts_from_its_exact_second() {
+        let db = test_database().await;
+        let user_id = test_user(&db, 1).await;
+        db.set_tracking_start(user_id, Some(1_000)).await.unwrap();
+        db.insert_log(user_id, 1, 999, None, None).await.unwrap();
+        db.insert_log(user_id, 1, 1_000, None, None).await.unwrap();
+
+        assert_eq!(db.get_user_stats(user_id, None, None).await.unwrap(), 1);
+        let totals = db.get_user_totals(10, None).await.unwrap();
+        assert_eq!(totals.len(), 1);
+        assert_eq!(totals[0].logs, 1);
+    }
+
+    #[tokio::test]
+    async fn kudos_stop_at_the_daily_limit_to_the_second() {
+        let db = test_database().await;
+        let giver = test_user(&db, 1).await;
+        let recipient = test_user(&db, 2).await;
+        for ts in [1_000, 2_000] {
+            let kudos = db.insert_kudos(recipient, 1, ts, giver, 2, None).await;
+            assert_eq!(kudos.unwrap(), Kudos::Given);
+        }
+        let kudos = db.insert_kudos(recipient, 1, 1_000 + 86_399, giver, 2, None);
+        assert_eq!(kudos.await.unwrap(), Kudos::LimitReached);
+        // A day after the first one, it no longer counts
+        let kudos = db.insert_kudos(recipient, 1, 1_000 + 86_400, giver, 2, None);
+        assert_eq!(kudos.await.unwrap(), Kudos::Given);
+        let kudos = db.insert_kudos(recipient, 1, 90_000, giver, 3, Some((0, 100_000)));
+        assert_eq!(kudos.await.unwrap(), Kudos::AlreadyLogged);
+    }
+
+    #[tokio::test]
+    async fn schema_and_maintenance_run_on_the_backend() {
+        let db = test_database().await;
+        let schema = db.get_schema().await.unwrap();
+        assert!(
+            schema
+                .iter()
+                .any(|sql| sql.starts_with("CREATE TABLE logs"))
+        );
+        let (before, after) = db.maintenance().await.unwrap();
+        assert!(before > 0 && after > 0);
+    }
+
+    #[cfg(not(feature = "postgres"))]
+    #[tokio::test]
+    async fn pool_reconnects_after_dropped_connections() {
+        use sqlx::Connection;
+
+        let path = std::env::temp_dir().join(format!("logger-bot-{}.db", std::process::id()));
+        let url = format!("sqlite://{}?mode=rwc", path.display());
+        let pool = pool_options(Duration::from_millis(50), Duration::from_millis(200))
+            .connect(&url)
+            .await
+            .unwrap();
+        MIGRATOR.run(&pool).await.unwrap();
+        let db = Database {
+            pool,
+            slow_query_threshold: DEFAULT_SLOW_QUERY_THRESHOLD,
+            timestamp_precision: 1,
+        };
+        let user_id = test_user(&db, 1).await;
+        db.insert_log(user_id, 1, 0, None, None).await.unwrap();
+
+        // A connection that goes away while the pool still counts on it
+        let conn = db.pool.acquire().await.unwrap().detach();
+        conn.close().await.unwrap();
+        db.insert_log(user_id, 1, 60, None, None).await.unwrap();
+
+        // Past the idle timeout and the lifetime, every connection has been closed
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        assert_eq!(db.pool.size(), 0);
+        assert_eq!(db.get_user_stats(user_id, None, None).await.unwrap(), 2);
+
+        db.pool.close().await;
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn monthly_hourly_and_weekday_counts_use_local_time() {
+        let db = test_database().await;
+        let user_id = test_user(&db, 1).await;
+        db.set_utc_offset(user_id, 2 * 60).await.unwrap();
+        // 2023-12-31 23:30 UTC, 01:30 on New Year's Day in UTC+2
+        db.insert_log(user_id, 1, 1_704_065_400, None, None)
+            .await
+            .unwrap();
+
+        let monthly = db.get_monthly_counts(user_id, 2024, None).await.unwrap();
+        assert_eq!(monthly[0], 1);
+        assert_eq!(
+            db.get_monthly_counts(user_id, 2023, None).await.unwrap(),
+            [0; 12]
+        );
+        let hourly = db.get_hourly_counts(user_id, None).await.unwrap();
+        assert_eq!(hourly[1], 1);
+        // A Monday
+        assert_eq!(db.get_weekday_counts(user_id).await.unwrap()[0], 1);
+
+        // Until 02:00 the log still counts for the last day of 2023
+        db.set_day_end(user_id, 2 * 60).await.unwrap();
+        let monthly = db.get_monthly_counts(user_id, 2023, None).await.unwrap();
+        assert_eq!(monthly[11], 1);
+        let hourly = db.get_hourly_counts(user_id, None).await.unwrap();
+        assert_eq!(hourly[1], 1);
+        assert_eq!(db.get_weekday_counts(user_id).await.unwrap()[6], 1);
+    }
+
+    #[tokio::test]
+    async fn user_totals_in_a_chat_only_count_its_members() {
+        let db = test_database().await;
+        let member = test_user(&db, 1).await;
+        let outsider = test_user(&db, 2).await;
+        db.insert_log(member, -100, 0, None, None).await.unwrap();
+        for ts in [0, 60] {
+            db.insert_log(outsider, 2, ts, None, None).await.unwrap();
+        }
+
+        let top = db.get_user_totals(1, None).await.unwrap();
+        assert_eq!(top[0].telegram_id, 2);
+        let top = db.get_user_totals(1, Some(-100)).await.unwrap();
+        assert_eq!(top[0].telegram_id, 1);
     }
 }
@@ -0,0 +1,63 @@
+use std::fmt::Write;
+
+use chrono::{DateTime, SecondsFormat};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, duplex};
+use tracing::error;
+
+use crate::database::Database;
+
+/// Number of logs read from the database at a time.
+const PAGE_SIZE: i64 = 1000;
+/// Bytes buffered between the database pages and the upload.
+const PIPE_CAPACITY: usize = 64 * 1024;
+
+/// Streams all of the user's logs as CSV, one database page at a time.
+/// The first column is the Unix timestamp, so the file can be imported again.
+pub fn export_csv(db: Database, user_id: i64) -> impl AsyncRead + Send + Unpin + 'static {
+    let (reader, mut writer) = duplex(PIPE_CAPACITY);
+    tokio::spawn(async move {
+        if let Err(err) = write_csv(&db, user_id, &mut writer).await {
+            error!("Failed to export the logs of the user {user_id}: {err}");
+        }
+    });
+    reader
+}
+
+async fn write_csv(
+    db: &Database,
+    user_id: i64,
+    writer: &mut (impl AsyncWrite + Unpin),
+) -> anyhow::Result<()> {
+    writer
+        .write_all(b"timestamp,time_utc,category,note\n")
+        .await?;
+    let mut after_id = 0;
+    loop {
+        let page = db.get_logs_page(user_id, after_id, PAGE_SIZE).await?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        after_id = last.id;
+        let mut chunk = String::new();
+        for log in &page {
+            let time = DateTime::from_timestamp(log.timestamp, 0)
+                .map(|dt| dt.to_rfc3339_opts(SecondsFormat::Secs, true))
+                .unwrap_or_default();
+            let category = log.category.as_deref().map(csv_field).unwrap_or_default();
+            let note = log.note.as_deref().map(csv_field).unwrap_or_default();
+            writeln!(chunk, "{},{time},{category},{note}", log.timestamp)?;
+        }
+        writer.write_all(chunk.as_bytes()).await?;
+    }
+    writer.shutdown().await?;
+    Ok(())
+}
+
+/// Quotes a field if it contains characters with a special meaning in CSV.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,236 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use reqwest::{
+    Client,
+    dns::{Addrs, Name, Resolve, Resolving},
+    header::CONTENT_TYPE,
+    redirect,
+};
+use serde_json::Value;
+use teloxide::{net::Download, prelude::*, types::Document};
+use url::{Host, Url};
+
+/// Larger downloads are aborted.
+const MAX_BODY_SIZE: usize = 1024 * 1024;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_REDIRECTS: usize = 3;
+const ALLOWED_CONTENT_TYPES: [&str; 3] = ["text/csv", "text/plain", "application/csv"];
+
+/// Downloads a CSV file, refusing anything but public HTTPS URLs and small text bodies.
+pub async fn fetch_csv(url: &Url) -> anyhow::Result<String> {
+    check_url(url)?;
+    let client = Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .dns_resolver(Arc::new(PublicResolver))
+        .redirect(redirect::Policy::custom(|attempt| {
+            if attempt.previous().len() >= MAX_REDIRECTS || check_url(attempt.url()).is_err() {
+                attempt.stop()
+            } else {
+                attempt.follow()
+            }
+        }))
+        .build()?;
+    let mut response = client.get(url.clone()).send().await?.error_for_status()?;
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_lowercase();
+    anyhow::ensure!(
+        ALLOWED_CONTENT_TYPES
+            .iter()
+            .any(|allowed| content_type.starts_with(allowed)),
+        "Unexpected content type: {content_type}"
+    );
+    if response
+        .content_length()
+        .is_some_and(|len| len > MAX_BODY_SIZE as u64)
+    {
+        anyhow::bail!("The file is too large");
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        body.extend_from_slice(&chunk);
+        anyhow::ensure!(body.len() <= MAX_BODY_SIZE, "The file is too large");
+    }
+    String::from_utf8(body).context("The file is not valid UTF-8")
+}
+
+/// Downloads a document sent to the bot, refusing files over the size limit.
+pub async fn fetch_document(bot: &Bot, document: &Document) -> anyhow::Result<String> {
+    anyhow::ensure!(
+        document.file.size as usize <= MAX_BODY_SIZE,
+        "The file is too large"
+    );
+    let file = bot.get_file(document.file.id.clone()).await?;
+    let mut body = Vec::new();
+    bot.download_file(&file.path, &mut body).await?;
+    String::from_utf8(body).context("The file is not valid UTF-8")
+}
+
+/// Whether a document should be parsed as JSON rather than CSV.
+pub fn is_json(document: &Document) -> bool {
+    let by_name = document
+        .file_name
+        .as_deref()
+        .is_some_and(|name| name.to_lowercase().ends_with(".json"));
+    let by_type = document
+        .mime_type
+        .as_ref()
+        .is_some_and(|mime| mime.essence_str() == "application/json");
+    by_name || by_type
+}
+
+/// Only HTTPS URLs not pointing at a local or private address are allowed.
+/// Domains are checked once resolved, by `PublicResolver`.
+fn check_url(url: &Url) -> anyhow::Result<()> {
+    anyhow::ensure!(url.scheme() == "https", "Only https URLs are allowed");
+    let ip: IpAddr = match url.host() {
+        Some(Host::Ipv4(ip)) => ip.into(),
+        Some(Host::Ipv6(ip)) => ip.into(),
+        Some(Host::Domain(domain)) => {
+            anyhow::ensure!(domain != "localhost", "Local addresses are not allowed");
+            return Ok(());
+        }
+        None => anyhow::bail!("The URL has no host"),
+    };
+    anyhow::ensure!(!is_private(ip), "Local addresses are not allowed");
+    Ok(())
+}
+
+/// Whether the address is loopback, private, link-local or unspecified,
+/// including IPv4 addresses mapped into IPv6.
+fn is_private(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_private(ip.into()),
+            None => {
+                ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_unique_local()
+                    || ip.is_unicast_link_local()
+            }
+        },
+    }
+}
+
+/// Resolves domains with the system resolver, refusing those with any local or private
+/// address. The connection only uses the checked addresses, so a domain can't be
+/// re-resolved to an internal one between the check and the request.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, 0)).await?.collect();
+            if addrs.iter().any(|addr| is_private(addr.ip())) {
+                return Err("Local addresses are not allowed".into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Parses the first column of each row as a Unix timestamp or an RFC 3339 date,
+/// returning the valid timestamps and the number of skipped rows.
+/// Rows that can't be parsed (e.g. a header) or lie in the future are skipped.
+pub fn parse_timestamps(csv: &str) -> (Vec<i64>, usize) {
+    let now = Utc::now().timestamp();
+    let mut timestamps = Vec::new();
+    let mut skipped = 0;
+    for line in csv.lines().filter(|l| !l.trim().is_empty()) {
+        let field = line.split(',').next().unwrap_or_default().trim();
+        match parse_timestamp(field.trim_matches('"')) {
+            Some(ts) if (0..=now).contains(&ts) => timestamps.push(ts),
+            _ => skipped += 1,
+        }
+    }
+    (timestamps, skipped)
+}
+
+/// Parses a JSON array of Unix timestamps, RFC 3339 dates or objects with a `timestamp` field,
+/// returning the valid timestamps and the number of skipped items.
+/// Items that can't be parsed or lie in the future are skipped.
+pub fn parse_json_timestamps(json: &str) -> anyhow::Result<(Vec<i64>, usize)> {
+    let items: Vec<Value> = serde_json::from_str(json).context("Expected a JSON array")?;
+    let now = Utc::now().timestamp();
+    let mut timestamps = Vec::new();
+    let mut skipped = 0;
+    for item in &items {
+        let value = match item {
+            Value::Object(fields) => fields.get("timestamp").unwrap_or(&Value::Null),
+            other => other,
+        };
+        let ts = match value {
+            Value::Number(n) => n.as_i64(),
+            Value::String(s) => parse_timestamp(s.trim()),
+            _ => None,
+        };
+        match ts {
+            Some(ts) if (0..=now).contains(&ts) => timestamps.push(ts),
+            _ => skipped += 1,
+        }
+    }
+    Ok((timestamps, skipped))
+}
+
+fn parse_timestamp(field: &str) -> Option<i64> {
+    field.parse::<i64>().ok().or_else(|| {
+        DateTime::parse_from_rfc3339(field)
+            .ok()
+            .map(|dt| dt.timestamp())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allowed(url: &str) -> bool {
+        check_url(&Url::parse(url).unwrap()).is_ok()
+    }
+
+    #[test]
+    fn check_url_rejects_local_addresses() {
+        assert!(allowed("https://example.com/logs.csv"));
+        assert!(allowed("https://93.184.216.34/logs.csv"));
+        assert!(allowed("https://[2606:2800:220:1::1]/logs.csv"));
+        assert!(!allowed("http://example.com/logs.csv"));
+        assert!(!allowed("https://localhost/logs.csv"));
+        for ip in [
+            "127.0.0.1",
+            "10.0.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "0.0.0.0",
+            "[::1]",
+            "[fd00::1]",
+            "[fe80::1]",
+            "[::ffff:127.0.0.1]",
+            "[::ffff:10.0.0.1]",
+        ] {
+            assert!(!allowed(&format!("https://{ip}/logs.csv")), "{ip}");
+        }
+    }
+
+    #[tokio::test]
+    async fn resolver_rejects_domains_with_local_addresses() {
+        let name: Name = "localhost".parse().unwrap();
+        assert!(PublicResolver.resolve(name).await.is_err());
+    }
+}
@@ -0,0 +1,24 @@
+/// Every log is worth one XP. Going from level `n` to `n + 1` takes `STEP * n` XP,
+/// so early levels come quickly and later ones take longer.
+const STEP: i64 = 10;
+
+/// Total XP needed to reach `level`.
+pub fn xp_for_level(level: i64) -> i64 {
+    STEP * level * (level - 1) / 2
+}
+
+/// The level reached with `xp`, starting at 1.
+pub fn level(xp: i64) -> i64 {
+    let mut level = 1;
+    while xp_for_level(level + 1) <= xp {
+        level += 1;
+    }
+    level
+}
+
+/// The level reached with `xp`, the XP gained within it and the XP the level takes.
+pub fn progress(xp: i64) -> (i64, i64, i64) {
+    let level = level(xp);
+    let start = xp_for_level(level);
+    (level, xp - start, xp_for_level(level + 1) - start)
+}
@@ -0,0 +1,1094 @@
+use std::fmt::Display;
+
+/// Abbreviated month names by language, for chart labels.
+const MONTH_NAMES: [(&str, [&str; 12]); 8] = [
+    (
+        "en",
+        [
+            "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+        ],
+    ),
+    (
+        "de",
+        [
+            "Jan", "Feb", "Mär", "Apr", "Mai", "Jun", "Jul", "Aug", "Sep", "Okt", "Nov", "Dez",
+        ],
+    ),
+    (
+        "es",
+        [
+            "ene", "feb", "mar", "abr", "may", "jun", "jul", "ago", "sep", "oct", "nov", "dic",
+        ],
+    ),
+    (
+        "fr",
+        [
+            "janv", "févr", "mars", "avr", "mai", "juin", "juil", "août", "sept", "oct", "nov",
+            "déc",
+        ],
+    ),
+    (
+        "it",
+        [
+            "gen", "feb", "mar", "apr", "mag", "giu", "lug", "ago", "set", "ott", "nov", "dic",
+        ],
+    ),
+    (
+        "pt",
+        [
+            "jan", "fev", "mar", "abr", "mai", "jun", "jul", "ago", "set", "out", "nov", "dez",
+        ],
+    ),
+    (
+        "ru",
+        [
+            "янв", "фев", "мар", "апр", "май", "июн", "июл", "авг", "сен", "окт", "ноя", "дек",
+        ],
+    ),
+    (
+        "uk",
+        [
+            "січ", "лют", "бер", "кві", "тра", "чер", "лип", "сер", "вер", "жов", "лис", "гру",
+        ],
+    ),
+];
+
+/// Abbreviated weekday names by language, Monday first, for chart labels.
+const WEEKDAY_NAMES: [(&str, [&str; 7]); 8] = [
+    ("en", ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"]),
+    ("de", ["Mo", "Di", "Mi", "Do", "Fr", "Sa", "So"]),
+    ("es", ["lun", "mar", "mié", "jue", "vie", "sáb", "dom"]),
+    ("fr", ["lun", "mar", "mer", "jeu", "ven", "sam", "dim"]),
+    ("it", ["lun", "mar", "mer", "gio", "ven", "sab", "dom"]),
+    ("pt", ["seg", "ter", "qua", "qui", "sex", "sáb", "dom"]),
+    ("ru", ["пн", "вт", "ср", "чт", "пт", "сб", "вс"]),
+    ("uk", ["пн", "вт", "ср", "чт", "пт", "сб", "нд"]),
+];
+
+/// Returns the month names for a Telegram language code like `de` or `pt-br`,
+/// `None` if the language is unknown.
+pub fn month_names(language: &str) -> Option<&'static [&'static str; 12]> {
+    let language = language.split('-').next()?.to_lowercase();
+    MONTH_NAMES
+        .iter()
+        .find(|(code, _)| *code == language)
+        .map(|(_, names)| names)
+}
+
+/// Returns the weekday names for a Telegram language code, `None` if the language is unknown.
+pub fn weekday_names(language: &str) -> Option<&'static [&'static str; 7]> {
+    let language = language.split('-').next()?.to_lowercase();
+    WEEKDAY_NAMES
+        .iter()
+        .find(|(code, _)| *code == language)
+        .map(|(_, names)| names)
+}
+
+/// A language the bot's replies can be in.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    English,
+    German,
+}
+
+impl Language {
+    pub const ALL: [Self; 2] = [Self::English, Self::German];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::English => "en",
+            Self::German => "de",
+        }
+    }
+
+    /// The language's own name, for menus.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::English => "English",
+            Self::German => "Deutsch",
+        }
+    }
+
+    /// Returns the language for a Telegram language code like `de` or `pt-br`,
+    /// `None` if there is no translation for it.
+    pub fn from_code(code: &str) -> Option<Self> {
+        let code = code.split('-').next()?.to_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|language| language.code() == code)
+    }
+
+    /// The language the user picked, otherwise the one of their Telegram app, otherwise English.
+    pub fn for_user(chosen: Option<&str>, telegram: Option<&str>) -> Self {
+        chosen
+            .or(telegram)
+            .and_then(Self::from_code)
+            .unwrap_or(Self::English)
+    }
+
+    /// Translates an English text, which is returned as is if it has no translation.
+    pub fn translate(self, text: &str) -> &str {
+        let catalog = match self {
+            Self::English => return text,
+            Self::German => GERMAN,
+        };
+        catalog
+            .iter()
+            .find(|(english, _)| *english == text)
+            .map_or(text, |(_, translated)| translated)
+    }
+}
+
+/// Replaces each `{}` in a translated `template` with the next of `args`.
+pub fn fill(template: &str, args: &[&(dyn Display + Sync)]) -> String {
+    let mut parts = template.split("{}");
+    let mut text = parts.next().unwrap_or_default().to_string();
+    for (i, part) in parts.enumerate() {
+        if let Some(arg) = args.get(i) {
+            text.push_str(&arg.to_string());
+        }
+        text.push_str(part);
+    }
+    text
+}
+
+/// German translations, keyed by the English text.
+const GERMAN: &[(&str, &str)] = &[
+    // Errors and limits
+    ("Database error :(", "Datenbankfehler :("),
+    (
+        "Error generating the chart :(",
+        "Fehler beim Erstellen des Diagramms :(",
+    ),
+    ("Please slow down", "Bitte etwas langsamer"),
+    (
+        "This button isn't for you",
+        "Dieser Knopf ist nicht für dich",
+    ),
+    ("Cancelled", "Abgebrochen"),
+    (
+        "You logged {} seconds ago, log again?",
+        "Du hast vor {} Sekunden einen Eintrag gemacht, noch einmal eintragen?",
+    ),
+    ("Log anyway", "Trotzdem eintragen"),
+    ("Cancel", "Abbrechen"),
+    (
+        "This confirmation has expired, send /done again",
+        "Diese Bestätigung ist abgelaufen, sende /done erneut",
+    ),
+    (
+        "This command is for admins only",
+        "Dieser Befehl ist nur für Admins",
+    ),
+    (
+        "Only chat admins can change this setting",
+        "Nur Chat-Admins können diese Einstellung ändern",
+    ),
+    (
+        "Under maintenance, back soon",
+        "Wartungsarbeiten, gleich wieder da",
+    ),
+    (
+        "Error generating the charts :(",
+        "Fehler beim Erstellen der Diagramme :(",
+    ),
+    (
+        "Error exporting the stats :(",
+        "Fehler beim Exportieren der Statistik :(",
+    ),
+    ("Keyboard updated", "Tastatur aktualisiert"),
+    // Logging
+    ("Already logged today ✅", "Heute schon eingetragen ✅"),
+    (
+        "👍 That's {} today, {} this month, streak: {}",
+        "👍 Das sind {} heute, {} diesen Monat, Serie: {}",
+    ),
+    ("1 day", "1 Tag"),
+    ("Share my stats", "Meine Statistik teilen"),
+    (
+        "📊 {}: {} logs, {} today, streak: {}",
+        "📊 {}: {} Einträge, {} heute, Serie: {}",
+    ),
+    ("Share my latest /card", "Meine letzte /card teilen"),
+    (
+        "Categories can be at most {} characters long",
+        "Kategorien dürfen höchstens {} Zeichen lang sein",
+    ),
+    (
+        "Notes can be at most {} characters long",
+        "Notizen dürfen höchstens {} Zeichen lang sein",
+    ),
+    (
+        "You have no logs to undo",
+        "Du hast keine Einträge zum Rückgängigmachen",
+    ),
+    (
+        "Removed your last log, your score: {}",
+        "Dein letzter Eintrag wurde entfernt, dein Punktestand: {}",
+    ),
+    (
+        "Removed your last log",
+        "Dein letzter Eintrag wurde entfernt",
+    ),
+    ("Logged for {} 👍", "Für {} eingetragen 👍"),
+    ("Already logged on {} ✅", "Am {} schon eingetragen ✅"),
+    (
+        "You can't log in the future",
+        "Du kannst nicht in der Zukunft eintragen",
+    ),
+    (
+        "Usage: /log [today|yesterday|2024-03-01] [14:30], in your timezone (see /timezone)",
+        "Verwendung: /log [today|yesterday|2024-03-01] [14:30], in deiner Zeitzone (siehe /timezone)",
+    ),
+    ("No logs yet", "Noch keine Einträge"),
+    ("◀ Prev", "◀ Zurück"),
+    ("Next ▶", "Weiter ▶"),
+    (
+        "No categories yet, log one with /done <category>",
+        "Noch keine Kategorien, trag eine mit /done <Kategorie> ein",
+    ),
+    // Stats
+    ("Your score: {}", "Dein Punktestand: {}"),
+    (" (since {})", " (seit {})"),
+    ("Level {} {} {}/{} XP", "Level {} {} {}/{} XP"),
+    ("By category:", "Nach Kategorie:"),
+    ("This week: {}/{} {}", "Diese Woche: {}/{} {}"),
+    ("This month: {}/{} {}", "Diesen Monat: {}/{} {}"),
+    ("You have no logs yet", "Du hast noch keine Einträge"),
+    (
+        "No logs in that category",
+        "Keine Einträge in dieser Kategorie",
+    ),
+    (
+        "From {} to {}: {} logs on {} days",
+        "Vom {} bis {}: {} Einträge an {} Tagen",
+    ),
+    (
+        "Since {}: {} logs on {} days",
+        "Seit {}: {} Einträge an {} Tagen",
+    ),
+    (
+        "Usage: /statssince 2024-01-01 [2024-03-31]",
+        "Verwendung: /statssince 2024-01-01 [2024-03-31]",
+    ),
+    (
+        "Usage: /monthstats [YYYY-MM]",
+        "Verwendung: /monthstats [JJJJ-MM]",
+    ),
+    (
+        "Usage: /annualstats [year] [category], the year from {} to {}",
+        "Verwendung: /annualstats [Jahr] [Kategorie], das Jahr von {} bis {}",
+    ),
+    (
+        "You're on fire this week! 🔥",
+        "Du bist diese Woche nicht zu bremsen! 🔥",
+    ),
+    (
+        "Look at that progress, keep climbing! 📈",
+        "Was für ein Fortschritt, weiter so! 📈",
+    ),
+    (
+        "Better than last week, that's how it's done 💪",
+        "Besser als letzte Woche, so geht das 💪",
+    ),
+    (
+        "Nice and steady, consistency wins 🐢",
+        "Schön gleichmäßig, Beständigkeit gewinnt 🐢",
+    ),
+    (
+        "Same great pace as last week, keep it up 👌",
+        "Gleiches starkes Tempo wie letzte Woche, weiter so 👌",
+    ),
+    (
+        "Slow and steady still gets there 🚶",
+        "Langsam, aber sicher kommt man auch ans Ziel 🚶",
+    ),
+    (
+        "A slower week happens, today is a good day to bounce back 🌱",
+        "Eine ruhigere Woche kommt vor, heute ist ein guter Tag für einen Neustart 🌱",
+    ),
+    (
+        "Every streak starts with a single /done 🙂",
+        "Jede Serie beginnt mit einem einzigen /done 🙂",
+    ),
+    (
+        "You've done it before, you can do it again 💫",
+        "Du hast es schon einmal geschafft, du schaffst es wieder 💫",
+    ),
+    // Goals, achievements and levels
+    (
+        "🎯 Weekly goal reached: {}/{}! Well done 🎉",
+        "🎯 Wochenziel erreicht: {}/{}! Gut gemacht 🎉",
+    ),
+    (
+        "🎯 Monthly goal reached: {}/{}! Well done 🎉",
+        "🎯 Monatsziel erreicht: {}/{}! Gut gemacht 🎉",
+    ),
+    ("🏅 Achievement unlocked!", "🏅 Abzeichen freigeschaltet!"),
+    ("{} {} ({} logs)", "{} {} ({} Einträge)"),
+    ("Your achievements:", "Deine Abzeichen:"),
+    (
+        "{} {} - {} logs, earned {}",
+        "{} {} - {} Einträge, erhalten am {}",
+    ),
+    ("🔒 {} - {} logs", "🔒 {} - {} Einträge"),
+    ("First steps", "Erste Schritte"),
+    ("Centurion", "Hunderter"),
+    ("Dedicated", "Engagiert"),
+    ("Thousand club", "Tausender-Club"),
+    ("Legend", "Legende"),
+    (
+        "⬆️ Level up! You are now level {}",
+        "⬆️ Aufgestiegen! Du bist jetzt Level {}",
+    ),
+    (
+        "Usage: /goal 5 per week or /goal 20 per month (0 to remove)",
+        "Verwendung: /goal 5 per week oder /goal 20 per month (0 zum Entfernen)",
+    ),
+    (
+        "Your goal is now {} per week",
+        "Dein Ziel ist jetzt {} pro Woche",
+    ),
+    (
+        "Your goal is now {} per month",
+        "Dein Ziel ist jetzt {} pro Monat",
+    ),
+    (
+        "Your weekly goal has been removed",
+        "Dein Wochenziel wurde entfernt",
+    ),
+    (
+        "Your monthly goal has been removed",
+        "Dein Monatsziel wurde entfernt",
+    ),
+    (
+        "Usage: /setweeklygoal 5 (0 to remove)",
+        "Verwendung: /setweeklygoal 5 (0 zum Entfernen)",
+    ),
+    ("Your weekly goal is now {}", "Dein Wochenziel ist jetzt {}"),
+    // Ranking and privacy
+    (
+        "You are #{} of {} (top {}%)",
+        "Du bist #{} von {} (Top {}%)",
+    ),
+    (
+        "You are not on the leaderboard yet, log something first",
+        "Du bist noch nicht in der Rangliste, trag zuerst etwas ein",
+    ),
+    (
+        "You are hidden from rankings, show yourself again with /privacy off",
+        "Du bist in Ranglisten ausgeblendet, zeig dich wieder mit /privacy off",
+    ),
+    ("Usage: /privacy on|off", "Verwendung: /privacy on|off"),
+    (
+        "You are now hidden from leaderboards, streak rankings and comparisons, your own stats are unchanged",
+        "Du bist jetzt in Ranglisten, Serien-Ranglisten und Vergleichen ausgeblendet, deine eigenen Statistiken bleiben unverändert",
+    ),
+    (
+        "You are now shown on leaderboards and streak rankings again",
+        "Du wirst wieder in Ranglisten und Serien-Ranglisten angezeigt",
+    ),
+    ("Usage: /quiet on|off", "Verwendung: /quiet on|off"),
+    (
+        "Reached goals, achievements and level-ups will no longer be announced",
+        "Erreichte Ziele, Abzeichen und Aufstiege werden nicht mehr angekündigt",
+    ),
+    (
+        "Reached goals, achievements and level-ups will be announced again",
+        "Erreichte Ziele, Abzeichen und Aufstiege werden wieder angekündigt",
+    ),
+    ("The leaderboard is empty", "Die Bestenliste ist leer"),
+    ("No active streaks", "Keine aktiven Serien"),
+    ("You", "Du"),
+    ("User {}", "Nutzer {}"),
+    (
+        "Usage: /leaderboardday 2024-06-01",
+        "Verwendung: /leaderboardday 2024-06-01",
+    ),
+    (
+        "Usage: /compare @user (they must have used the bot before)",
+        "Verwendung: /compare @user (die Person muss den Bot schon benutzt haben)",
+    ),
+    (
+        "This user doesn't allow comparisons, they can turn them on with /allowcompare on",
+        "Diese Person erlaubt keine Vergleiche, sie kann sie mit /allowcompare on einschalten",
+    ),
+    (
+        "Use /compareyears to compare with yourself",
+        "Nutze /compareyears, um dich mit dir selbst zu vergleichen",
+    ),
+    (
+        "Usage: /allowcompare on|off",
+        "Verwendung: /allowcompare on|off",
+    ),
+    (
+        "Others can now compare their charts with yours using /compare",
+        "Andere können ihre Diagramme jetzt mit /compare mit deinen vergleichen",
+    ),
+    (
+        "Others can no longer compare their charts with yours",
+        "Andere können ihre Diagramme nicht mehr mit deinen vergleichen",
+    ),
+    // Groups and teams
+    (
+        "🏆 {} | 👥 {} | 📊 {} total | 🔥 top streak {}d",
+        "🏆 {} | 👥 {} | 📊 {} gesamt | 🔥 längste Serie {}T",
+    ),
+    (
+        "Usage: /anonymousleaderboard on|off",
+        "Verwendung: /anonymousleaderboard on|off",
+    ),
+    (
+        "The leaderboard is now anonymous",
+        "Die Bestenliste ist jetzt anonym",
+    ),
+    (
+        "The leaderboard now shows usernames",
+        "Die Bestenliste zeigt jetzt Benutzernamen",
+    ),
+    ("Usage: /habitmode on|off", "Verwendung: /habitmode on|off"),
+    (
+        "Habit mode is on: only the first /done of each day counts",
+        "Gewohnheitsmodus ist an: nur das erste /done eines Tages zählt",
+    ),
+    (
+        "Habit mode is off: every /done counts",
+        "Gewohnheitsmodus ist aus: jedes /done zählt",
+    ),
+    (
+        "Usage: /ack reply|reaction|silent",
+        "Verwendung: /ack reply|reaction|silent",
+    ),
+    (
+        "New logs are now acknowledged with a reply",
+        "Neue Einträge werden jetzt mit einer Antwort bestätigt",
+    ),
+    (
+        "New logs are now acknowledged with a 👍 reaction",
+        "Neue Einträge werden jetzt mit einer 👍-Reaktion bestätigt",
+    ),
+    (
+        "New logs are now recorded without an acknowledgement",
+        "Neue Einträge werden jetzt ohne Bestätigung erfasst",
+    ),
+    (
+        "Usage: /dailycap 5 or /dailycap off",
+        "Verwendung: /dailycap 5 oder /dailycap off",
+    ),
+    (
+        "At most {} logs per day now count toward scores",
+        "Jetzt zählen höchstens {} Einträge pro Tag für die Punkte",
+    ),
+    (
+        "All logs now count toward scores",
+        "Jetzt zählen alle Einträge für die Punkte",
+    ),
+    (
+        "Usage: /teamgoal 1000 or /teamgoal off",
+        "Verwendung: /teamgoal 1000 oder /teamgoal off",
+    ),
+    (
+        "The team goal is now {} logs per month",
+        "Das Teamziel ist jetzt {} Einträge pro Monat",
+    ),
+    (
+        "The team goal has been removed",
+        "Das Teamziel wurde entfernt",
+    ),
+    (
+        "This chat has no team goal, set one with /teamgoal",
+        "Dieser Chat hat kein Teamziel, setz eins mit /teamgoal",
+    ),
+    (
+        "Team progress this month: {}/{} {}",
+        "Teamfortschritt diesen Monat: {}/{} {}",
+    ),
+    ("Top contributors:", "Die fleißigsten Mitglieder:"),
+    // Kudos and watching
+    (
+        "Reply to someone's message with /kudos to log for them",
+        "Antworte mit /kudos auf die Nachricht von jemandem, um für diese Person einzutragen",
+    ),
+    ("👏 Kudos to {}!", "👏 Anerkennung für {}!"),
+    (
+        "{} already logged today ✅",
+        "{} hat heute schon eingetragen ✅",
+    ),
+    (
+        "You've given all your kudos for today",
+        "Du hast heute schon alle deine Anerkennungen vergeben",
+    ),
+    (
+        "Usage: /watch @user (they must have used the bot before)",
+        "Verwendung: /watch @user (die Person muss den Bot schon benutzt haben)",
+    ),
+    (
+        "You can't watch yourself",
+        "Du kannst dir nicht selbst folgen",
+    ),
+    (
+        "{} wants to be notified about your milestones",
+        "{} möchte über deine Meilensteine benachrichtigt werden",
+    ),
+    ("Allow", "Erlauben"),
+    ("Decline", "Ablehnen"),
+    (
+        "Request sent, you'll be notified once they allow it",
+        "Anfrage gesendet, du wirst benachrichtigt, sobald sie erlaubt wird",
+    ),
+    (
+        "Couldn't ask them, they need to start a private chat with the bot first",
+        "Die Anfrage ging nicht durch, die Person muss zuerst einen privaten Chat mit dem Bot starten",
+    ),
+    (
+        "{} allowed you to watch their milestones",
+        "{} hat dir erlaubt, ihren Meilensteinen zu folgen",
+    ),
+    (
+        "They will be notified about your milestones",
+        "Die Person wird über deine Meilensteine benachrichtigt",
+    ),
+    ("Declined", "Abgelehnt"),
+    (
+        "This request no longer exists",
+        "Diese Anfrage existiert nicht mehr",
+    ),
+    ("Usage: /unwatch @user", "Verwendung: /unwatch @user"),
+    (
+        "You no longer watch them",
+        "Du folgst der Person nicht mehr",
+    ),
+    (
+        "You weren't watching them",
+        "Du bist der Person nicht gefolgt",
+    ),
+    (
+        "🎉 {} just reached {} logs!",
+        "🎉 {} hat gerade {} Einträge erreicht!",
+    ),
+    // Menu
+    ("What would you like to see?", "Was möchtest du sehen?"),
+    ("Stats", "Statistik"),
+    ("Charts", "Diagramme"),
+    ("Leaderboard", "Bestenliste"),
+    ("Settings", "Einstellungen"),
+    ("◀ Menu", "◀ Menü"),
+    ("Pick a chart:", "Wähle ein Diagramm:"),
+    ("Year", "Jahr"),
+    ("Hours", "Stunden"),
+    ("Weekdays", "Wochentage"),
+    // Settings
+    ("Your settings:", "Deine Einstellungen:"),
+    ("Language: {}", "Sprache: {}"),
+    ("Timezone: {}", "Zeitzone: {}"),
+    ("Chart theme: {}", "Diagramm-Design: {}"),
+    (
+        "Reminder: {} (set it with /remind HH:MM)",
+        "Erinnerung: {} (stell sie mit /remind HH:MM ein)",
+    ),
+    ("Hidden from rankings: {}", "In Ranglisten ausgeblendet: {}"),
+    ("Quiet mode: {}", "Ruhemodus: {}"),
+    ("Day ends at: {}", "Tag endet um: {}"),
+    ("Reply to /done: {}", "Antwort auf /done: {}"),
+    (
+        "Log with \"done\" or \"+1\": {}",
+        "Mit \"done\" oder \"+1\" eintragen: {}",
+    ),
+    ("Reply to /done: {} ▶", "Antwort auf /done: {} ▶"),
+    ("on", "an"),
+    ("off", "aus"),
+    ("automatic", "automatisch"),
+    ("default", "Standard"),
+    ("Timezone −1h", "Zeitzone −1h"),
+    ("Timezone +1h", "Zeitzone +1h"),
+    ("Language: {} ▶", "Sprache: {} ▶"),
+    ("Theme: {} ▶", "Design: {} ▶"),
+    ("Turn the reminder off", "Erinnerung ausschalten"),
+    ("Usage: /language {}|auto", "Verwendung: /language {}|auto"),
+    (
+        "Replies now follow the language of your Telegram app",
+        "Antworten folgen jetzt der Sprache deiner Telegram-App",
+    ),
+    ("Replies are now in {}", "Antworten sind jetzt auf {}"),
+    (
+        "Usage: /timezone +3 or /timezone -5:30",
+        "Verwendung: /timezone +3 oder /timezone -5:30",
+    ),
+    ("Your timezone is now {}", "Deine Zeitzone ist jetzt {}"),
+    (
+        "Usage: /startdate 2024-01-01 or /startdate off",
+        "Verwendung: /startdate 2024-01-01 oder /startdate off",
+    ),
+    (
+        "Your stats now start on {}. Earlier logs are kept, just not counted",
+        "Deine Statistik beginnt jetzt am {}. Frühere Einträge bleiben erhalten, werden aber nicht gezählt",
+    ),
+    (
+        "Your stats now include all your logs",
+        "Deine Statistik umfasst jetzt alle deine Einträge",
+    ),
+    (
+        "Usage: /sharesettings on|off",
+        "Verwendung: /sharesettings on|off",
+    ),
+    (
+        "Others can now copy your settings with /copysettings",
+        "Andere können deine Einstellungen jetzt mit /copysettings übernehmen",
+    ),
+    (
+        "Your settings are no longer shared",
+        "Deine Einstellungen werden nicht mehr geteilt",
+    ),
+    (
+        "Usage: /copysettings @user (they must have used the bot before)",
+        "Verwendung: /copysettings @user (die Person muss den Bot schon benutzt haben)",
+    ),
+    (
+        "Copied their settings:\ntimezone: {}\nend of day: {}\nweekly goal: {}\nmonthly goal: {}\nchart theme: {}",
+        "Einstellungen übernommen:\nZeitzone: {}\nTagesende: {}\nWochenziel: {}\nMonatsziel: {}\nDiagramm-Design: {}",
+    ),
+    ("none", "keins"),
+    (
+        "This user doesn't share their settings",
+        "Diese Person teilt ihre Einstellungen nicht",
+    ),
+    ("Usage: /theme {}|default", "Verwendung: /theme {}|default"),
+    (
+        "Your charts now use the {} theme",
+        "Deine Diagramme nutzen jetzt das Design {}",
+    ),
+    (
+        "Usage: /remind HH:MM or /remind off",
+        "Verwendung: /remind HH:MM oder /remind off",
+    ),
+    (
+        "You'll get a private message at {} (your time, see /timezone) on days you haven't logged",
+        "Du bekommst um {} (deine Zeit, siehe /timezone) eine private Nachricht an Tagen, an denen du nichts eingetragen hast",
+    ),
+    ("Reminder turned off", "Erinnerung ausgeschaltet"),
+    ("Usage: /digest on|off", "Verwendung: /digest on|off"),
+    (
+        "You'll get a summary of your week every Monday in a private message",
+        "Du bekommst jeden Montag eine Zusammenfassung deiner Woche als private Nachricht",
+    ),
+    (
+        "You won't get weekly summaries anymore",
+        "Du bekommst keine wöchentlichen Zusammenfassungen mehr",
+    ),
+    // Reminders and digests
+    (
+        "Your {}-day streak ends in {} hour! /done",
+        "Deine {}-Tage-Serie endet in {} Stunde! /done",
+    ),
+    (
+        "Your {}-day streak ends in {} hours! /done",
+        "Deine {}-Tage-Serie endet in {} Stunden! /done",
+    ),
+    (
+        "You haven't logged anything today yet. /done",
+        "Du hast heute noch nichts eingetragen. /done",
+    ),
+    ("Your week: {} logs", "Deine Woche: {} Einträge"),
+    (
+        "Your week: {} logs, {} compared to the week before",
+        "Deine Woche: {} Einträge, {} im Vergleich zur Vorwoche",
+    ),
+    ("Week of {}", "Woche vom {}"),
+    // Charts
+    ("Month", "Monat"),
+    ("Day", "Tag"),
+    ("Hour", "Stunde"),
+    ("Day of year", "Tag des Jahres"),
+    ("Score", "Punkte"),
+    ("Total score", "Punkte insgesamt"),
+    ("Logs in a day", "Einträge an einem Tag"),
+    ("Number of such days", "Anzahl solcher Tage"),
+    ("Streak", "Serie"),
+    ("Days", "Tage"),
+    ("Current", "Aktuell"),
+    ("Best", "Beste"),
+    ("Goal", "Ziel"),
+    ("{} - no streak yet", "{} - noch keine Serie"),
+    ("{} - {} (no data for {})", "{} - {} (keine Daten für {})"),
+    ("Total", "Gesamt"),
+    ("Rank", "Rang"),
+    ("Current streak", "Aktuelle Serie"),
+    ("Best streak", "Beste Serie"),
+    ("Best day", "Bester Tag"),
+    ("{} days", "{} Tage"),
+    ("{} on {}", "{} am {}"),
+    ("Logs per month, {}", "Einträge pro Monat, {}"),
+    ("Less", "Weniger"),
+    ("More (max {})", "Mehr (max. {})"),
+    // Your data
+    (
+        "Send /delete in a private chat with me",
+        "Sende /delete in einem privaten Chat mit mir",
+    ),
+    (
+        "Usage: /importurl https://example.com/logs.csv",
+        "Verwendung: /importurl https://example.com/logs.csv",
+    ),
+    (
+        "Importing from URLs is disabled",
+        "Importieren von URLs ist deaktiviert",
+    ),
+    (
+        "Usage: reply to a CSV or JSON file with /import",
+        "Verwendung: antworte mit /import auf eine CSV- oder JSON-Datei",
+    ),
+    (
+        "Couldn't download the file: {}",
+        "Die Datei konnte nicht heruntergeladen werden: {}",
+    ),
+    (
+        "Couldn't read the file: {}",
+        "Die Datei konnte nicht gelesen werden: {}",
+    ),
+    (
+        "Imported {} logs, skipped {} rows",
+        "{} Einträge importiert, {} Zeilen übersprungen",
+    ),
+    (
+        "Delete all of your logs from today?",
+        "Alle deine heutigen Einträge löschen?",
+    ),
+    ("Yes, clear today", "Ja, heute leeren"),
+    (
+        "Cleared {} entries from today",
+        "{} Einträge von heute gelöscht",
+    ),
+    (
+        "Delete all of your logs and settings for good?",
+        "Alle deine Einträge und Einstellungen endgültig löschen?",
+    ),
+    ("Yes, delete everything", "Ja, alles löschen"),
+    (
+        "This deletes all of your logs and settings for good. Send {} within {} seconds to confirm",
+        "Damit werden alle deine Einträge und Einstellungen endgültig gelöscht. Sende {} innerhalb von {} Sekunden zur Bestätigung",
+    ),
+    (
+        "No confirmation received, your data has not been deleted",
+        "Keine Bestätigung erhalten, deine Daten wurden nicht gelöscht",
+    ),
+    (
+        "Deletion cancelled, your data has not been deleted",
+        "Löschen abgebrochen, deine Daten wurden nicht gelöscht",
+    ),
+    (
+        "Something went wrong, your data has not been deleted",
+        "Etwas ist schiefgelaufen, deine Daten wurden nicht gelöscht",
+    ),
+    (
+        "All your data has been deleted",
+        "Alle deine Daten wurden gelöscht",
+    ),
+    // Admin
+    (
+        "Maintenance started, this may take a while",
+        "Wartung gestartet, das kann eine Weile dauern",
+    ),
+    (
+        "Maintenance finished\nDatabase size: {} KiB -> {} KiB",
+        "Wartung abgeschlossen\nDatenbankgröße: {} KiB -> {} KiB",
+    ),
+    ("Maintenance failed :(", "Wartung fehlgeschlagen :("),
+    (
+        "Usage: /maintenancemode on|off",
+        "Verwendung: /maintenancemode on|off",
+    ),
+    (
+        "Maintenance mode is on, only admins can use the bot",
+        "Wartungsmodus ist an, nur Admins können den Bot benutzen",
+    ),
+    ("Maintenance mode is off", "Wartungsmodus ist aus"),
+    ("Users with logs: {}", "Nutzer mit Einträgen: {}"),
+    ("p{}: {} logs", "p{}: {} Einträge"),
+    ("max: {} logs", "max: {} Einträge"),
+    ("The audit log is empty", "Das Audit-Log ist leer"),
+    (
+        "The audit log is empty, enable it with AUDIT_LOG=true",
+        "Das Audit-Log ist leer, aktiviere es mit AUDIT_LOG=true",
+    ),
+    ("Diagnostics:", "Diagnose:"),
+    ("Database read", "Datenbank lesen"),
+    ("Database write", "Datenbank schreiben"),
+    ("Chart generation", "Diagrammerstellung"),
+    ("Telegram API", "Telegram-API"),
+    ("Migrations:", "Migrationen:"),
+    (" (failed)", " (fehlgeschlagen)"),
+    ("Schema:", "Schema:"),
+    (
+        "Duplicates can't be told apart from separate logs with rounded timestamps",
+        "Duplikate lassen sich bei gerundeten Zeitstempeln nicht von einzelnen Einträgen unterscheiden",
+    ),
+    ("Removed {} duplicate logs", "{} doppelte Einträge entfernt"),
+    // Command descriptions
+    ("Start the bot", "Den Bot starten"),
+    ("Refresh the keyboard", "Die Tastatur aktualisieren"),
+    (
+        "Open a menu with buttons for your stats, charts, leaderboard and settings",
+        "Ein Menü mit Knöpfen für Statistik, Diagramme, Bestenliste und Einstellungen öffnen",
+    ),
+    (
+        "Log when you're done, optionally with a category and a note: /done gym leg day",
+        "Eintragen, wenn du fertig bist, optional mit Kategorie und Notiz: /done gym Beintag",
+    ),
+    (
+        "Log for an earlier time in your timezone: /log yesterday 14:30 or /log 2024-03-01",
+        "Für einen früheren Zeitpunkt in deiner Zeitzone eintragen: /log yesterday 14:30 oder /log 2024-03-01",
+    ),
+    ("Remove your last log", "Deinen letzten Eintrag entfernen"),
+    (
+        "Log for someone else by replying to their message",
+        "Für jemand anderen eintragen, als Antwort auf dessen Nachricht",
+    ),
+    ("Show your stats", "Deine Statistiken anzeigen"),
+    (
+        "Get some motivation based on your recent activity",
+        "Motivation passend zu deiner letzten Aktivität",
+    ),
+    (
+        "Browse your logs with their notes",
+        "Deine Einträge mit Notizen durchblättern",
+    ),
+    (
+        "Count your logs in a period: /statssince 2024-01-01 [2024-03-31]",
+        "Deine Einträge in einem Zeitraum zählen: /statssince 2024-01-01 [2024-03-31]",
+    ),
+    (
+        "Show your annual stats, optionally for a year and a category: /annualstats 2023 gym",
+        "Deine Jahresstatistik anzeigen, optional für ein Jahr und eine Kategorie: /annualstats 2023 gym",
+    ),
+    (
+        "Show your hourly stats, optionally for a category: /hourlystats gym",
+        "Deine Statistik nach Uhrzeit anzeigen, optional für eine Kategorie: /hourlystats gym",
+    ),
+    (
+        "Show every log this year as a dot by date and time",
+        "Jeden Eintrag dieses Jahres als Punkt nach Datum und Uhrzeit anzeigen",
+    ),
+    (
+        "Show this year's logs per day as a calendar",
+        "Die Einträge dieses Jahres pro Tag als Kalender anzeigen",
+    ),
+    (
+        "Show your logs per day of a month: /monthstats [2024-06]",
+        "Deine Einträge pro Tag eines Monats anzeigen: /monthstats [2024-06]",
+    ),
+    (
+        "Show your annual, hourly and day of the week charts at once",
+        "Deine Jahres-, Uhrzeit- und Wochentagsdiagramme auf einmal anzeigen",
+    ),
+    (
+        "Show your logs per day of the week",
+        "Deine Einträge pro Wochentag anzeigen",
+    ),
+    (
+        "Compare this year's logs per month with last year",
+        "Die Einträge pro Monat dieses Jahres mit dem letzten Jahr vergleichen",
+    ),
+    (
+        "Compare this year's logs per month with someone: /compare @user",
+        "Die Einträge pro Monat dieses Jahres mit jemandem vergleichen: /compare @user",
+    ),
+    (
+        "Compare this year's pace with last year",
+        "Das Tempo dieses Jahres mit dem letzten Jahr vergleichen",
+    ),
+    (
+        "Show how many logs your days usually have",
+        "Anzeigen, wie viele Einträge deine Tage meistens haben",
+    ),
+    (
+        "Compare your current streak with your best one",
+        "Deine aktuelle Serie mit deiner besten vergleichen",
+    ),
+    (
+        "Show all your numbers on one card",
+        "Alle deine Zahlen auf einer Karte anzeigen",
+    ),
+    (
+        "List the badges you earned and the ones still locked",
+        "Deine erhaltenen und noch gesperrten Abzeichen auflisten",
+    ),
+    (
+        "Show the leaderboard, optionally for a category or period: /leaderboard gym|day|week|month|alltime",
+        "Die Rangliste anzeigen, optional für eine Kategorie oder einen Zeitraum: /leaderboard gym|day|week|month|alltime",
+    ),
+    (
+        "Show your position on the leaderboard",
+        "Deinen Platz in der Rangliste anzeigen",
+    ),
+    (
+        "Rank everyone by their current streak",
+        "Alle nach ihrer aktuellen Serie ordnen",
+    ),
+    (
+        "Show a one-line summary to pin",
+        "Eine einzeilige Zusammenfassung zum Anheften anzeigen",
+    ),
+    (
+        "Show who logged the most on a day: /leaderboardday 2024-06-01",
+        "Anzeigen, wer an einem Tag am meisten eingetragen hat: /leaderboardday 2024-06-01",
+    ),
+    (
+        "List the categories used in logs",
+        "Die in Einträgen verwendeten Kategorien auflisten",
+    ),
+    (
+        "Hide names on this chat's leaderboard: /anonymousleaderboard on|off",
+        "Namen in der Rangliste dieses Chats ausblenden: /anonymousleaderboard on|off",
+    ),
+    (
+        "Count only the first log of each day: /habitmode on|off",
+        "Nur den ersten Eintrag jedes Tages zählen: /habitmode on|off",
+    ),
+    (
+        "Acknowledge logs in this chat with a reply, a reaction or silently: /ack reply|reaction|silent",
+        "Einträge in diesem Chat mit einer Antwort, einer Reaktion oder still bestätigen: /ack reply|reaction|silent",
+    ),
+    (
+        "Count at most N logs per day in this chat: /dailycap 5|off",
+        "In diesem Chat höchstens N Einträge pro Tag zählen: /dailycap 5|off",
+    ),
+    (
+        "Start a new season, counting only logs from now on in this chat",
+        "Eine neue Saison starten, die in diesem Chat nur Einträge ab jetzt zählt",
+    ),
+    (
+        "Set a monthly goal for the whole chat: /teamgoal 1000|off",
+        "Ein Monatsziel für den ganzen Chat setzen: /teamgoal 1000|off",
+    ),
+    (
+        "Show the chat's progress toward the team goal",
+        "Den Fortschritt des Chats zum Teamziel anzeigen",
+    ),
+    (
+        "Set a goal: /goal 5 per week or /goal 20 per month (0 to remove)",
+        "Ein Ziel setzen: /goal 5 per week oder /goal 20 per month (0 zum Entfernen)",
+    ),
+    (
+        "Set a weekly goal: /setweeklygoal 5 (0 to remove)",
+        "Ein Wochenziel setzen: /setweeklygoal 5 (0 zum Entfernen)",
+    ),
+    (
+        "Ignore logs before a date in your stats (they are kept): /startdate 2024-01-01 or /startdate off",
+        "Einträge vor einem Datum in deinen Statistiken ignorieren (sie bleiben erhalten): /startdate 2024-01-01 oder /startdate off",
+    ),
+    (
+        "Pick the colors of your charts: /theme dark|light|colorblind|highcontrast|default",
+        "Die Farben deiner Diagramme wählen: /theme dark|light|colorblind|highcontrast|default",
+    ),
+    (
+        "Set your UTC offset, e.g. /timezone +3 or /timezone -5:30",
+        "Deinen UTC-Versatz setzen, z. B. /timezone +3 oder /timezone -5:30",
+    ),
+    (
+        "Redraw your charts and reminders after changing your timezone",
+        "Diagramme und Erinnerungen nach einem Zeitzonenwechsel neu berechnen",
+    ),
+    (
+        "Set your reply to /done, phrases separated by | taking turns, {today} for today's count: /ackmessage 🔥 {today}|off",
+        "Deine Antwort auf /done festlegen, abwechselnde Sätze durch | getrennt, {today} für die heutige Anzahl: /ackmessage 🔥 {today}|off",
+    ),
+    (
+        "Usage: /ackmessage 🔥 {today} or /ackmessage Nice!|Keep going|off, up to {} characters",
+        "Verwendung: /ackmessage 🔥 {today} oder /ackmessage Super!|Weiter so|off, bis zu {} Zeichen",
+    ),
+    (
+        "Your /done replies are now: {}",
+        "Deine Antworten auf /done sind jetzt: {}",
+    ),
+    (
+        "Your /done replies are back to the default",
+        "Deine Antworten auf /done sind wieder die Standardantwort",
+    ),
+    (
+        "Log by sending done, +1 or ✅ to the bot in a private chat: /plainlog on|off",
+        "Einträge durch Senden von done, +1 oder ✅ im privaten Chat mit dem Bot: /plainlog on|off",
+    ),
+    ("Usage: /plainlog on|off", "Verwendung: /plainlog on|off"),
+    (
+        "Sending done, +1 or ✅ to me in a private chat now logs like /done",
+        "Wenn du mir im privaten Chat done, +1 oder ✅ sendest, wird jetzt wie mit /done eingetragen",
+    ),
+    ("Only /done logs again", "Nur /done trägt wieder ein"),
+    (
+        "Let your day end after midnight, e.g. /dayend 04:00, or /dayend off",
+        "Deinen Tag nach Mitternacht enden lassen, z. B. /dayend 04:00, oder /dayend off",
+    ),
+    (
+        "Usage: /dayend HH:MM up to 06:00, or /dayend off",
+        "Verwendung: /dayend HH:MM bis 06:00, oder /dayend off",
+    ),
+    (
+        "Your day now ends at {}, earlier logs count for the day before",
+        "Dein Tag endet jetzt um {}, frühere Einträge zählen für den Vortag",
+    ),
+    (
+        "Your day now ends at midnight",
+        "Dein Tag endet jetzt um Mitternacht",
+    ),
+    (
+        "Let others compare their charts with yours: /allowcompare on|off",
+        "Anderen erlauben, ihre Diagramme mit deinen zu vergleichen: /allowcompare on|off",
+    ),
+    (
+        "Hide yourself from leaderboards, streak rankings and comparisons: /privacy on|off",
+        "Dich in Ranglisten, Serien-Ranglisten und Vergleichen ausblenden: /privacy on|off",
+    ),
+    (
+        "Don't announce reached goals, achievements and level-ups: /quiet on|off",
+        "Erreichte Ziele, Abzeichen und Aufstiege nicht ankündigen: /quiet on|off",
+    ),
+    (
+        "Pick the language of the replies: /language en|de|auto",
+        "Die Sprache der Antworten wählen: /language en|de|auto",
+    ),
+    (
+        "View and change your settings",
+        "Deine Einstellungen ansehen und ändern",
+    ),
+    (
+        "Let others copy your settings: /sharesettings on|off",
+        "Anderen erlauben, deine Einstellungen zu übernehmen: /sharesettings on|off",
+    ),
+    (
+        "Copy someone's settings: /copysettings @user",
+        "Die Einstellungen von jemandem übernehmen: /copysettings @user",
+    ),
+    (
+        "Get reminded if you haven't logged by a time: /remind 20:00|off",
+        "Erinnert werden, wenn du bis zu einer Uhrzeit nichts eingetragen hast: /remind 20:00|off",
+    ),
+    (
+        "Get a weekly summary in a private message: /digest on|off",
+        "Eine wöchentliche Zusammenfassung als private Nachricht erhalten: /digest on|off",
+    ),
+    (
+        "Get notified about someone's milestones: /watch @user",
+        "Über die Meilensteine von jemandem benachrichtigt werden: /watch @user",
+    ),
+    (
+        "Stop watching someone's milestones: /unwatch @user",
+        "Die Meilensteine von jemandem nicht mehr verfolgen: /unwatch @user",
+    ),
+    (
+        "Import logs from a CSV file: /importurl https://example.com/logs.csv",
+        "Einträge aus einer CSV-Datei importieren: /importurl https://example.com/logs.csv",
+    ),
+    (
+        "Import logs by replying to a CSV or JSON file with /import",
+        "Einträge importieren, indem du auf eine CSV- oder JSON-Datei mit /import antwortest",
+    ),
+    (
+        "Download all your logs as a CSV file",
+        "Alle deine Einträge als CSV-Datei herunterladen",
+    ),
+    (
+        "Delete all of today's logs",
+        "Alle heutigen Einträge löschen",
+    ),
+    ("Delete all your data", "Alle deine Daten löschen"),
+];
@@ -1,13 +1,78 @@
-use crate::{bot::run_bot, database::Database};
+use futures::future::try_join_all;
+use teloxide::{Bot, dispatching::dialogue::InMemStorage, net::client_from_env};
 
+use crate::{
+    backup::run_backups,
+    bot::{MaintenanceMode, run_bot},
+    cache::ChartCache,
+    config::Config,
+    database::Database,
+    pending::PendingLogs,
+    scheduler::run_scheduler,
+};
+
+mod achievements;
+mod backup;
 mod bot;
+mod cache;
 mod chart;
+mod config;
+mod cooldown;
 mod database;
+mod export;
+mod import;
+mod levels;
+mod locale;
+mod names;
+mod pending;
+mod plain;
+mod scheduler;
+mod snapshot;
+mod stats;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     dotenvy::dotenv()?;
     tracing_subscriber::fmt().init();
+    let config = Config::from_env()?;
     let db = Database::new().await?;
-    run_bot(db).await
+    let client = client_from_env();
+    let bots: Vec<Bot> = config
+        .bot_tokens
+        .iter()
+        .map(|token| Bot::with_client(token, client.clone()))
+        .collect();
+    // Bots share the state tied to users rather than to a bot, since a user can talk to all of them
+    let maintenance = MaintenanceMode::default();
+    let cache = ChartCache::default();
+    let pending = PendingLogs::default();
+    let deletes = InMemStorage::new();
+    // Streak warnings are sent by the first bot only, so nobody gets them twice.
+    tokio::spawn(run_scheduler(
+        bots[0].clone(),
+        db.clone(),
+        config.clone(),
+        maintenance.clone(),
+    ));
+    if let Some(dir) = config.backup_dir.clone() {
+        tokio::spawn(run_backups(
+            db.clone(),
+            dir,
+            config.backup_interval,
+            config.backup_retention,
+        ));
+    }
+    try_join_all(bots.into_iter().map(|bot| {
+        run_bot(
+            bot,
+            db.clone(),
+            config.clone(),
+            maintenance.clone(),
+            cache.clone(),
+            pending.clone(),
+            deletes.clone(),
+        )
+    }))
+    .await?;
+    Ok(())
 }
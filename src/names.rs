@@ -0,0 +1,95 @@
+use teloxide::{
+    types::{User, UserId},
+    utils::html,
+};
+
+use crate::database::{RankedTotal, UserName, UserTotal};
+
+/// How to show a user, from the database or from an update.
+pub struct DisplayName<'a> {
+    telegram_id: i64,
+    username: Option<&'a str>,
+    first_name: Option<&'a str>,
+    last_name: Option<&'a str>,
+}
+
+impl DisplayName<'_> {
+    /// The first and last name, `None` for users who haven't used the bot since names were stored.
+    fn full_name(&self) -> Option<String> {
+        let name = match (self.first_name, self.last_name) {
+            (Some(first), Some(last)) => format!("{first} {last}"),
+            (Some(name), None) | (None, Some(name)) => name.to_string(),
+            (None, None) => return None,
+        };
+        let name = name.trim();
+        (!name.is_empty()).then(|| name.to_string())
+    }
+
+    /// The username without `@`, otherwise the full name, e.g. for chart titles.
+    pub fn title(&self) -> String {
+        self.username
+            .map(str::to_string)
+            .or_else(|| self.full_name())
+            .unwrap_or_else(|| self.telegram_id.to_string())
+    }
+
+    /// `@username`, otherwise the full name.
+    pub fn plain(&self) -> String {
+        match self.username {
+            Some(username) => format!("@{username}"),
+            None => self.title(),
+        }
+    }
+
+    /// Like `plain`, as HTML with the full name linking to the user with a text mention.
+    pub fn html(&self) -> String {
+        match (self.username, self.full_name()) {
+            (None, Some(name)) => html::user_mention(UserId(self.telegram_id as u64), &name),
+            _ => html::escape(&self.plain()),
+        }
+    }
+}
+
+impl<'a> From<&'a User> for DisplayName<'a> {
+    fn from(user: &'a User) -> Self {
+        Self {
+            telegram_id: user.id.0 as i64,
+            username: user.username.as_deref(),
+            first_name: Some(&user.first_name),
+            last_name: user.last_name.as_deref(),
+        }
+    }
+}
+
+impl<'a> From<&'a UserName> for DisplayName<'a> {
+    fn from(name: &'a UserName) -> Self {
+        Self {
+            telegram_id: name.telegram_id,
+            username: name.username.as_deref(),
+            first_name: name.first_name.as_deref(),
+            last_name: name.last_name.as_deref(),
+        }
+    }
+}
+
+impl<'a> From<&'a UserTotal> for DisplayName<'a> {
+    fn from(total: &'a UserTotal) -> Self {
+        Self {
+            telegram_id: total.telegram_id,
+            username: total.username.as_deref(),
+            first_name: total.first_name.as_deref(),
+            last_name: total.last_name.as_deref(),
+        }
+    }
+}
+
+impl<'a> From<&'a RankedTotal> for DisplayName<'a> {
+    fn from(total: &'a RankedTotal) -> Self {
+        Self {
+            telegram_id: total.telegram_id,
+            username: total.username.as_deref(),
+            first_name: total.first_name.as_deref(),
+            last_name: total.last_name.as_deref(),
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// How long a log waits for the user to confirm it.
+const PENDING_LOG_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// A `/done` sent right after another log, kept until the user confirms it.
+pub struct PendingLog {
+    pub chat_id: i64,
+    pub timestamp: i64,
+    pub category: Option<String>,
+    pub note: Option<String>,
+}
+
+/// The unconfirmed log of each user, only the newest one.
+#[derive(Clone, Default)]
+pub struct PendingLogs {
+    logs: Arc<Mutex<HashMap<i64, (PendingLog, Instant)>>>,
+}
+
+impl PendingLogs {
+    pub fn insert(&self, user_id: i64, log: PendingLog) {
+        self.logs
+            .lock()
+            .unwrap()
+            .insert(user_id, (log, Instant::now()));
+    }
+
+    /// Removes the user's log, returning it unless it has expired.
+    pub fn take(&self, user_id: i64) -> Option<PendingLog> {
+        let (log, at) = self.logs.lock().unwrap().remove(&user_id)?;
+        (at.elapsed() < PENDING_LOG_TIMEOUT).then_some(log)
+    }
+
+    /// Forgets logs that were never confirmed.
+    pub fn prune(&self) {
+        self.logs
+            .lock()
+            .unwrap()
+            .retain(|_, (_, at)| at.elapsed() < PENDING_LOG_TIMEOUT);
+    }
+}
@@ -0,0 +1,8 @@
+/// Private messages that log like `/done` for users who turned it on with `/plainlog`.
+const LOG_MESSAGES: [&str; 4] = ["done", "+1", "✅", "✔️"];
+
+/// Whether the text is one of the plain log messages, ignoring case and surrounding spaces.
+pub fn is_log_message(text: &str) -> bool {
+    let text = text.trim().to_lowercase();
+    LOG_MESSAGES.contains(&text.as_str())
+}
@@ -0,0 +1,213 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Days, Timelike, Utc};
+use teloxide::{prelude::*, types::InputFile};
+use tracing::{error, info};
+
+use crate::{
+    bot::MaintenanceMode,
+    chart::{ChartTheme, generate_week_chart, render},
+    config::Config,
+    database::{Database, StreakWarningCandidate},
+    locale::{Language, fill},
+    stats::{
+        dates_from_day_numbers, day_bounds, day_offset, local_midnight, local_monday,
+        streak_ending_on,
+    },
+};
+
+const TICK: Duration = Duration::from_secs(5 * 60);
+
+/// Local hour on Monday from which the weekly digest is sent.
+const DIGEST_HOUR: u32 = 9;
+
+pub async fn run_scheduler(bot: Bot, db: Database, config: Config, maintenance: MaintenanceMode) {
+    let mut interval = tokio::time::interval(TICK);
+    loop {
+        interval.tick().await;
+        // Whatever is due is sent on the first tick after maintenance
+        if maintenance.is_enabled() {
+            continue;
+        }
+        if let Err(err) = send_streak_warnings(&bot, &db, &config).await {
+            error!("Failed to send streak warnings: {err}");
+        }
+        if let Err(err) = send_reminders(&bot, &db).await {
+            error!("Failed to send reminders: {err}");
+        }
+        if let Err(err) = send_weekly_digests(&bot, &db).await {
+            error!("Failed to send weekly digests: {err}");
+        }
+    }
+}
+
+/// Warns users whose streak will be lost at the end of their local day unless they log.
+async fn send_streak_warnings(bot: &Bot, db: &Database, config: &Config) -> anyhow::Result<()> {
+    let now = Utc::now();
+    for user in db.get_streak_warning_candidates().await? {
+        if let Err(err) = send_streak_warning(bot, db, config, &user, now).await {
+            error!(
+                "Failed to check the streak of the user {}: {err}",
+                user.user_id
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Warns the user if their streak is about to end. A failed send counts as warned too,
+/// e.g. a user who blocked the bot isn't retried on every tick.
+async fn send_streak_warning(
+    bot: &Bot,
+    db: &Database,
+    config: &Config,
+    user: &StreakWarningCandidate,
+    now: DateTime<Utc>,
+) -> anyhow::Result<()> {
+    let offset = day_offset(user.utc_offset, user.day_end);
+    let today = now.with_timezone(&offset).date_naive();
+    let today_str = today.to_string();
+    if user.streak_warned_on.as_deref() == Some(today_str.as_str()) {
+        return Ok(());
+    }
+
+    let (_, end_of_day) = day_bounds(today, offset);
+    let seconds_left = end_of_day - now.timestamp();
+    if seconds_left > i64::from(config.streak_warning_hours) * 3600 {
+        return Ok(());
+    }
+
+    let dates = dates_from_day_numbers(&db.get_active_days(user.user_id).await?);
+    if dates.contains(&today) {
+        return Ok(());
+    }
+    let Some(yesterday) = today.pred_opt() else {
+        return Ok(());
+    };
+    let streak = streak_ending_on(&dates, yesterday);
+    if streak < config.streak_warning_threshold {
+        return Ok(());
+    }
+
+    let hours_left = (seconds_left + 3599) / 3600;
+    let lang = Language::for_user(user.language.as_deref(), None);
+    let template = if hours_left == 1 {
+        "Your {}-day streak ends in {} hour! /done"
+    } else {
+        "Your {}-day streak ends in {} hours! /done"
+    };
+    let text = fill(lang.translate(template), &[&streak, &hours_left]);
+    match bot.send_message(ChatId(user.telegram_id), text).await {
+        Ok(_) => info!("Sent a streak warning to the user {}", user.user_id),
+        Err(err) => error!(
+            "Failed to send a streak warning to {}: {err}",
+            user.telegram_id
+        ),
+    }
+    db.set_streak_warned_on(user.user_id, &today_str).await
+}
+
+/// Reminds users who haven't logged today once their local reminder time has passed.
+async fn send_reminders(bot: &Bot, db: &Database) -> anyhow::Result<()> {
+    let now = Utc::now();
+    for reminder in db.get_reminders().await? {
+        let offset = day_offset(reminder.utc_offset, reminder.day_end);
+        let local_now = now.with_timezone(&offset);
+        let today = local_now.date_naive();
+        let today_str = today.to_string();
+        // The reminder is set on the clock, the user's day starts at `day_end` on it
+        let due = (reminder.minute - reminder.day_end).rem_euclid(24 * 60);
+        if reminder.reminded_on.as_deref() == Some(today_str.as_str())
+            || i64::from(local_now.num_seconds_from_midnight() / 60) < due
+        {
+            continue;
+        }
+
+        let (start, end) = day_bounds(today, offset);
+        if db.get_count_between(reminder.user_id, start, end).await? == 0 {
+            let lang = Language::for_user(reminder.language.as_deref(), None);
+            let text = lang.translate("You haven't logged anything today yet. /done");
+            if let Err(err) = bot.send_message(ChatId(reminder.telegram_id), text).await {
+                error!(
+                    "Failed to send a reminder to {}: {err}",
+                    reminder.telegram_id
+                );
+                continue;
+            }
+            info!("Sent a reminder to the user {}", reminder.user_id);
+        }
+        db.set_reminded_on(reminder.user_id, &today_str).await?;
+    }
+    Ok(())
+}
+
+/// Sends subscribers a summary of the previous (local) week, once per week.
+async fn send_weekly_digests(bot: &Bot, db: &Database) -> anyhow::Result<()> {
+    let now = Utc::now();
+    for sub in db.get_digest_subscribers().await? {
+        let offset = day_offset(sub.utc_offset, sub.day_end);
+        let local_now = now.with_timezone(&offset);
+        let today = local_now.date_naive();
+        let monday = local_monday(now, offset);
+        let week = monday.to_string();
+        if sub.sent_for_week == week || (today == monday && local_now.hour() < DIGEST_HOUR) {
+            continue;
+        }
+
+        let this_week = local_midnight(monday, offset);
+        let last_week = this_week - 7 * 24 * 60 * 60;
+        let timestamps = db
+            .get_user_timestamps_between(sub.user_id, last_week, this_week)
+            .await?;
+        let previous = db
+            .get_count_between(sub.user_id, last_week - 7 * 24 * 60 * 60, last_week)
+            .await?;
+        let total = timestamps.len() as i64;
+        let lang = Language::for_user(sub.language.as_deref(), None);
+        let text = if previous == 0 {
+            fill(lang.translate("Your week: {} logs"), &[&total])
+        } else {
+            fill(
+                lang.translate("Your week: {} logs, {} compared to the week before"),
+                &[&total, &format!("{:+}", total - previous)],
+            )
+        };
+        let mut per_day = [0; 7];
+        for ts in timestamps {
+            per_day[((ts - last_week) / (24 * 60 * 60)).clamp(0, 6) as usize] += 1;
+        }
+
+        let chat_id = ChatId(sub.telegram_id);
+        let caption = fill(lang.translate("Week of {}"), &[&(monday - Days::new(7))]);
+        let theme = ChartTheme::for_user(sub.chart_theme.as_deref());
+        let language = sub.language.clone();
+        let sent = match render(move || {
+            generate_week_chart(&caption, per_day, language.as_deref(), theme)
+        })
+        .await
+        {
+            Ok(png_bytes) => bot
+                .send_photo(chat_id, InputFile::memory(png_bytes))
+                .caption(text)
+                .await
+                .map(|_| ()),
+            Err(err) => {
+                error!(
+                    "Failed to generate the digest chart for {}: {err}",
+                    sub.user_id
+                );
+                bot.send_message(chat_id, text).await.map(|_| ())
+            }
+        };
+        if let Err(err) = sent {
+            error!(
+                "Failed to send the weekly digest to {}: {err}",
+                sub.telegram_id
+            );
+            continue;
+        }
+        db.set_digest_sent_for_week(sub.user_id, &week).await?;
+        info!("Sent the weekly digest to the user {}", sub.user_id);
+    }
+    Ok(())
+}
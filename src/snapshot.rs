@@ -0,0 +1,67 @@
+use chrono::Utc;
+use serde::Serialize;
+
+use crate::database::{Database, RankedTotal, UserTotal};
+
+/// Maximum number of users included in `Snapshot::users`.
+const MAX_USERS: i64 = 1000;
+
+/// A machine-readable snapshot of the aggregated stats, e.g. for dashboards.
+#[derive(Serialize)]
+pub struct Snapshot {
+    /// UTC timestamp of when the snapshot was taken.
+    pub generated_at: i64,
+    pub total_users: i64,
+    pub total_logs: i64,
+    /// Per-user totals, highest first, capped at 1000 users.
+    pub users: Vec<UserEntry>,
+    /// The top 10 shown by /leaderboard in chats without a daily cap.
+    pub leaderboard: Vec<UserEntry>,
+}
+
+#[derive(Serialize)]
+pub struct UserEntry {
+    pub telegram_id: i64,
+    /// The last known username, `null` if the user has none.
+    pub username: Option<String>,
+    pub logs: i64,
+}
+
+impl From<UserTotal> for UserEntry {
+    fn from(total: UserTotal) -> Self {
+        Self {
+            telegram_id: total.telegram_id,
+            username: total.username,
+            logs: total.logs,
+        }
+    }
+}
+
+impl From<RankedTotal> for UserEntry {
+    fn from(total: RankedTotal) -> Self {
+        Self {
+            telegram_id: total.telegram_id,
+            username: total.username,
+            logs: total.logs,
+        }
+    }
+}
+
+pub async fn build_snapshot(db: &Database, daily_cap: Option<i64>) -> anyhow::Result<Snapshot> {
+    let (total_users, total_logs) = db.get_global_counts().await?;
+    let totals = db.get_user_totals(MAX_USERS, None).await?;
+    let leaderboard = db
+        .get_leaderboard(daily_cap, None, None, None, None, None)
+        .await?
+        .into_iter()
+        .map(UserEntry::from)
+        .collect();
+    let users = totals.into_iter().map(UserEntry::from).collect();
+    Ok(Snapshot {
+        generated_at: Utc::now().timestamp(),
+        total_users,
+        total_logs,
+        users,
+        leaderboard,
+    })
+}
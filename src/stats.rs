@@ -0,0 +1,235 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use chrono::{DateTime, Datelike, Days, FixedOffset, Months, NaiveDate, NaiveTime, Utc};
+
+/// Days from 0001-01-01 to 1970-01-01, as counted by `NaiveDate::from_num_days_from_ce_opt`.
+const UNIX_EPOCH_DAYS_FROM_CE: i64 = 719_163;
+
+/// Converts an offset in minutes into a `FixedOffset`, falling back to UTC if it is out of range.
+pub fn user_offset(utc_offset: i64) -> FixedOffset {
+    FixedOffset::east_opt((utc_offset * 60) as i32).unwrap_or(FixedOffset::east_opt(0).unwrap())
+}
+
+/// The offset whose midnight is the end of the user's day, `day_end` minutes after the local one.
+/// Days of logs are taken in it, so that e.g. a log at 1am can count for the day before.
+pub fn day_offset(utc_offset: i64, day_end: i64) -> FixedOffset {
+    user_offset(utc_offset - day_end)
+}
+
+/// Returns the set of local dates on which at least one log happened.
+pub fn local_dates(timestamps: &[i64], offset: FixedOffset) -> BTreeSet<NaiveDate> {
+    timestamps
+        .iter()
+        .filter_map(|&ts| DateTime::from_timestamp(ts, 0))
+        .map(|dt| dt.with_timezone(&offset).date_naive())
+        .collect()
+}
+
+/// Converts local day numbers, counted from 1970-01-01, into dates.
+pub fn dates_from_day_numbers(days: &[i64]) -> BTreeSet<NaiveDate> {
+    days.iter()
+        .filter_map(|&day| {
+            NaiveDate::from_num_days_from_ce_opt((day + UNIX_EPOCH_DAYS_FROM_CE) as i32)
+        })
+        .collect()
+}
+
+/// Returns the local day with the most logs and their number, the earliest on ties.
+pub fn best_day(timestamps: &[i64], offset: FixedOffset) -> Option<(NaiveDate, usize)> {
+    let mut counts: BTreeMap<NaiveDate, usize> = BTreeMap::new();
+    for dt in timestamps
+        .iter()
+        .filter_map(|&ts| DateTime::from_timestamp(ts, 0))
+    {
+        *counts
+            .entry(dt.with_timezone(&offset).date_naive())
+            .or_default() += 1;
+    }
+    counts.into_iter().rev().max_by_key(|&(_, n)| n)
+}
+
+/// Counts consecutive days with logs, going back from `last_day` inclusive.
+pub fn streak_ending_on(dates: &BTreeSet<NaiveDate>, last_day: NaiveDate) -> u32 {
+    let mut streak = 0;
+    let mut day = last_day;
+    while dates.contains(&day) {
+        streak += 1;
+        match day.pred_opt() {
+            Some(prev) => day = prev,
+            None => break,
+        }
+    }
+    streak
+}
+
+/// Returns the streak that is still alive on `today`, which includes yesterday's
+/// streak if nothing was logged today yet.
+pub fn current_streak(dates: &BTreeSet<NaiveDate>, today: NaiveDate) -> u32 {
+    match today.pred_opt() {
+        Some(yesterday) if !dates.contains(&today) => streak_ending_on(dates, yesterday),
+        _ => streak_ending_on(dates, today),
+    }
+}
+
+/// Returns the longest run of consecutive days with logs.
+pub fn longest_streak(dates: &BTreeSet<NaiveDate>) -> u32 {
+    let mut best = 0;
+    let mut streak = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for &day in dates {
+        streak = match previous.and_then(|p| p.succ_opt()) {
+            Some(next) if next == day => streak + 1,
+            _ => 1,
+        };
+        best = best.max(streak);
+        previous = Some(day);
+    }
+    best
+}
+
+/// Returns the streaks still alive per user, longest first,
+/// given `(user_id, utc_offset, local day number)` rows.
+pub fn current_streaks(days: &[(i64, i64, i64)], now: DateTime<Utc>) -> Vec<(i64, u32)> {
+    let mut by_user: HashMap<i64, (i64, BTreeSet<NaiveDate>)> = HashMap::new();
+    for &(user_id, utc_offset, day) in days {
+        let date = NaiveDate::from_num_days_from_ce_opt((day + UNIX_EPOCH_DAYS_FROM_CE) as i32);
+        let entry = by_user
+            .entry(user_id)
+            .or_insert((utc_offset, BTreeSet::new()));
+        entry.1.extend(date);
+    }
+    let mut streaks: Vec<(i64, u32)> = by_user
+        .into_iter()
+        .map(|(user_id, (utc_offset, dates))| {
+            let today = now.with_timezone(&user_offset(utc_offset)).date_naive();
+            (user_id, current_streak(&dates, today))
+        })
+        .filter(|&(_, streak)| streak > 0)
+        .collect();
+    streaks.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    streaks
+}
+
+/// Returns the longest streak still alive among all users,
+/// given `(user_id, utc_offset, local day number)` rows.
+pub fn top_current_streak(days: &[(i64, i64, i64)], now: DateTime<Utc>) -> u32 {
+    current_streaks(days, now)
+        .first()
+        .map_or(0, |&(_, streak)| streak)
+}
+
+/// Returns the UTC timestamp of the local midnight starting `day`.
+pub fn local_midnight(day: NaiveDate, offset: FixedOffset) -> i64 {
+    day.and_time(NaiveTime::MIN).and_utc().timestamp() - i64::from(offset.local_minus_utc())
+}
+
+/// Returns the `[start, end)` UTC timestamps of the local `day`.
+pub fn day_bounds(day: NaiveDate, offset: FixedOffset) -> (i64, i64) {
+    let start = local_midnight(day, offset);
+    (start, start + 24 * 60 * 60)
+}
+
+/// Returns the `[start, end)` UTC timestamps of the current calendar month in UTC.
+pub fn utc_month_bounds(now: DateTime<Utc>) -> (i64, i64) {
+    let first = now.date_naive().with_day(1).unwrap_or(now.date_naive());
+    let next = first.checked_add_months(Months::new(1)).unwrap_or(first);
+    (
+        first.and_time(NaiveTime::MIN).and_utc().timestamp(),
+        next.and_time(NaiveTime::MIN).and_utc().timestamp(),
+    )
+}
+
+/// Returns the local date of the most recent Monday.
+pub fn local_monday(now: DateTime<Utc>, offset: FixedOffset) -> NaiveDate {
+    let today = now.with_timezone(&offset).date_naive();
+    today - Days::new(today.weekday().num_days_from_monday().into())
+}
+
+/// Returns the UTC timestamp of the most recent local Monday midnight.
+pub fn week_start(now: DateTime<Utc>, offset: FixedOffset) -> i64 {
+    local_midnight(local_monday(now, offset), offset)
+}
+
+/// Returns the `[start, end)` UTC timestamps of `months` local months starting on `first`.
+pub fn months_bounds(first: NaiveDate, months: u32, offset: FixedOffset) -> (i64, i64) {
+    let next = first
+        .checked_add_months(Months::new(months))
+        .unwrap_or(first);
+    (local_midnight(first, offset), local_midnight(next, offset))
+}
+
+/// Returns the UTC timestamp of the local midnight starting the current month.
+pub fn month_start(now: DateTime<Utc>, offset: FixedOffset) -> i64 {
+    let today = now.with_timezone(&offset).date_naive();
+    local_midnight(today.with_day(1).unwrap_or(today), offset)
+}
+
+/// Returns the `p`-th percentile of ascending `values` by the nearest-rank method.
+pub fn percentile(values: &[i64], p: u32) -> Option<i64> {
+    let rank = (values.len() * p as usize).div_ceil(100).max(1);
+    values.get(rank - 1).copied()
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ActivityTrend {
+    Improving,
+    Steady,
+    Slipping,
+}
+
+/// Compares the number of logs in the recent window with the one before it.
+/// A change of less than 20% either way counts as steady.
+pub fn classify_trend(recent: i64, previous: i64) -> ActivityTrend {
+    if recent * 5 > previous * 6 {
+        ActivityTrend::Improving
+    } else if recent * 5 < previous * 4 {
+        ActivityTrend::Slipping
+    } else {
+        ActivityTrend::Steady
+    }
+}
+
+/// Whether reaching `total` logs is worth celebrating: 10, 50, 100, 250, 500
+/// and every multiple of 1000.
+pub fn is_milestone(total: i64) -> bool {
+    matches!(total, 10 | 50 | 100 | 250 | 500) || (total > 0 && total % 1000 == 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trend_by_change_between_windows() {
+        let cases = [
+            // No history at all is neither better nor worse.
+            (0, 0, ActivityTrend::Steady),
+            (3, 0, ActivityTrend::Improving),
+            (0, 3, ActivityTrend::Slipping),
+            (13, 10, ActivityTrend::Improving),
+            (12, 10, ActivityTrend::Steady),
+            (10, 10, ActivityTrend::Steady),
+            (8, 10, ActivityTrend::Steady),
+            (7, 10, ActivityTrend::Slipping),
+        ];
+        for (recent, previous, expected) in cases {
+            assert_eq!(
+                classify_trend(recent, previous),
+                expected,
+                "{recent} after {previous}"
+            );
+        }
+    }
+
+    #[test]
+    fn months_bounds_span_local_months() {
+        let offset = user_offset(120);
+        let first = NaiveDate::from_ymd_opt(2024, 12, 1).unwrap();
+        assert_eq!(
+            months_bounds(first, 1, offset),
+            (1_733_004_000, 1_735_682_400)
+        );
+        let jan = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        assert_eq!(months_bounds(jan, 12, offset).1, 1_735_682_400);
+    }
+}